@@ -1,18 +1,33 @@
-use crate::editor::Document;
+use crate::bookmarks::{self, BookmarkStore};
+use crate::config::Config;
+use crate::editor::{
+    DiskChange, Document, Editorconfig, FileLoad, HexView, IndentStyle, LineEnding, SaveActions,
+    SelectionMode, TextCase,
+};
 use crate::file_tree::FileTree;
+use crate::file_watcher::{FileEvent, FileWatcher};
 use crate::highlighting::HighlightingManager;
-use crate::input::InputHandler;
+use crate::input::{AppEvent, InputHandler};
+use crate::macros::{self, Macros, Repeat};
+use crate::recovery::{self, SwapFile, SwapWriter};
 use crate::search::SearchState;
 use crate::terminal::Terminal;
 use crate::theme::Theme;
-use crate::ui::dialog::{AboutDialog, Dialog, FileOpenDialog, FileSaveAsDialog, GoToLineDialog};
+use crate::ui::dialog::{
+    AboutDialog, Dialog, EncodingDialog, FileChangedDialog, FileOpenDialog, FileSaveAsDialog,
+    GoToLineDialog, IndentDialog, MessageDialog, PlayMacroDialog, RecoveryDialog, SaveMacroDialog,
+    INDENT_OPTIONS,
+};
 use crate::ui::{self, Pane};
 use crate::utils::clipboard::Clipboard;
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use ratatui::prelude::*;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Pause in input after which fold regions are brought up to date
+const FOLD_UPDATE_DELAY: Duration = Duration::from_millis(300);
 
 /// The main application state
 pub struct App {
@@ -46,12 +61,20 @@ pub struct App {
     pub documents: Vec<Document>,
     /// Currently active document index
     pub active_doc: usize,
+    /// Large files still loading in the background
+    pub loads: Vec<FileLoad>,
     /// Last known editor area for mouse hit detection
     pub editor_area: Option<Rect>,
+    /// Indentation indicator in the status bar, for click detection
+    pub indent_indicator: Option<Rect>,
     /// Currently open menu (None = menu bar closed)
     pub menu_open: Option<usize>,
     /// Currently selected menu item within open menu
     pub menu_selected: Option<usize>,
+    /// Selected item of the selected item's submenu (None = submenu closed)
+    pub submenu_selected: Option<usize>,
+    /// Where the open submenu was drawn, for click detection
+    pub submenu_area: Option<Rect>,
     /// Menu positions for click detection (start_x, end_x, menu_index)
     pub menu_positions: Vec<(u16, u16, usize)>,
     /// Active dialog (if any)
@@ -68,6 +91,22 @@ pub struct App {
     pub highlighting: HighlightingManager,
     /// Search state
     pub search: SearchState,
+    /// User configuration
+    pub config: Config,
+    /// Swap files for crash recovery
+    pub swap: SwapWriter,
+    /// Time of the last key press or mouse event
+    pub last_input: Instant,
+    /// Whether the idle auto-save already ran since the last input
+    pub idle_saved: bool,
+    /// Watches open files for changes by other programs
+    pub watcher: FileWatcher,
+    /// Open files reported changed on disk, waiting to be checked
+    pub changed_files: Vec<PathBuf>,
+    /// Bookmarks of files, kept between sessions
+    pub bookmark_store: BookmarkStore,
+    /// Keyboard macros: the one being recorded, the last one and saved ones
+    pub macros: Macros,
 }
 
 /// Which divider is being resized
@@ -84,10 +123,7 @@ impl App {
         let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
         let file_tree = FileTree::new(cwd.clone(), false);
 
-        // Start with one empty document
-        let documents = vec![Document::new()];
-
-        Self {
+        let mut app = Self {
             should_quit: false,
             focused_pane: Pane::Editor,
             show_sidebar: true,
@@ -101,11 +137,15 @@ impl App {
             cwd,
             file_tree,
             file_tree_area: None,
-            documents,
+            documents: Vec::new(),
             active_doc: 0,
+            loads: Vec::new(),
             editor_area: None,
+            indent_indicator: None,
             menu_open: None,
             menu_selected: None,
+            submenu_selected: None,
+            submenu_area: None,
             menu_positions: Vec::new(),
             dialog: None,
             terminals: Terminal::new(80, 24).map(|t| vec![t]).unwrap_or_default(),
@@ -114,7 +154,28 @@ impl App {
             clipboard: Clipboard::new(),
             highlighting: HighlightingManager::new(),
             search: SearchState::new(),
+            config: Config::load(),
+            swap: SwapWriter::new(None, 0),
+            last_input: Instant::now(),
+            idle_saved: false,
+            watcher: FileWatcher::new(),
+            changed_files: Vec::new(),
+            bookmark_store: BookmarkStore::load(bookmarks::bookmarks_path()),
+            macros: Macros::load(macros::macros_path()),
+        };
+        app.swap = SwapWriter::new(recovery::swap_dir(), app.config.editor.swap_interval_secs);
+
+        // Start with one empty document
+        app.new_file();
+
+        // Offer unsaved changes left behind by a crashed session
+        if let Some(dir) = recovery::swap_dir() {
+            let orphans = SwapFile::find_orphans(&dir);
+            if !orphans.is_empty() {
+                app.dialog = Some(Dialog::Recovery(RecoveryDialog::new(orphans)));
+            }
         }
+        app
     }
 
     /// Get the active terminal
@@ -225,6 +286,86 @@ impl App {
         self.dialog = Some(Dialog::GoToLine(GoToLineDialog::new(total_lines)));
     }
 
+    /// Bring the active document's fold regions up to date (not for large
+    /// files, where the scan is too slow); done for fold commands and after a
+    /// pause in typing rather than on every edit
+    pub fn update_folds(&mut self) {
+        if self
+            .active_document()
+            .is_some_and(|doc| doc.folds_outdated() && !doc.large_file)
+        {
+            // Bracket regions need the strings and comments of the whole file
+            self.update_literals(usize::MAX);
+            if let Some(doc) = self.active_document_mut() {
+                doc.update_folds();
+            }
+        }
+    }
+
+    /// Scan the active document's strings and comments through line `through`
+    /// (see `Document::scan_literals`)
+    pub fn update_literals(&mut self, through: usize) {
+        if let Some(doc) = self.documents.get_mut(self.active_doc) {
+            doc.scan_literals(&mut self.highlighting, through);
+        }
+    }
+
+    /// Open the indentation dialog for the active document
+    pub fn show_indent_dialog(&mut self) {
+        if let Some(doc) = self.active_document().filter(|doc| doc.hex.is_none()) {
+            let dialog = IndentDialog::new(doc.indent_style, doc.indent_width);
+            self.dialog = Some(Dialog::Indent(dialog));
+        }
+    }
+
+    /// Open the encoding dialog, to save with or reopen with another encoding
+    pub fn show_encoding_dialog(&mut self, reopen: bool) {
+        if let Some(doc) = self.active_document().filter(|doc| doc.hex.is_none()) {
+            let dialog = EncodingDialog::new(&doc.encoding, reopen);
+            self.dialog = Some(Dialog::Encoding(dialog));
+        }
+    }
+
+    /// Save the active document with the given line ending from now on
+    fn convert_line_endings(&mut self, line_ending: LineEnding) {
+        if let Some(doc) = self.active_document_mut().filter(|doc| doc.hex.is_none()) {
+            doc.convert_line_endings(line_ending);
+        }
+    }
+
+    /// Show the active document's file as bytes in the hex view
+    fn reopen_as_hex(&mut self) {
+        let Some(doc) = self.active_document() else {
+            return;
+        };
+        let Some(path) = doc.path.clone() else {
+            return;
+        };
+        if doc.hex.is_some() {
+            return;
+        }
+        let message = if doc.modified {
+            format!(
+                "Save or undo the changes to {} before reopening it",
+                doc.title()
+            )
+        } else {
+            match Document::open_hex(path) {
+                Ok(hex) => {
+                    self.bookmark_store
+                        .remember(&self.documents[self.active_doc]);
+                    self.documents[self.active_doc] = self.configure_document(hex);
+                    return;
+                }
+                Err(e) => format!("Could not read {}: {}", doc.title(), e),
+            }
+        };
+        self.dialog = Some(Dialog::Message(MessageDialog {
+            title: "Reopen Failed".to_string(),
+            message,
+        }));
+    }
+
     /// Check if a dialog is open
     pub fn has_dialog(&self) -> bool {
         self.dialog.is_some()
@@ -234,20 +375,21 @@ impl App {
     pub fn close_menu(&mut self) {
         self.menu_open = None;
         self.menu_selected = None;
+        self.submenu_selected = None;
     }
 
     /// Open a specific menu
     pub fn open_menu(&mut self, menu_idx: usize) {
         self.menu_open = Some(menu_idx);
         self.menu_selected = Some(0);
+        self.submenu_selected = None;
     }
 
     /// Move to the next menu
     pub fn next_menu(&mut self) {
         if let Some(idx) = self.menu_open {
             let next = (idx + 1) % crate::ui::menu_bar::MENUS.len();
-            self.menu_open = Some(next);
-            self.menu_selected = Some(0);
+            self.open_menu(next);
         }
     }
 
@@ -259,58 +401,75 @@ impl App {
             } else {
                 idx - 1
             };
-            self.menu_open = Some(prev);
-            self.menu_selected = Some(0);
+            self.open_menu(prev);
         }
     }
 
-    /// Move selection down in the menu
-    pub fn menu_select_next(&mut self) {
-        if let (Some(menu_idx), Some(sel)) = (self.menu_open, self.menu_selected) {
-            if let Some((_, items)) = crate::ui::menu_bar::MENUS.get(menu_idx) {
-                let mut next = sel + 1;
-                // Skip separators and wrap
-                while next < items.len() {
-                    if items[next].action != crate::ui::menu_bar::MenuAction::Separator {
-                        break;
-                    }
-                    next += 1;
-                }
-                if next >= items.len() {
-                    next = 0;
-                    // Skip initial separators
-                    while next < items.len()
-                        && items[next].action == crate::ui::menu_bar::MenuAction::Separator
-                    {
-                        next += 1;
-                    }
-                }
-                self.menu_selected = Some(next);
-            }
+    /// The selected dropdown item's index and its submenu, if that is open
+    pub fn open_submenu(&self) -> Option<(usize, &'static [crate::ui::menu_bar::MenuItem])> {
+        use crate::ui::menu_bar::{MenuAction, MENUS, SUBMENUS};
+
+        self.submenu_selected?;
+        let selected = self.menu_selected?;
+        let (_, items) = MENUS.get(self.menu_open?)?;
+        match items.get(selected)?.action {
+            MenuAction::Submenu(i) => Some((selected, SUBMENUS.get(i)?)),
+            _ => None,
         }
     }
 
-    /// Move selection up in the menu
+    /// Whether the selected dropdown item opens a submenu
+    pub fn menu_item_has_submenu(&self) -> bool {
+        use crate::ui::menu_bar::{MenuAction, MENUS};
+
+        let item = self
+            .menu_open
+            .zip(self.menu_selected)
+            .and_then(|(menu, sel)| MENUS.get(menu)?.1.get(sel));
+        item.is_some_and(|item| matches!(item.action, MenuAction::Submenu(_)))
+    }
+
+    /// Move selection down in the menu (or the open submenu)
+    pub fn menu_select_next(&mut self) {
+        self.menu_step(true);
+    }
+
+    /// Move selection up in the menu (or the open submenu)
     pub fn menu_select_prev(&mut self) {
-        if let (Some(menu_idx), Some(sel)) = (self.menu_open, self.menu_selected) {
-            if let Some((_, items)) = crate::ui::menu_bar::MENUS.get(menu_idx) {
-                let mut prev = if sel == 0 { items.len() - 1 } else { sel - 1 };
-                // Skip separators
-                while prev > 0 && items[prev].action == crate::ui::menu_bar::MenuAction::Separator {
-                    prev -= 1;
-                }
-                // If we hit a separator at 0, go to end
-                if items[prev].action == crate::ui::menu_bar::MenuAction::Separator {
-                    prev = items.len() - 1;
-                    while prev > 0
-                        && items[prev].action == crate::ui::menu_bar::MenuAction::Separator
-                    {
-                        prev -= 1;
-                    }
-                }
-                self.menu_selected = Some(prev);
+        self.menu_step(false);
+    }
+
+    /// Move the menu selection one item, skipping separators and wrapping
+    fn menu_step(&mut self, forward: bool) {
+        use crate::ui::menu_bar::{MenuAction, MENUS};
+
+        let items = match self.open_submenu() {
+            Some((_, submenu)) => submenu,
+            None => match self.menu_open.and_then(|i| MENUS.get(i)) {
+                Some((_, items)) => *items,
+                None => return,
+            },
+        };
+        let slot = if self.submenu_selected.is_some() {
+            &mut self.submenu_selected
+        } else {
+            &mut self.menu_selected
+        };
+        let Some(mut sel) = *slot else {
+            return;
+        };
+        let len = items.len();
+        for _ in 0..len {
+            sel = if forward {
+                (sel + 1) % len
+            } else {
+                (sel + len - 1) % len
+            };
+            if items[sel].action != MenuAction::Separator {
+                break;
             }
         }
+        *slot = Some(sel);
     }
 
     /// Execute the currently selected menu action
@@ -319,8 +478,17 @@ impl App {
 
         if let (Some(menu_idx), Some(sel)) = (self.menu_open, self.menu_selected) {
             if let Some((_, items)) = crate::ui::menu_bar::MENUS.get(menu_idx) {
-                if let Some(item) = items.get(sel) {
+                // The open submenu's selection, else the dropdown's
+                let item = match self.open_submenu() {
+                    Some((_, submenu)) => self.submenu_selected.and_then(|i| submenu.get(i)),
+                    None => items.get(sel),
+                };
+                if let Some(item) = item {
                     let action = item.action;
+                    if let MenuAction::Submenu(_) = action {
+                        self.submenu_selected = Some(0);
+                        return;
+                    }
                     self.close_menu();
 
                     match action {
@@ -329,68 +497,76 @@ impl App {
                             self.show_open_dialog();
                         }
                         MenuAction::Save => {
-                            if let Some(doc) = self.active_document_mut() {
-                                if doc.path.is_some() {
-                                    let _ = doc.save();
-                                }
+                            if self.active_document().is_some_and(|d| d.path.is_some()) {
+                                self.save_document(self.active_doc);
                             }
                         }
                         MenuAction::SaveAs => {
                             self.show_save_as_dialog();
                         }
                         MenuAction::SaveAll => {
-                            for doc in &mut self.documents {
+                            for i in 0..self.documents.len() {
+                                let doc = &self.documents[i];
                                 if doc.path.is_some() && doc.modified {
-                                    let _ = doc.save();
+                                    self.save_document(i);
                                 }
                             }
                         }
                         MenuAction::Close => self.close_current(),
                         MenuAction::CloseAll => {
+                            for doc in &self.documents {
+                                self.bookmark_store.remember(doc);
+                            }
                             self.documents.clear();
-                            self.documents.push(Document::new());
-                            self.active_doc = 0;
+                            self.new_file();
                         }
                         MenuAction::Quit => self.should_quit = true,
 
-                        MenuAction::Undo => {
-                            // TODO: Implement undo
+                        MenuAction::Undo => self.handle_app_event(AppEvent::Undo),
+                        MenuAction::Redo => self.handle_app_event(AppEvent::Redo),
+                        MenuAction::Cut => self.handle_app_event(AppEvent::Cut),
+                        MenuAction::Copy => self.handle_app_event(AppEvent::Copy),
+                        MenuAction::Paste => self.handle_app_event(AppEvent::Paste),
+                        MenuAction::SelectAll => {
+                            if let Some(doc) = self.active_document_mut() {
+                                doc.select_all();
+                            }
                         }
-                        MenuAction::Redo => {
-                            // TODO: Implement redo
+                        MenuAction::AddCursorAbove => {
+                            self.handle_app_event(AppEvent::AddCursorAbove)
                         }
-                        MenuAction::Cut => {
-                            // Get selected text first
-                            let text = self.active_document().map(|doc| doc.selected_text());
-                            if let Some(text) = text {
-                                if !text.is_empty() {
-                                    let _ = self.clipboard.set_text(&text);
-                                    if let Some(doc) = self.active_document_mut() {
-                                        doc.delete_selection();
-                                    }
-                                }
-                            }
+                        MenuAction::AddCursorBelow => {
+                            self.handle_app_event(AppEvent::AddCursorBelow)
                         }
-                        MenuAction::Copy => {
-                            if let Some(doc) = self.active_document() {
-                                let text = doc.selected_text();
-                                if !text.is_empty() {
-                                    let _ = self.clipboard.set_text(&text);
-                                }
-                            }
+                        MenuAction::AddNextOccurrence => {
+                            self.handle_app_event(AppEvent::AddNextOccurrence)
                         }
-                        MenuAction::Paste => {
-                            if let Ok(text) = self.clipboard.get_text() {
-                                if let Some(doc) = self.active_document_mut() {
-                                    doc.insert_str(&text);
-                                }
-                            }
+                        MenuAction::ToggleLineComment => {
+                            self.handle_app_event(AppEvent::ToggleLineComment)
                         }
-                        MenuAction::SelectAll => {
-                            if let Some(doc) = self.active_document_mut() {
-                                doc.select_all();
-                            }
+                        MenuAction::ToggleBlockComment => {
+                            self.handle_app_event(AppEvent::ToggleBlockComment)
+                        }
+                        MenuAction::IndentLines => self.handle_app_event(AppEvent::IndentLines),
+                        MenuAction::SortLines => self.handle_app_event(AppEvent::SortLines),
+                        MenuAction::RemoveDuplicateLines => {
+                            self.handle_app_event(AppEvent::RemoveDuplicateLines)
                         }
+                        MenuAction::ReverseLines => self.handle_app_event(AppEvent::ReverseLines),
+                        MenuAction::JoinLines => self.handle_app_event(AppEvent::JoinLines),
+                        MenuAction::ChangeCase(case) => {
+                            self.handle_app_event(AppEvent::ChangeCase(case))
+                        }
+                        MenuAction::TransposeChars => {
+                            self.handle_app_event(AppEvent::TransposeChars)
+                        }
+                        MenuAction::ToggleMacroRecording => {
+                            self.handle_app_event(AppEvent::ToggleMacroRecording)
+                        }
+                        MenuAction::PlayLastMacro => self.handle_app_event(AppEvent::PlayLastMacro),
+                        MenuAction::PlayMacro => self.handle_app_event(AppEvent::PlayMacro),
+                        MenuAction::SaveMacro => self.handle_app_event(AppEvent::SaveMacro),
+                        MenuAction::UnindentLines => self.handle_app_event(AppEvent::UnindentLines),
 
                         MenuAction::Find => {
                             self.search.open();
@@ -409,6 +585,17 @@ impl App {
                         MenuAction::GoToLine => {
                             self.show_go_to_line_dialog();
                         }
+                        MenuAction::GoToMatchingBrace => {
+                            self.handle_app_event(AppEvent::GoToMatchingBrace)
+                        }
+                        MenuAction::SelectToMatchingBrace => {
+                            self.handle_app_event(AppEvent::SelectToMatchingBrace)
+                        }
+                        MenuAction::ToggleBookmark => {
+                            self.handle_app_event(AppEvent::ToggleBookmark)
+                        }
+                        MenuAction::NextBookmark => self.handle_app_event(AppEvent::NextBookmark),
+                        MenuAction::PrevBookmark => self.handle_app_event(AppEvent::PrevBookmark),
 
                         MenuAction::ToggleSidebar => {
                             self.show_sidebar = !self.show_sidebar;
@@ -419,6 +606,13 @@ impl App {
                         MenuAction::ToggleTerminal => {
                             self.show_terminal = !self.show_terminal;
                         }
+                        MenuAction::ToggleWordWrap => {
+                            self.handle_app_event(AppEvent::ToggleWordWrap)
+                        }
+                        MenuAction::Fold => self.handle_app_event(AppEvent::Fold),
+                        MenuAction::Unfold => self.handle_app_event(AppEvent::Unfold),
+                        MenuAction::FoldAll => self.handle_app_event(AppEvent::FoldAll),
+                        MenuAction::UnfoldAll => self.handle_app_event(AppEvent::UnfoldAll),
                         MenuAction::FocusEditor => {
                             self.focused_pane = Pane::Editor;
                         }
@@ -448,17 +642,242 @@ impl App {
                             self.prev_terminal();
                         }
 
+                        MenuAction::SetEncoding => self.show_encoding_dialog(false),
+                        MenuAction::ReopenWithEncoding => self.show_encoding_dialog(true),
+                        MenuAction::ConvertToLf => self.convert_line_endings(LineEnding::Lf),
+                        MenuAction::ConvertToCrLf => self.convert_line_endings(LineEnding::CrLf),
+                        MenuAction::ConvertToCr => self.convert_line_endings(LineEnding::Cr),
+                        MenuAction::StripTrailingWhitespace => {
+                            if let Some(doc) = self.active_document_mut() {
+                                doc.strip_trailing_whitespace();
+                            }
+                        }
+                        MenuAction::EnsureFinalNewline => {
+                            if let Some(doc) = self.active_document_mut() {
+                                doc.ensure_final_newline();
+                            }
+                        }
+                        MenuAction::Indentation => self.show_indent_dialog(),
+                        MenuAction::IndentToSpaces => {
+                            if let Some(doc) = self.active_document_mut() {
+                                doc.convert_indentation(IndentStyle::Spaces);
+                            }
+                        }
+                        MenuAction::IndentToTabs => {
+                            if let Some(doc) = self.active_document_mut() {
+                                doc.convert_indentation(IndentStyle::Tabs);
+                            }
+                        }
+                        MenuAction::ReopenAsHex => self.reopen_as_hex(),
+
                         MenuAction::About => {
                             self.dialog = Some(Dialog::About(AboutDialog::new()));
                         }
 
-                        MenuAction::Separator => {}
+                        MenuAction::Separator | MenuAction::Submenu(_) => {}
                     }
                 }
             }
         }
     }
 
+    /// Execute an application-level command
+    pub fn handle_app_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Quit => self.should_quit = true,
+
+            AppEvent::FocusFileTree if self.show_sidebar => self.focused_pane = Pane::FileTree,
+            AppEvent::FocusEditor => self.focused_pane = Pane::Editor,
+            AppEvent::FocusTerminal if self.show_terminal => self.focused_pane = Pane::Terminal,
+            AppEvent::CycleFocusForward => self.cycle_focus(true),
+            AppEvent::CycleFocusBackward => self.cycle_focus(false),
+
+            AppEvent::ToggleSidebar => self.show_sidebar = !self.show_sidebar,
+            AppEvent::ToggleTerminal => self.show_terminal = !self.show_terminal,
+            AppEvent::ToggleWordWrap => {
+                // The editor view reflows on the next draw
+                self.config.editor.word_wrap = !self.config.editor.word_wrap;
+            }
+
+            AppEvent::Fold => {
+                self.update_folds();
+                if let Some(doc) = self.active_document_mut() {
+                    doc.fold_at(doc.cursor.line);
+                }
+            }
+            AppEvent::Unfold => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.unfold_at(doc.cursor.line);
+                }
+            }
+            AppEvent::FoldAll => {
+                self.update_folds();
+                if let Some(doc) = self.active_document_mut() {
+                    doc.fold_all();
+                }
+            }
+            AppEvent::UnfoldAll => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.unfold_all();
+                }
+            }
+
+            AppEvent::NewFile => self.new_file(),
+            AppEvent::OpenFile => self.show_open_dialog(),
+            AppEvent::SaveAs => self.show_save_as_dialog(),
+            AppEvent::CloseFile => self.close_current(),
+
+            AppEvent::Undo => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.undo();
+                }
+            }
+            AppEvent::Redo => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.redo();
+                }
+            }
+            AppEvent::SelectAll => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.select_all();
+                }
+            }
+            AppEvent::Cut if self.copy_selection() => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.delete_selection();
+                }
+            }
+            AppEvent::Copy => {
+                self.copy_selection();
+            }
+            AppEvent::Paste => {
+                if let Ok(text) = self.clipboard.get_text() {
+                    let block = self.clipboard.is_block_text(&text);
+                    if let Some(doc) = self.active_document_mut() {
+                        // A copied block goes back in as a block unless there are carets to fill
+                        if block && doc.caret_count() == 1 && !doc.has_selection() {
+                            doc.paste_block(&text);
+                        } else {
+                            doc.paste(&text);
+                        }
+                    }
+                }
+            }
+            AppEvent::AddCursorAbove => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.add_cursor_above();
+                }
+            }
+            AppEvent::AddCursorBelow => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.add_cursor_below();
+                }
+            }
+            AppEvent::AddNextOccurrence => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.add_next_occurrence();
+                }
+            }
+            AppEvent::ToggleLineComment => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.toggle_line_comment();
+                }
+            }
+            AppEvent::ToggleBlockComment => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.toggle_block_comment();
+                }
+            }
+            AppEvent::IndentLines => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.indent_lines();
+                }
+            }
+            AppEvent::UnindentLines => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.unindent_lines();
+                }
+            }
+            AppEvent::SortLines => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.sort_lines();
+                }
+            }
+            AppEvent::RemoveDuplicateLines => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.remove_duplicate_lines();
+                }
+            }
+            AppEvent::ReverseLines => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.reverse_lines();
+                }
+            }
+            AppEvent::JoinLines => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.join_lines();
+                }
+            }
+            AppEvent::ChangeCase(case) => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.change_case(case);
+                }
+            }
+            AppEvent::TransposeChars => {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.transpose_chars();
+                }
+            }
+
+            AppEvent::ToggleMacroRecording => self.macros.toggle_recording(),
+            // A macro cannot be played while one is being recorded
+            AppEvent::PlayLastMacro if !self.macros.is_recording() => {
+                let keys = self.macros.last().to_vec();
+                self.play_macro(&keys, Repeat::Times(1));
+            }
+            AppEvent::PlayMacro if !self.macros.is_recording() => {
+                self.dialog = Some(Dialog::PlayMacro(PlayMacroDialog::new(self.macros.names())));
+            }
+            AppEvent::SaveMacro => {
+                self.dialog = Some(Dialog::SaveMacro(SaveMacroDialog::new()));
+            }
+
+            AppEvent::Find => self.search.open(),
+            AppEvent::FindNext => self.find_next(),
+            AppEvent::FindPrevious => self.find_prev(),
+            AppEvent::Replace => self.search.open_replace(),
+            AppEvent::GoToLine => self.show_go_to_line_dialog(),
+            AppEvent::GoToMatchingBrace => {
+                self.update_literals(usize::MAX);
+                if let Some(doc) = self.active_document_mut() {
+                    doc.go_to_matching_bracket();
+                }
+            }
+            AppEvent::SelectToMatchingBrace => {
+                self.update_literals(usize::MAX);
+                if let Some(doc) = self.active_document_mut() {
+                    doc.select_to_matching_bracket();
+                }
+            }
+
+            AppEvent::ToggleBookmark => {
+                if let Some(doc) = self.documents.get_mut(self.active_doc) {
+                    doc.toggle_bookmark();
+                    self.bookmark_store.remember(doc);
+                }
+            }
+            AppEvent::NextBookmark => self.go_to_bookmark(true),
+            AppEvent::PrevBookmark => self.go_to_bookmark(false),
+
+            AppEvent::NextTab => self.next_tab(),
+            AppEvent::PreviousTab => self.prev_tab(),
+            AppEvent::GoToTab(tab) => self.go_to_tab(tab),
+
+            // Not implemented yet
+            _ => {}
+        }
+    }
+
     /// Get the current active document
     pub fn active_document(&self) -> Option<&Document> {
         self.documents.get(self.active_doc)
@@ -469,6 +888,26 @@ impl App {
         self.documents.get_mut(self.active_doc)
     }
 
+    /// Copy the active document's selection to the clipboard; returns whether
+    /// anything was copied
+    fn copy_selection(&mut self) -> bool {
+        let Some(doc) = self.active_document() else {
+            return false;
+        };
+        let text = doc.selected_text();
+        let block = doc.selection.is_block();
+        if text.is_empty() {
+            return false;
+        }
+
+        let result = if block {
+            self.clipboard.set_block_text(&text)
+        } else {
+            self.clipboard.set_text(&text)
+        };
+        result.is_ok()
+    }
+
     /// Open a file in a new tab
     pub fn open_file(&mut self, path: PathBuf) -> Result<()> {
         // Check if file is already open
@@ -479,21 +918,113 @@ impl App {
             }
         }
 
+        if self.loads.iter().any(|load| load.path == path) {
+            return Ok(());
+        }
+
+        // Large files load in the background; see poll_loads
+        const MB: u64 = 1024 * 1024;
+        let size = std::fs::metadata(&path)?.len();
+        if size > self.config.editor.large_file_threshold_mb * MB {
+            let max_bytes = self.config.editor.max_file_size_mb * MB;
+            self.loads.push(FileLoad::start(path, max_bytes)?);
+            return Ok(());
+        }
+
         // Open the file
-        let doc = Document::open(path)?;
+        let doc = self.configure_document(Document::open(path)?);
+        self.add_opened_document(doc);
+        Ok(())
+    }
+
+    /// Show an opened document in a new tab, warning about mixed line endings
+    fn add_opened_document(&mut self, doc: Document) {
+        if doc.mixed_line_endings && self.config.editor.warn_mixed_line_endings {
+            self.dialog = Some(Dialog::Message(MessageDialog {
+                title: "Mixed Line Endings".to_string(),
+                message: format!(
+                    "{} has mixed line endings; saving converts them to {}",
+                    doc.title(),
+                    doc.line_ending.display_name()
+                ),
+            }));
+        }
         self.documents.push(doc);
         self.active_doc = self.documents.len() - 1;
-        Ok(())
+    }
+
+    /// Add documents whose background load has finished
+    fn poll_loads(&mut self) {
+        let mut i = 0;
+        while i < self.loads.len() {
+            let Some(result) = self.loads[i].poll() else {
+                i += 1;
+                continue;
+            };
+            let load = self.loads.remove(i);
+            let replaced = load
+                .replaces
+                .and_then(|id| self.documents.iter().position(|doc| doc.id == id));
+            match (result, replaced) {
+                // Edited while it was being reloaded: ask what to keep
+                (Ok(_), Some(index)) if self.documents[index].modified => {
+                    self.changed_files.push(load.path.clone());
+                }
+                (Ok(doc), Some(index)) => {
+                    // A reload: keep the cursor and scroll position
+                    let old = &self.documents[index];
+                    let (line, col) = (old.cursor.line, old.cursor.col);
+                    let (scroll_y, scroll_x) = (old.scroll_y, old.scroll_x);
+                    let mut doc = self.configure_document(doc);
+                    doc.move_to(line, col, false);
+                    doc.scroll_y = scroll_y.min(doc.line_count().saturating_sub(1));
+                    doc.scroll_x = scroll_x;
+                    self.documents[index] = doc;
+                }
+                // Closed while it was being reloaded
+                (Ok(_), None) if load.replaces.is_some() => {}
+                (Ok(doc), None) => {
+                    let doc = self.configure_document(doc);
+                    self.add_opened_document(doc);
+                    if load.truncated {
+                        self.dialog = Some(Dialog::Message(MessageDialog {
+                            title: "File Too Large".to_string(),
+                            message: format!(
+                                "{} is larger than {} MB; showing the first part read-only",
+                                load.title(),
+                                self.config.editor.max_file_size_mb
+                            ),
+                        }));
+                    }
+                }
+                (Err(e), _) if load.replaces.is_some() => {
+                    self.dialog = Some(Dialog::Message(MessageDialog {
+                        title: "Reload Failed".to_string(),
+                        message: format!("Could not reload {}: {}", load.title(), e),
+                    }));
+                }
+                (Err(e), _) => {
+                    self.dialog = Some(Dialog::Message(MessageDialog {
+                        title: "Open Failed".to_string(),
+                        message: format!("Could not open {}: {}", load.title(), e),
+                    }));
+                }
+            }
+        }
     }
 
     /// Create a new empty document
     pub fn new_file(&mut self) {
-        self.documents.push(Document::new());
+        let doc = self.configure_document(Document::new());
+        self.documents.push(doc);
         self.active_doc = self.documents.len() - 1;
     }
 
     /// Close the current document
     pub fn close_current(&mut self) {
+        if let Some(doc) = self.documents.get(self.active_doc) {
+            self.bookmark_store.remember(doc);
+        }
         if self.documents.len() > 1 {
             self.documents.remove(self.active_doc);
             if self.active_doc >= self.documents.len() {
@@ -501,8 +1032,99 @@ impl App {
             }
         } else {
             // Replace with empty document instead of removing last one
-            self.documents[0] = Document::new();
+            self.documents[0] = self.configure_document(Document::new());
+        }
+    }
+
+    /// Apply editor settings from the configuration to a new document
+    fn configure_document(&self, mut doc: Document) -> Document {
+        doc.tab_width = self.config.editor.tab_width;
+        doc.indent_width = self.config.editor.tab_width;
+        doc.indent_style = if self.config.editor.insert_spaces {
+            IndentStyle::Spaces
+        } else {
+            IndentStyle::Tabs
+        };
+        doc.auto_indent = self.config.editor.auto_indent;
+        doc.indent_rules = self.config.editor.indent_rules(&doc.filetype);
+        doc.auto_pairs = self.config.editor.auto_pairs(&doc.filetype);
+        doc.comment_tokens = self.config.editor.comment_tokens(&doc.filetype);
+        doc.backup_on_save = self.config.editor.backup_on_save;
+        // Follow the file's own indentation (too slow for large files)
+        if !doc.large_file && doc.hex.is_none() {
+            doc.detect_indentation();
+        }
+        // .editorconfig files take precedence over gterm's own settings
+        if let Some(path) = doc.path.clone() {
+            doc.apply_editorconfig(Editorconfig::for_file(&path));
+            if doc.hex.is_none() {
+                let lines = self.bookmark_store.get(&path);
+                let count = doc.line_count();
+                doc.bookmarks
+                    .set(lines.into_iter().filter(|&l| l < count).collect());
+            }
+        }
+        doc
+    }
+
+    /// Cleanups to run before saving a document
+    fn save_actions(&self, doc: &Document) -> SaveActions {
+        doc.editorconfig
+            .save_actions(self.config.editor.save_actions(&doc.filetype))
+    }
+
+    /// Save a document to its path, reporting failure in a dialog
+    pub fn save_document(&mut self, index: usize) {
+        let Some(actions) = self.documents.get(index).map(|doc| self.save_actions(doc)) else {
+            return;
+        };
+        let doc = &mut self.documents[index];
+        doc.apply_save_actions(&actions);
+        if let Err(e) = doc.save() {
+            let title = doc.title();
+            self.show_save_error(&title, e);
+            return;
         }
+        self.bookmark_store.remember(&self.documents[index]);
+    }
+
+    /// Save the active document under a new path, reporting failure in a dialog
+    pub fn save_document_as(&mut self, path: PathBuf) {
+        let Some(doc) = self.documents.get_mut(self.active_doc) else {
+            return;
+        };
+        // The new location may have different .editorconfig settings
+        doc.apply_editorconfig(Editorconfig::for_file(&path));
+        let actions = self.save_actions(&self.documents[self.active_doc]);
+        let doc = &mut self.documents[self.active_doc];
+        doc.apply_save_actions(&actions);
+        if let Err(e) = doc.save_as(path.clone()) {
+            let title = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string());
+            self.show_save_error(&title, e);
+            return;
+        }
+        // Saving may have given the document a new filetype
+        let doc = &self.documents[self.active_doc];
+        let rules = self.config.editor.indent_rules(&doc.filetype);
+        let pairs = self.config.editor.auto_pairs(&doc.filetype);
+        let comments = self.config.editor.comment_tokens(&doc.filetype);
+        let doc = &mut self.documents[self.active_doc];
+        doc.indent_rules = rules;
+        doc.auto_pairs = pairs;
+        doc.comment_tokens = comments;
+        self.bookmark_store
+            .remember(&self.documents[self.active_doc]);
+    }
+
+    /// Tell the user a save failed; the file on disk is left as it was
+    fn show_save_error(&mut self, title: &str, error: std::io::Error) {
+        self.dialog = Some(Dialog::Message(MessageDialog {
+            title: "Save Failed".to_string(),
+            message: format!("Could not save {}: {}", title, error),
+        }));
     }
 
     /// Switch to the next tab
@@ -519,30 +1141,136 @@ impl App {
         }
     }
 
+    /// Replay macro keys in the editor. Playback stops early when a key opens
+    /// a dialog or leaves the editor; with `Repeat::ToEnd` it also stops once
+    /// a run does not bring the caret closer to the end of the document.
+    pub fn play_macro(&mut self, keys: &[event::KeyEvent], repeat: Repeat) {
+        if keys.is_empty() {
+            return;
+        }
+        let lines_left = |app: &App| {
+            app.active_document()
+                .map(|doc| (doc.id, doc.line_count() - doc.cursor.line))
+        };
+        let mut run = 0;
+        loop {
+            if let Repeat::Times(times) = repeat {
+                if run == times {
+                    break;
+                }
+            }
+            run += 1;
+
+            let before = lines_left(self);
+            for &key in keys {
+                let interrupted = self.dialog.is_some()
+                    || self.search.active
+                    || self.menu_open.is_some()
+                    || self.focused_pane != Pane::Editor;
+                if interrupted || self.handle_editor_key(key).is_err() {
+                    return;
+                }
+            }
+            if repeat == Repeat::ToEnd {
+                match (before, lines_left(self)) {
+                    (Some((id, before)), Some((after_id, after)))
+                        if after_id == id && after < before => {}
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    /// Move to the next bookmark after the caret (or the previous one before
+    /// it), continuing into the other documents in tab order and wrapping
+    /// around
+    pub fn go_to_bookmark(&mut self, forward: bool) {
+        let count = self.documents.len();
+        if count == 0 {
+            return;
+        }
+        if self.documents[self.active_doc].go_to_next_bookmark(forward) {
+            return;
+        }
+        // Ends with the active document, whose first (or last) bookmark is
+        // the wrap-around target
+        for step in 1..=count {
+            let index = if forward {
+                (self.active_doc + step) % count
+            } else {
+                (self.active_doc + count - step) % count
+            };
+            let doc = &mut self.documents[index];
+            let lines = doc.bookmarks.lines();
+            let line = if forward { lines.first() } else { lines.last() };
+            if let Some(&line) = line {
+                doc.move_to(line, 0, false);
+                self.active_doc = index;
+                return;
+            }
+        }
+    }
+
     /// Switch to a specific tab by index (1-based for user, converted to 0-based)
     pub fn go_to_tab(&mut self, tab: u8) {
         let index = if tab == 0 {
             self.documents.len().saturating_sub(1) // Tab 0 = last tab
         } else {
-            (tab as usize).saturating_sub(1)
-        };
-        if index < self.documents.len() {
-            self.active_doc = index;
+            (tab as usize).saturating_sub(1)
+        };
+        if index < self.documents.len() {
+            self.active_doc = index;
+        }
+    }
+
+    /// Perform search with current query in active document
+    pub fn do_search(&mut self) {
+        if let Some(doc) = self.documents.get(self.active_doc) {
+            self.search.search(doc);
+        }
+    }
+
+    /// Update matches after the query changed; in a large file the search
+    /// waits for Enter instead of running on every keystroke
+    fn search_query_changed(&mut self) {
+        if self
+            .active_document()
+            .is_some_and(|doc| doc.large_file || doc.hex.is_some())
+        {
+            self.search.matches.clear();
+            self.search.current_match = None;
+        } else {
+            self.do_search();
         }
     }
 
-    /// Perform search with current query in active document
-    pub fn do_search(&mut self) {
-        // Clone the document to avoid borrow issues
-        let doc_clone = match self.documents.get(self.active_doc) {
-            Some(doc) => doc.clone(),
-            None => return,
+    /// Search now if a large file's matches are still pending
+    fn ensure_search_results(&mut self) {
+        if self.search.matches.is_empty()
+            && !self.search.query.is_empty()
+            && self.active_document().is_some_and(|doc| doc.large_file)
+        {
+            self.do_search();
+        }
+    }
+
+    /// Search a binary document for the query as a byte pattern; returns
+    /// false for text documents
+    fn find_bytes(&mut self, forward: bool) -> bool {
+        let pattern = HexView::parse_pattern(&self.search.query);
+        let Some(hex) = self.active_document_mut().and_then(|doc| doc.hex.as_mut()) else {
+            return false;
         };
-        self.search.search(&doc_clone);
+        hex.find(&pattern, forward);
+        true
     }
 
     /// Find the next search match and move cursor to it
     pub fn find_next(&mut self) {
+        if self.find_bytes(true) {
+            return;
+        }
+        self.ensure_search_results();
         if let Some(doc) = self.active_document() {
             let (line, col) = (doc.cursor.line, doc.cursor.col);
             if let Some(m) = self.search.find_next_from(line, col) {
@@ -561,6 +1289,10 @@ impl App {
 
     /// Find the previous search match and move cursor to it
     pub fn find_prev(&mut self) {
+        if self.find_bytes(false) {
+            return;
+        }
+        self.ensure_search_results();
         if let Some(doc) = self.active_document() {
             let (line, col) = (doc.cursor.line, doc.cursor.col);
             if let Some(m) = self.search.find_prev_from(line, col) {
@@ -599,6 +1331,21 @@ impl App {
             // Check if terminal area changed and resize PTY
             self.check_terminal_resize();
 
+            // Pick up large files that finished loading
+            self.poll_loads();
+
+            // Notice open files changed by other programs
+            self.check_file_changes();
+
+            // Keep swap files current and auto-save after a pause
+            self.swap.tick(&self.documents);
+            self.check_idle_auto_save();
+
+            // Fold markers follow edits once typing pauses
+            if self.last_input.elapsed() >= FOLD_UPDATE_DELAY {
+                self.update_folds();
+            }
+
             // Handle events with a small timeout for responsiveness
             if event::poll(Duration::from_millis(16))? {
                 let event = event::read()?;
@@ -606,9 +1353,141 @@ impl App {
             }
         }
 
+        // A normal exit leaves nothing to recover
+        self.swap.remove_all();
+        for doc in &self.documents {
+            self.bookmark_store.remember(doc);
+        }
         Ok(())
     }
 
+    /// Reload open files changed on disk, or ask what to do if they have
+    /// local edits; follow renames and report deletions
+    fn check_file_changes(&mut self) {
+        self.watcher
+            .watch_files(self.documents.iter().filter_map(|doc| doc.path.as_deref()));
+
+        for event in self.watcher.events() {
+            let path = match event {
+                // Follow a file that was renamed, unless something took its place
+                FileEvent::Renamed { from, to } if !from.exists() => {
+                    for doc in &mut self.documents {
+                        if doc.path.as_ref() == Some(&from) {
+                            doc.path = Some(to.clone());
+                        }
+                    }
+                    to
+                }
+                FileEvent::Renamed { to, .. } => to,
+                FileEvent::Changed(path) => path,
+            };
+            let is_open = self
+                .documents
+                .iter()
+                .any(|doc| doc.path.as_ref() == Some(&path));
+            if is_open && !self.changed_files.contains(&path) {
+                self.changed_files.push(path);
+            }
+        }
+
+        // One question at a time
+        let mut reloading = Vec::new();
+        while self.dialog.is_none() {
+            let Some(path) = self.changed_files.pop() else {
+                break;
+            };
+            let Some(doc) = self
+                .documents
+                .iter_mut()
+                .find(|doc| doc.path.as_ref() == Some(&path))
+            else {
+                continue;
+            };
+            // A partly loaded file cannot be reloaded
+            if doc.read_only {
+                continue;
+            }
+            // Look again once the reload under way has finished
+            if self.loads.iter().any(|load| load.path == path) {
+                reloading.push(path);
+                continue;
+            }
+            match doc.disk_change() {
+                None => {}
+                Some(DiskChange::Modified) if !doc.modified => {
+                    let doc_id = doc.id;
+                    self.reload_document(doc_id);
+                }
+                Some(DiskChange::Modified) => {
+                    self.dialog = Some(Dialog::FileChanged(FileChangedDialog {
+                        doc_id: doc.id,
+                        title: doc.title(),
+                        diff: None,
+                    }));
+                }
+                Some(DiskChange::Deleted) => {
+                    doc.forget_deleted_file();
+                    self.dialog = Some(Dialog::Message(MessageDialog {
+                        title: "File Deleted".to_string(),
+                        message: format!(
+                            "{} was deleted from disk; saving creates it again",
+                            doc.title()
+                        ),
+                    }));
+                }
+            }
+        }
+        self.changed_files.extend(reloading);
+    }
+
+    /// Re-read a document's file; a large file is read in the background like
+    /// when it was opened (see poll_loads), and the reload cannot be undone
+    fn reload_document(&mut self, doc_id: u64) {
+        let Some(doc) = self.documents.iter_mut().find(|doc| doc.id == doc_id) else {
+            return;
+        };
+        let result = match (&doc.path, doc.large_file) {
+            (Some(path), true) => {
+                const MB: u64 = 1024 * 1024;
+                let max_bytes = self.config.editor.max_file_size_mb * MB;
+                FileLoad::start(path.clone(), max_bytes).map(|mut load| {
+                    load.replaces = Some(doc_id);
+                    self.loads.push(load);
+                })
+            }
+            _ => doc.reload(),
+        };
+        if let Err(e) = result {
+            let title = doc.title();
+            self.dialog = Some(Dialog::Message(MessageDialog {
+                title: "Reload Failed".to_string(),
+                message: format!("Could not reload {}: {}", title, e),
+            }));
+        }
+    }
+
+    /// Save every modified document that has a file (auto-save)
+    fn auto_save(&mut self) {
+        for i in 0..self.documents.len() {
+            let doc = &self.documents[i];
+            if doc.modified && doc.path.is_some() && !doc.read_only {
+                self.save_document(i);
+            }
+        }
+    }
+
+    /// Auto-save once the user has been idle for the configured time
+    fn check_idle_auto_save(&mut self) {
+        let idle_secs = self.config.editor.auto_save_idle_secs;
+        if idle_secs > 0
+            && !self.idle_saved
+            && self.last_input.elapsed() >= Duration::from_secs(idle_secs)
+        {
+            self.idle_saved = true;
+            self.auto_save();
+        }
+    }
+
     /// Check if terminal area changed and resize PTY accordingly
     fn check_terminal_resize(&mut self) {
         if let Some(area) = self.terminal_area {
@@ -629,6 +1508,10 @@ impl App {
 
     /// Handle an input event
     fn handle_event(&mut self, event: Event) -> Result<()> {
+        if matches!(event, Event::Key(_) | Event::Mouse(_)) {
+            self.last_input = Instant::now();
+            self.idle_saved = false;
+        }
         match event {
             Event::Key(key) => self.handle_key_event(key),
             Event::Mouse(mouse) => self.handle_mouse_event(mouse),
@@ -636,6 +1519,12 @@ impl App {
                 // Terminal resize is handled automatically by ratatui
                 Ok(())
             }
+            Event::FocusLost => {
+                if self.config.editor.auto_save_on_focus_loss {
+                    self.auto_save();
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
@@ -655,6 +1544,17 @@ impl App {
         // If menu is open, handle menu navigation first
         if self.menu_open.is_some() {
             match key.code {
+                // Esc and Left close an open submenu first
+                KeyCode::Esc | KeyCode::Left if self.submenu_selected.is_some() => {
+                    self.submenu_selected = None;
+                    return Ok(());
+                }
+                KeyCode::Right
+                    if self.submenu_selected.is_none() && self.menu_item_has_submenu() =>
+                {
+                    self.execute_menu_action();
+                    return Ok(());
+                }
                 KeyCode::Esc => {
                     self.close_menu();
                     return Ok(());
@@ -706,16 +1606,18 @@ impl App {
             (KeyModifiers::CONTROL, KeyCode::Char('q')) => {
                 self.should_quit = true;
             }
-            // Toggle sidebar: Ctrl+B
-            (KeyModifiers::CONTROL, KeyCode::Char('b')) => {
+            // Toggle sidebar: Ctrl+Shift+B
+            (_, KeyCode::Char('b') | KeyCode::Char('B')) if ctrl_shift => {
                 self.show_sidebar = !self.show_sidebar;
             }
             // Toggle terminal: Ctrl+T
             (KeyModifiers::CONTROL, KeyCode::Char('t')) => {
                 self.show_terminal = !self.show_terminal;
             }
-            // Toggle editor: Ctrl+E
-            (KeyModifiers::CONTROL, KeyCode::Char('e')) => {
+            // Toggle editor: Ctrl+E (toggles comments in the editor)
+            (KeyModifiers::CONTROL, KeyCode::Char('e'))
+                if self.focused_pane != Pane::Editor || !self.show_editor =>
+            {
                 self.show_editor = !self.show_editor;
             }
             // Focus file tree: F3
@@ -766,7 +1668,7 @@ impl App {
             (KeyModifiers::NONE, KeyCode::Tab) if self.focused_pane != Pane::Editor => {
                 self.cycle_focus(true);
             }
-            (KeyModifiers::SHIFT, KeyCode::BackTab) => {
+            (KeyModifiers::SHIFT, KeyCode::BackTab) if self.focused_pane != Pane::Editor => {
                 self.cycle_focus(false);
             }
             _ => {
@@ -866,13 +1768,10 @@ impl App {
                             // Save with current filename
                             if save_dialog.is_valid() {
                                 let save_path = save_dialog.get_save_path();
-                                if let Some(doc) = self.active_document_mut() {
-                                    doc.path = Some(save_path);
-                                    let _ = doc.save();
-                                }
+                                self.dialog = None;
+                                self.save_document_as(save_path);
                                 // Refresh file tree to show the new file
                                 self.file_tree.refresh();
-                                self.dialog = None;
                             }
                         } else {
                             // Enter in file list: navigate into dir or select file
@@ -918,6 +1817,159 @@ impl App {
                 // Any key closes about dialog
                 self.dialog = None;
             }
+            Dialog::Recovery(ref mut recovery_dialog) => {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.dialog = None;
+                    }
+                    KeyCode::Up => recovery_dialog.move_up(),
+                    KeyCode::Down => recovery_dialog.move_down(),
+                    KeyCode::Enter => {
+                        if let Some(entry) = recovery_dialog.take_selected() {
+                            let doc = self.configure_document(entry.recover());
+                            self.documents.push(doc);
+                            self.active_doc = self.documents.len() - 1;
+                            // The recovered document gets a swap file of its own
+                            entry.discard();
+                        }
+                    }
+                    KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => {
+                        if let Some(entry) = recovery_dialog.take_selected() {
+                            entry.discard();
+                        }
+                    }
+                    _ => {}
+                }
+                if matches!(&self.dialog, Some(Dialog::Recovery(d)) if d.entries.is_empty()) {
+                    self.dialog = None;
+                }
+            }
+            Dialog::FileChanged(ref mut changed_dialog) => {
+                let doc_id = changed_dialog.doc_id;
+                let Some(doc) = self.documents.iter_mut().find(|doc| doc.id == doc_id) else {
+                    self.dialog = None;
+                    return Ok(());
+                };
+                match key.code {
+                    KeyCode::Char('r') | KeyCode::Char('R') => {
+                        self.dialog = None;
+                        self.reload_document(doc_id);
+                    }
+                    KeyCode::Char('k') | KeyCode::Char('K') | KeyCode::Esc => {
+                        // Saving will overwrite the other program's version
+                        doc.record_disk_state();
+                        self.dialog = None;
+                    }
+                    KeyCode::Char('d') | KeyCode::Char('D') => {
+                        changed_dialog.diff = Some(doc.diff_against_disk());
+                    }
+                    _ => {}
+                }
+            }
+            Dialog::PlayMacro(ref mut macro_dialog) => match key.code {
+                KeyCode::Esc => {
+                    self.dialog = None;
+                }
+                KeyCode::Up => macro_dialog.move_up(),
+                KeyCode::Down => macro_dialog.move_down(),
+                KeyCode::Tab => macro_dialog.toggle_to_end(),
+                KeyCode::Backspace => macro_dialog.handle_backspace(),
+                KeyCode::Char(c) => macro_dialog.handle_input(c),
+                KeyCode::Delete => {
+                    if let Some(name) = macro_dialog.remove_selected() {
+                        if let Err(e) = self.macros.remove(&name) {
+                            self.dialog = Some(Dialog::Message(MessageDialog {
+                                title: "Delete Failed".to_string(),
+                                message: format!("Could not delete macro {}: {}", name, e),
+                            }));
+                        }
+                    }
+                }
+                KeyCode::Enter => {
+                    let keys = match macro_dialog.selected_name() {
+                        Some(name) => self.macros.get(name).unwrap_or_default().to_vec(),
+                        None => self.macros.last().to_vec(),
+                    };
+                    let repeat = macro_dialog.repeat();
+                    self.dialog = None;
+                    self.play_macro(&keys, repeat);
+                }
+                _ => {}
+            },
+            Dialog::SaveMacro(ref mut save_dialog) => match key.code {
+                KeyCode::Esc => {
+                    self.dialog = None;
+                }
+                KeyCode::Backspace => save_dialog.handle_backspace(),
+                KeyCode::Char(c) => save_dialog.handle_input(c),
+                KeyCode::Enter => {
+                    let name = save_dialog.name.trim().to_string();
+                    if name.is_empty() {
+                        save_dialog.error = Some("Enter a name".to_string());
+                    } else if self.macros.last().is_empty() {
+                        save_dialog.error = Some("Record a macro first (F7)".to_string());
+                    } else {
+                        match self.macros.save_last(&name) {
+                            Ok(()) => self.dialog = None,
+                            Err(e) => save_dialog.error = Some(format!("Could not save: {}", e)),
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Dialog::Indent(ref mut indent_dialog) => match key.code {
+                KeyCode::Esc => {
+                    self.dialog = None;
+                }
+                KeyCode::Up => indent_dialog.move_up(),
+                KeyCode::Down => indent_dialog.move_down(),
+                KeyCode::Tab => indent_dialog.toggle_mode(),
+                KeyCode::Enter => {
+                    let (style, width) = INDENT_OPTIONS[indent_dialog.selected];
+                    let convert = indent_dialog.convert;
+                    self.dialog = None;
+                    let Some(doc) = self.active_document_mut() else {
+                        return Ok(());
+                    };
+                    if convert {
+                        doc.reindent(style, width);
+                    } else {
+                        doc.indent_style = style;
+                        doc.indent_width = if style == IndentStyle::Tabs {
+                            doc.tab_width
+                        } else {
+                            width
+                        };
+                    }
+                }
+                _ => {}
+            },
+            Dialog::Encoding(ref mut encoding_dialog) => match key.code {
+                KeyCode::Esc => {
+                    self.dialog = None;
+                }
+                KeyCode::Up => encoding_dialog.move_up(),
+                KeyCode::Down => encoding_dialog.move_down(),
+                KeyCode::Char(' ') => encoding_dialog.toggle_bom(),
+                KeyCode::Tab => encoding_dialog.toggle_mode(),
+                KeyCode::Enter => {
+                    let encoding = encoding_dialog.encoding();
+                    let reopen = encoding_dialog.reopen;
+                    self.dialog = None;
+                    let Some(doc) = self.active_document_mut() else {
+                        return Ok(());
+                    };
+                    if !reopen {
+                        doc.set_encoding(encoding);
+                    } else if let Err(e) = doc.reopen_with_encoding(encoding.encoding) {
+                        self.dialog = Some(Dialog::Message(MessageDialog {
+                            title: "Reopen Failed".to_string(),
+                            message: e.to_string(),
+                        }));
+                    }
+                }
+                _ => {}
+            },
         }
 
         Ok(())
@@ -950,7 +2002,7 @@ impl App {
                     self.search.replace_backspace();
                 } else {
                     self.search.backspace();
-                    self.do_search();
+                    self.search_query_changed();
                 }
             }
             KeyCode::Char(c) => {
@@ -981,7 +2033,7 @@ impl App {
                         self.search.replace_input_char(c);
                     } else {
                         self.search.input_char(c);
-                        self.do_search();
+                        self.search_query_changed();
                     }
                 }
             }
@@ -1030,9 +2082,11 @@ impl App {
                 doc.selection.anchor = doc.cursor;
                 doc.cursor.col = m.end_col;
                 doc.selection.head = doc.cursor;
-                // Delete and insert replacement
+                // Delete and insert replacement as one undo step
+                doc.begin_group();
                 doc.delete_selection();
                 doc.insert_str(&replace_text);
+                doc.end_group();
             }
 
             // Re-search to update matches
@@ -1066,6 +2120,8 @@ impl App {
         });
 
         if let Some(doc) = self.active_document_mut() {
+            // The whole replace-all is a single undo step
+            doc.begin_group();
             for m in matches {
                 // Move to the match position
                 doc.move_to(m.line, m.start_col, false);
@@ -1077,6 +2133,7 @@ impl App {
                 doc.delete_selection();
                 doc.insert_str(&replace_text);
             }
+            doc.end_group();
         }
 
         // Re-search to update (should find no matches now if replacement doesn't contain search term)
@@ -1105,6 +2162,50 @@ impl App {
         let shift = key.modifiers.contains(KeyModifiers::SHIFT);
         let alt = key.modifiers.contains(KeyModifiers::ALT);
 
+        // Macro recording and playback: F7 / F8 / Shift+F8 / Ctrl+F8; these
+        // keys are not recorded themselves
+        if let KeyCode::F(7 | 8) = key.code {
+            self.handle_app_event(match key.code {
+                KeyCode::F(7) => AppEvent::ToggleMacroRecording,
+                _ if ctrl => AppEvent::SaveMacro,
+                _ if shift => AppEvent::PlayMacro,
+                _ => AppEvent::PlayLastMacro,
+            });
+            return Ok(());
+        }
+        self.macros.record(key);
+
+        // Multi-cursor shortcuts: Ctrl+Alt+Up/Down/D
+        if ctrl && alt && !shift {
+            let event = match key.code {
+                KeyCode::Up => Some(AppEvent::AddCursorAbove),
+                KeyCode::Down => Some(AppEvent::AddCursorBelow),
+                KeyCode::Char('d') => Some(AppEvent::AddNextOccurrence),
+                _ => None,
+            };
+            if let Some(event) = event {
+                self.handle_app_event(event);
+                return Ok(());
+            }
+        }
+
+        // Block selection: Alt+Shift+arrows
+        if alt && shift && !ctrl {
+            let delta = match key.code {
+                KeyCode::Up => Some((-1, 0)),
+                KeyCode::Down => Some((1, 0)),
+                KeyCode::Left => Some((0, -1)),
+                KeyCode::Right => Some((0, 1)),
+                _ => None,
+            };
+            if let Some((lines, cols)) = delta {
+                if let Some(doc) = self.active_document_mut() {
+                    doc.extend_block_selection(lines, cols);
+                }
+                return Ok(());
+            }
+        }
+
         // Handle global editor shortcuts first
         match (ctrl, shift, key.code) {
             // New file: Ctrl+N
@@ -1121,9 +2222,7 @@ impl App {
             (true, false, KeyCode::Char('s')) => {
                 if let Some(doc) = self.active_document() {
                     if doc.path.is_some() {
-                        if let Some(doc) = self.active_document_mut() {
-                            let _ = doc.save();
-                        }
+                        self.save_document(self.active_doc);
                     } else {
                         // No path - show save as dialog
                         self.show_save_as_dialog();
@@ -1151,6 +2250,16 @@ impl App {
                 self.prev_tab();
                 return Ok(());
             }
+            // Undo: Ctrl+Z
+            (true, false, KeyCode::Char('z')) => {
+                self.handle_app_event(AppEvent::Undo);
+                return Ok(());
+            }
+            // Redo: Ctrl+Y or Ctrl+Shift+Z
+            (true, false, KeyCode::Char('y')) | (true, true, KeyCode::Char('Z')) => {
+                self.handle_app_event(AppEvent::Redo);
+                return Ok(());
+            }
             // Select all: Ctrl+A
             (true, false, KeyCode::Char('a')) => {
                 if let Some(doc) = self.active_document_mut() {
@@ -1160,34 +2269,74 @@ impl App {
             }
             // Cut: Ctrl+X
             (true, false, KeyCode::Char('x')) => {
-                let text = self.active_document().map(|doc| doc.selected_text());
-                if let Some(text) = text {
-                    if !text.is_empty() {
-                        let _ = self.clipboard.set_text(&text);
-                        if let Some(doc) = self.active_document_mut() {
-                            doc.delete_selection();
-                        }
-                    }
-                }
+                self.handle_app_event(AppEvent::Cut);
                 return Ok(());
             }
             // Copy: Ctrl+C
             (true, false, KeyCode::Char('c')) => {
-                if let Some(doc) = self.active_document() {
-                    let text = doc.selected_text();
-                    if !text.is_empty() {
-                        let _ = self.clipboard.set_text(&text);
-                    }
-                }
+                self.handle_app_event(AppEvent::Copy);
                 return Ok(());
             }
             // Paste: Ctrl+V
             (true, false, KeyCode::Char('v')) => {
-                if let Ok(text) = self.clipboard.get_text() {
-                    if let Some(doc) = self.active_document_mut() {
-                        doc.insert_str(&text);
-                    }
-                }
+                self.handle_app_event(AppEvent::Paste);
+                return Ok(());
+            }
+            // Toggle bookmark: Ctrl+M (in terminals that report it apart from Enter)
+            (true, false, KeyCode::Char('m')) => {
+                self.handle_app_event(AppEvent::ToggleBookmark);
+                return Ok(());
+            }
+            // Next / previous bookmark: Ctrl+. / Ctrl+,
+            (true, false, KeyCode::Char('.')) => {
+                self.handle_app_event(AppEvent::NextBookmark);
+                return Ok(());
+            }
+            (true, false, KeyCode::Char(',')) => {
+                self.handle_app_event(AppEvent::PrevBookmark);
+                return Ok(());
+            }
+            // Join lines: Ctrl+J
+            (true, false, KeyCode::Char('j')) => {
+                self.handle_app_event(AppEvent::JoinLines);
+                return Ok(());
+            }
+            // Sort / reverse / remove duplicate lines: F9 / Shift+F9 / Ctrl+F9
+            (_, _, KeyCode::F(9)) => {
+                self.handle_app_event(if ctrl {
+                    AppEvent::RemoveDuplicateLines
+                } else if shift {
+                    AppEvent::ReverseLines
+                } else {
+                    AppEvent::SortLines
+                });
+                return Ok(());
+            }
+            // Indent / unindent lines: Ctrl+I / Ctrl+U
+            (true, false, KeyCode::Char('i')) => {
+                self.handle_app_event(AppEvent::IndentLines);
+                return Ok(());
+            }
+            (true, false, KeyCode::Char('u')) => {
+                self.handle_app_event(AppEvent::UnindentLines);
+                return Ok(());
+            }
+            // Toggle line / block comment: Ctrl+E / Ctrl+Shift+E
+            (true, _, KeyCode::Char('e') | KeyCode::Char('E')) => {
+                self.handle_app_event(if shift {
+                    AppEvent::ToggleBlockComment
+                } else {
+                    AppEvent::ToggleLineComment
+                });
+                return Ok(());
+            }
+            // Go to / select to matching bracket: Ctrl+B / Ctrl+Alt+B
+            (true, false, KeyCode::Char('b')) => {
+                self.handle_app_event(if alt {
+                    AppEvent::SelectToMatchingBrace
+                } else {
+                    AppEvent::GoToMatchingBrace
+                });
                 return Ok(());
             }
             // Find: Ctrl+F
@@ -1217,19 +2366,77 @@ impl App {
             _ => {}
         }
 
-        // Handle Alt+number for tab switching
+        // snake_case / camelCase: Alt+Shift+S / Alt+Shift+C
+        if alt && !ctrl {
+            let case = match key.code {
+                KeyCode::Char('S') => Some(TextCase::Snake),
+                KeyCode::Char('s') if shift => Some(TextCase::Snake),
+                KeyCode::Char('C') => Some(TextCase::Camel),
+                KeyCode::Char('c') if shift => Some(TextCase::Camel),
+                _ => None,
+            };
+            if let Some(case) = case {
+                self.handle_app_event(AppEvent::ChangeCase(case));
+                return Ok(());
+            }
+        }
+
+        if alt && !ctrl {
+            if let Some(event) = fold_key_event(key.code, shift) {
+                self.handle_app_event(event);
+                return Ok(());
+            }
+        }
+
+        // Handle Alt+number for tab switching, Alt+Z for word wrap, Alt+U/L/C
+        // for case, Alt+T to transpose and Alt+M . , for bookmarks
         if alt && !ctrl && !shift {
             if let KeyCode::Char(c @ '0'..='9') = key.code {
                 let tab = c.to_digit(10).unwrap() as u8;
                 self.go_to_tab(tab);
                 return Ok(());
             }
+            let event = match key.code {
+                KeyCode::Char('z') => Some(AppEvent::ToggleWordWrap),
+                KeyCode::Char('u') => Some(AppEvent::ChangeCase(TextCase::Upper)),
+                KeyCode::Char('l') => Some(AppEvent::ChangeCase(TextCase::Lower)),
+                KeyCode::Char('c') => Some(AppEvent::ChangeCase(TextCase::Title)),
+                KeyCode::Char('t') => Some(AppEvent::TransposeChars),
+                KeyCode::Char('m') => Some(AppEvent::ToggleBookmark),
+                KeyCode::Char('.') => Some(AppEvent::NextBookmark),
+                KeyCode::Char(',') => Some(AppEvent::PrevBookmark),
+                _ => None,
+            };
+            if let Some(event) = event {
+                self.handle_app_event(event);
+                return Ok(());
+            }
+        }
+
+        // Binary documents are edited in the hex view
+        if self.active_document().is_some_and(|doc| doc.hex.is_some()) {
+            self.handle_hex_key(key);
+            return Ok(());
+        }
+
+        // Pairs are not closed inside strings and comments; only the lines up
+        // to the last caret need scanning
+        if let KeyCode::Char(c) = key.code {
+            let last_caret_line = self
+                .active_document()
+                .filter(|doc| doc.is_pair_char(c))
+                .and_then(|doc| doc.selections().iter().map(|sel| sel.head.line).max());
+            if let Some(line) = last_caret_line {
+                self.update_literals(line);
+            }
         }
 
         // Handle regular editor input
         if let Some(doc) = self.active_document_mut() {
             match key.code {
                 // Cursor movement
+                KeyCode::Left if ctrl => doc.move_word_left(shift),
+                KeyCode::Right if ctrl => doc.move_word_right(shift),
                 KeyCode::Left => doc.move_left(shift),
                 KeyCode::Right => doc.move_right(shift),
                 KeyCode::Up => doc.move_up(shift),
@@ -1252,14 +2459,15 @@ impl App {
                 KeyCode::PageDown => doc.page_down(20, shift),
 
                 // Editing
+                KeyCode::Backspace if ctrl => doc.delete_word_backward(),
+                KeyCode::Delete if ctrl => doc.delete_word_forward(),
                 KeyCode::Backspace => doc.backspace(),
                 KeyCode::Delete => doc.delete(),
-                KeyCode::Enter => doc.insert_char('\n'),
-                KeyCode::Tab => {
-                    // Insert 4 spaces (or tab character based on config)
-                    doc.insert_str("    ");
-                }
+                KeyCode::Enter => doc.insert_newline(),
+                KeyCode::Tab => doc.insert_indent(),
+                KeyCode::BackTab => doc.unindent_lines(),
                 KeyCode::Insert => doc.toggle_insert_mode(),
+                KeyCode::Esc => doc.clear_secondary_cursors(),
 
                 // Character input
                 KeyCode::Char(c) => {
@@ -1275,6 +2483,46 @@ impl App {
         Ok(())
     }
 
+    /// Handle navigation and overwrite typing in the hex view
+    fn handle_hex_key(&mut self, key: event::KeyEvent) {
+        use crate::editor::BYTES_PER_ROW;
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let page = self
+            .editor_area
+            .map(|a| a.height as usize)
+            .unwrap_or(20)
+            .max(1);
+        let Some(doc) = self.active_document_mut() else {
+            return;
+        };
+        if let KeyCode::Char(c) = key.code {
+            if !ctrl && !alt {
+                doc.hex_type_char(c);
+            }
+            return;
+        }
+        let Some(hex) = doc.hex.as_mut() else {
+            return;
+        };
+        let row = BYTES_PER_ROW as isize;
+        match key.code {
+            KeyCode::Left => hex.move_by(-1),
+            KeyCode::Right => hex.move_by(1),
+            KeyCode::Up => hex.move_by(-row),
+            KeyCode::Down => hex.move_by(row),
+            KeyCode::PageUp => hex.move_by(-row * page as isize),
+            KeyCode::PageDown => hex.move_by(row * page as isize),
+            KeyCode::Home if ctrl => hex.move_to(0),
+            KeyCode::End if ctrl => hex.move_to(usize::MAX),
+            KeyCode::Home => hex.move_to_row_edge(false),
+            KeyCode::End => hex.move_to_row_edge(true),
+            KeyCode::Tab => hex.toggle_column(),
+            _ => {}
+        }
+    }
+
     /// Handle keyboard events for the terminal
     fn handle_terminal_key(&mut self, key: event::KeyEvent) -> Result<()> {
         let shift = key.modifiers.contains(KeyModifiers::SHIFT);
@@ -1408,6 +2656,24 @@ impl App {
                     return Ok(());
                 }
 
+                // Check if clicking in an open submenu
+                if let Some(area) = self
+                    .submenu_area
+                    .filter(|_| self.submenu_selected.is_some())
+                {
+                    if area.contains(Position::new(mouse.column, mouse.row)) {
+                        let row = mouse.row.saturating_sub(area.y + 1) as usize;
+                        let item = self
+                            .open_submenu()
+                            .and_then(|(_, items)| menu_bar::selectable_item(items, row));
+                        if mouse.row > area.y && item.is_some() {
+                            self.submenu_selected = item;
+                            self.execute_menu_action();
+                        }
+                        return Ok(());
+                    }
+                }
+
                 // Check if clicking in dropdown menu
                 if let Some(menu_idx) = self.menu_open {
                     // Get dropdown bounds
@@ -1428,6 +2694,7 @@ impl App {
                                     menu_bar::item_at_position(menu_idx, item_row)
                                 {
                                     self.menu_selected = Some(item_idx);
+                                    self.submenu_selected = None;
                                     self.execute_menu_action();
                                     return Ok(());
                                 }
@@ -1440,6 +2707,16 @@ impl App {
                     // Don't return - let click be processed normally
                 }
 
+                // Clicking the indentation in the status bar opens its dialog
+                if self
+                    .indent_indicator
+                    .is_some_and(|r| r.contains(Position::new(mouse.column, mouse.row)))
+                {
+                    self.close_menu();
+                    self.show_indent_dialog();
+                    return Ok(());
+                }
+
                 // Check if clicking on a divider to start resize
                 if let Some(target) = self.check_divider_click(mouse.column, mouse.row) {
                     self.resizing = Some(target);
@@ -1447,13 +2724,39 @@ impl App {
                     // Check if clicking in file tree
                     self.focused_pane = Pane::FileTree;
                     self.file_tree.select_index(index);
+                } else if let Some((line, on_fold_marker)) =
+                    ui::editor::gutter_line_from_screen(self, mouse.column, mouse.row)
+                {
+                    // Clicking a fold marker in the gutter folds/unfolds, the
+                    // rest of the gutter toggles a bookmark
+                    self.focused_pane = Pane::Editor;
+                    if on_fold_marker {
+                        self.update_folds();
+                        if let Some(doc) = self.active_document_mut() {
+                            doc.toggle_fold(line);
+                        }
+                    } else if let Some(doc) = self.documents.get_mut(self.active_doc) {
+                        doc.bookmarks.toggle(line);
+                        self.bookmark_store.remember(doc);
+                    }
                 } else if let Some((line, col)) =
-                    self.get_editor_position_at(mouse.column, mouse.row)
+                    ui::editor::position_from_screen(self, mouse.column, mouse.row)
                 {
-                    // Check if clicking in editor
+                    // Check if clicking in editor (Alt+click adds a caret,
+                    // double/triple click selects a word/line)
                     self.focused_pane = Pane::Editor;
+                    let add_cursor = mouse.modifiers.contains(KeyModifiers::ALT);
+                    let clicks = self.input_handler.record_click(mouse.column, mouse.row);
                     if let Some(doc) = self.active_document_mut() {
-                        doc.move_to(line, col, false);
+                        if add_cursor {
+                            doc.add_cursor_at(line, col);
+                        } else {
+                            match clicks {
+                                2 => doc.select_word_at(line, col),
+                                3 => doc.select_line(line),
+                                _ => doc.move_to(line, col, false),
+                            }
+                        }
                     }
                 } else {
                     // Click to focus pane
@@ -1466,14 +2769,27 @@ impl App {
             MouseEventKind::Drag(MouseButton::Left) => {
                 if let Some(target) = self.resizing {
                     self.handle_resize_drag(target, mouse.column, mouse.row);
+                } else if self.focused_pane == Pane::Editor
+                    && mouse.modifiers.contains(KeyModifiers::ALT)
+                {
+                    // Alt+drag selects a block
+                    if let Some((line, vcol)) =
+                        ui::editor::cell_from_screen(self, mouse.column, mouse.row)
+                    {
+                        if let Some(doc) = self.active_document_mut() {
+                            doc.block_select_to(line, vcol);
+                        }
+                    }
                 } else if self.focused_pane == Pane::Editor {
                     // Drag to select text
-                    if let Some((line, col)) = self.get_editor_position_at(mouse.column, mouse.row)
+                    if let Some((line, col)) =
+                        ui::editor::position_from_screen(self, mouse.column, mouse.row)
                     {
                         if let Some(doc) = self.active_document_mut() {
                             doc.cursor.line = line;
                             doc.cursor.col = col;
                             doc.selection.head = doc.cursor;
+                            doc.selection.mode = SelectionMode::Normal;
                         }
                     }
                 }
@@ -1486,7 +2802,7 @@ impl App {
                 }
                 Pane::Editor => {
                     if let Some(doc) = self.active_document_mut() {
-                        doc.scroll_y = doc.scroll_y.saturating_sub(3);
+                        doc.scroll_lines(-3);
                     }
                 }
                 Pane::Terminal => {
@@ -1503,8 +2819,7 @@ impl App {
                 }
                 Pane::Editor => {
                     if let Some(doc) = self.active_document_mut() {
-                        let max_scroll = doc.line_count().saturating_sub(1);
-                        doc.scroll_y = (doc.scroll_y + 3).min(max_scroll);
+                        doc.scroll_lines(3);
                     }
                 }
                 Pane::Terminal => {
@@ -1532,43 +2847,6 @@ impl App {
         }
     }
 
-    /// Get the editor document position at a screen position
-    fn get_editor_position_at(&self, x: u16, y: u16) -> Option<(usize, usize)> {
-        let area = self.editor_area?;
-
-        if x < area.x || x >= area.x + area.width || y < area.y || y >= area.y + area.height {
-            return None;
-        }
-
-        let doc = self.active_document()?;
-
-        // Calculate gutter width
-        let line_count = doc.line_count();
-        let digits = if line_count == 0 {
-            1
-        } else {
-            (line_count as f64).log10().floor() as u16 + 1
-        };
-        let gutter_width = digits.max(4) + 1;
-
-        // Check if click is in gutter
-        if x < area.x + gutter_width {
-            return None;
-        }
-
-        let screen_row = (y - area.y) as usize;
-        let screen_col = (x - area.x - gutter_width) as usize;
-
-        let line = doc.scroll_y + screen_row;
-        let col = doc.scroll_x + screen_col;
-
-        // Clamp to valid positions
-        let line = line.min(doc.line_count().saturating_sub(1));
-        let col = col.min(doc.line_len(line));
-
-        Some((line, col))
-    }
-
     /// Cycle focus between visible panes
     fn cycle_focus(&mut self, forward: bool) {
         let panes: Vec<Pane> = {
@@ -1718,3 +2996,45 @@ impl Default for App {
         Self::new()
     }
 }
+
+/// Folding command for a key pressed with Alt: `-`/`=` fold and unfold at the
+/// cursor, with Shift (`_`/`+`) everywhere. Alt+[ is not used, as terminals
+/// send it as the start of an escape sequence.
+fn fold_key_event(code: KeyCode, shift: bool) -> Option<AppEvent> {
+    match code {
+        KeyCode::Char('-') if !shift => Some(AppEvent::Fold),
+        KeyCode::Char('=') if !shift => Some(AppEvent::Unfold),
+        KeyCode::Char('-' | '_') => Some(AppEvent::FoldAll),
+        KeyCode::Char('=' | '+') => Some(AppEvent::UnfoldAll),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_keys() {
+        assert_eq!(
+            fold_key_event(KeyCode::Char('-'), false),
+            Some(AppEvent::Fold)
+        );
+        assert_eq!(
+            fold_key_event(KeyCode::Char('='), false),
+            Some(AppEvent::Unfold)
+        );
+        // Terminals differ in whether they report Shift with the shifted key
+        for shift in [false, true] {
+            assert_eq!(
+                fold_key_event(KeyCode::Char('_'), shift),
+                Some(AppEvent::FoldAll)
+            );
+            assert_eq!(
+                fold_key_event(KeyCode::Char('+'), shift),
+                Some(AppEvent::UnfoldAll)
+            );
+        }
+        assert_eq!(fold_key_event(KeyCode::Char('['), false), None);
+    }
+}
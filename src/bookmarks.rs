@@ -0,0 +1,135 @@
+//! Bookmarks kept between sessions
+//!
+//! The bookmarked lines of every file are stored in `bookmarks.toml` in
+//! gterm's state directory (`~/.local/state/gterm` on Linux), keyed by the
+//! file's path, and restored when the file is opened again.
+
+use crate::editor::Document;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Location of the bookmarks file
+pub fn bookmarks_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|p| p.join("gterm").join("bookmarks.toml"))
+}
+
+/// Contents of the bookmarks file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BookmarksFile {
+    /// 0-based bookmarked lines per file path
+    #[serde(default)]
+    files: BTreeMap<String, Vec<usize>>,
+}
+
+/// Bookmarks of all files, as last stored
+#[derive(Debug, Default)]
+pub struct BookmarkStore {
+    /// Where the store is saved (None = not saved)
+    path: Option<PathBuf>,
+    contents: BookmarksFile,
+}
+
+impl BookmarkStore {
+    /// Read the store from `path`; a missing or unreadable file gives an
+    /// empty store
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let contents = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        Self { path, contents }
+    }
+
+    /// Bookmarked lines stored for `file`
+    pub fn get(&self, file: &Path) -> Vec<usize> {
+        self.contents
+            .files
+            .get(&key(file))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Store the bookmarks of a document that has a file, and save the store
+    /// if they changed; hex views have no lines to mark, and the lines of a
+    /// modified document may not match the file on disk
+    pub fn remember(&mut self, doc: &Document) {
+        let Some(file) = doc
+            .path
+            .as_ref()
+            .filter(|_| doc.hex.is_none() && !doc.modified)
+        else {
+            return;
+        };
+        let key = key(file);
+        let lines = doc.bookmarks.lines();
+        let changed = if lines.is_empty() {
+            self.contents.files.remove(&key).is_some()
+        } else {
+            self.contents.files.insert(key, lines.to_vec()).as_deref() != Some(lines)
+        };
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Write the store; failures are ignored (bookmarks are a convenience)
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Ok(text) = toml::to_string(&self.contents) {
+            let _ = std::fs::write(path, text);
+        }
+    }
+}
+
+/// Store key of a file: its absolute path
+fn key(file: &Path) -> String {
+    std::path::absolute(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_saved_and_restored_per_file() {
+        let dir = std::env::temp_dir().join(format!("gterm-bookmarks-{}", std::process::id()));
+        let path = dir.join("bookmarks.toml");
+        let file = dir.join("notes.txt");
+
+        let mut store = BookmarkStore::load(Some(path.clone()));
+        let mut doc = Document::new();
+        doc.insert_str("a\nb\nc\n");
+        doc.path = Some(file.clone());
+        doc.bookmarks.set(vec![2, 0]);
+        store.remember(&doc);
+        assert!(store.get(&file).is_empty());
+        doc.modified = false;
+        store.remember(&doc);
+
+        let mut store = BookmarkStore::load(Some(path.clone()));
+        assert_eq!(store.get(&file), vec![0, 2]);
+        assert!(store.get(&dir.join("other.txt")).is_empty());
+
+        // Bookmarks of unsaved changes do not replace those of the saved file
+        doc.insert_str("x\n");
+        assert!(doc.modified);
+        doc.bookmarks.set(vec![1]);
+        store.remember(&doc);
+        let store = BookmarkStore::load(Some(path));
+        assert_eq!(store.get(&file), vec![0, 2]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,8 @@
+use crate::editor::{
+    pairs_for_filetype, parse_pair, CommentTokens, IndentRules, IndentStyle, SaveActions,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// Application configuration
@@ -33,6 +37,144 @@ pub struct EditorConfig {
     /// Syntax highlighting theme name
     #[serde(default = "default_syntax_theme")]
     pub syntax_theme: String,
+    /// Show a dialog when a file with mixed line endings is opened
+    #[serde(default = "default_true")]
+    pub warn_mixed_line_endings: bool,
+    /// Copy the previous contents to `file~` when saving
+    #[serde(default)]
+    pub backup_on_save: bool,
+    /// Files larger than this (in MB) load in the background in large-file
+    /// mode, without syntax highlighting or live search
+    #[serde(default = "default_large_file_threshold")]
+    pub large_file_threshold_mb: u64,
+    /// Files larger than this (in MB) are opened read-only, showing only the
+    /// first part
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size_mb: u64,
+    /// Write unsaved changes to a swap file every this many seconds, for
+    /// recovery after a crash (0 = off)
+    #[serde(default = "default_swap_interval")]
+    pub swap_interval_secs: u64,
+    /// Save modified files when the terminal window loses focus
+    #[serde(default)]
+    pub auto_save_on_focus_loss: bool,
+    /// Save modified files after this many seconds without input (0 = off)
+    #[serde(default)]
+    pub auto_save_idle_secs: u64,
+    /// Remove trailing whitespace when saving
+    #[serde(default)]
+    pub strip_trailing_whitespace: bool,
+    /// Add a newline at the end of files that have none when saving
+    #[serde(default)]
+    pub ensure_final_newline: bool,
+    /// Rewrite indentation when saving: "keep", "tabs" or "spaces"
+    #[serde(default)]
+    pub indent_on_save: IndentOnSave,
+    /// Insert the closing bracket or quote when typing an opening one
+    #[serde(default = "default_true")]
+    pub auto_close_pairs: bool,
+    /// Settings for particular filetypes, keyed by the filetype shown in the
+    /// status bar (e.g. `[editor.filetypes.Markdown]`)
+    #[serde(default)]
+    pub filetypes: HashMap<String, FiletypeConfig>,
+}
+
+/// How to rewrite indentation when saving
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentOnSave {
+    #[default]
+    Keep,
+    Tabs,
+    Spaces,
+}
+
+/// Per-filetype settings; anything not set falls back to `[editor]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FiletypeConfig {
+    pub strip_trailing_whitespace: Option<bool>,
+    pub ensure_final_newline: Option<bool>,
+    pub indent_on_save: Option<IndentOnSave>,
+    /// Line endings that indent the next line (replaces the built-in list)
+    pub indent_after: Option<Vec<String>>,
+    /// Line contents that dedent the line when typed (replaces the built-in list)
+    pub dedent_on: Option<Vec<String>>,
+    /// Pairs closed automatically, e.g. `["()", "\"\""]` (replaces the built-in list)
+    pub auto_pairs: Option<Vec<String>>,
+    /// Line comment prefix, e.g. `"//"` (empty for none)
+    pub line_comment: Option<String>,
+    /// Block comment delimiters, e.g. `["/*", "*/"]` (empty for none)
+    pub block_comment: Option<Vec<String>>,
+}
+
+impl EditorConfig {
+    /// Cleanups to run when saving a document of this filetype
+    pub fn save_actions(&self, filetype: &str) -> SaveActions {
+        let overrides = self.filetypes.get(filetype).cloned().unwrap_or_default();
+        let indent = overrides.indent_on_save.unwrap_or(self.indent_on_save);
+        SaveActions {
+            strip_trailing_whitespace: overrides
+                .strip_trailing_whitespace
+                .unwrap_or(self.strip_trailing_whitespace),
+            // Turned off, the file's own final newline is left alone
+            final_newline: overrides
+                .ensure_final_newline
+                .unwrap_or(self.ensure_final_newline)
+                .then_some(true),
+            convert_indentation: match indent {
+                IndentOnSave::Keep => None,
+                IndentOnSave::Tabs => Some(IndentStyle::Tabs),
+                IndentOnSave::Spaces => Some(IndentStyle::Spaces),
+            },
+            ..SaveActions::default()
+        }
+    }
+
+    /// Brackets and quotes closed automatically for a filetype
+    pub fn auto_pairs(&self, filetype: &str) -> Vec<(char, char)> {
+        if !self.auto_close_pairs {
+            return Vec::new();
+        }
+        match self
+            .filetypes
+            .get(filetype)
+            .and_then(|o| o.auto_pairs.as_ref())
+        {
+            Some(pairs) => pairs.iter().filter_map(|p| parse_pair(p)).collect(),
+            None => pairs_for_filetype(filetype),
+        }
+    }
+
+    /// Smart indentation rules for a filetype: built in, or from the config
+    pub fn indent_rules(&self, filetype: &str) -> IndentRules {
+        let mut rules = IndentRules::for_filetype(filetype);
+        if let Some(overrides) = self.filetypes.get(filetype) {
+            if let Some(indent_after) = &overrides.indent_after {
+                rules.indent_after = indent_after.clone();
+            }
+            if let Some(dedent_on) = &overrides.dedent_on {
+                rules.dedent_on = dedent_on.clone();
+            }
+        }
+        rules
+    }
+
+    /// Comment tokens for a filetype: built in, or from the config
+    pub fn comment_tokens(&self, filetype: &str) -> CommentTokens {
+        let mut tokens = CommentTokens::for_filetype(filetype);
+        if let Some(overrides) = self.filetypes.get(filetype) {
+            if let Some(line) = &overrides.line_comment {
+                tokens.line = (!line.is_empty()).then(|| line.clone());
+            }
+            if let Some(block) = &overrides.block_comment {
+                tokens.block = match block.as_slice() {
+                    [open, close] => Some((open.clone(), close.clone())),
+                    _ => None,
+                };
+            }
+        }
+        tokens
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +232,15 @@ fn default_theme() -> String {
 fn default_syntax_theme() -> String {
     "base16-ocean.dark".to_string()
 }
+fn default_large_file_threshold() -> u64 {
+    16
+}
+fn default_max_file_size() -> u64 {
+    512
+}
+fn default_swap_interval() -> u64 {
+    5
+}
 
 impl Default for Config {
     fn default() -> Self {
@@ -112,6 +263,18 @@ impl Default for EditorConfig {
             highlight_current_line: true,
             word_wrap: false,
             syntax_theme: "base16-ocean.dark".to_string(),
+            warn_mixed_line_endings: true,
+            backup_on_save: false,
+            large_file_threshold_mb: default_large_file_threshold(),
+            max_file_size_mb: default_max_file_size(),
+            swap_interval_secs: default_swap_interval(),
+            auto_save_on_focus_loss: false,
+            auto_save_idle_secs: 0,
+            strip_trailing_whitespace: false,
+            ensure_final_newline: false,
+            indent_on_save: IndentOnSave::Keep,
+            auto_close_pairs: true,
+            filetypes: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,206 @@
+//! Rectangular (block/column) selection for `Document`
+//!
+//! A block selection is a `Selection` whose mode is `SelectionMode::Block`. Its
+//! anchor and head lines give the rows, and the display columns stored in the
+//! mode give the left and right edges, so the block keeps its shape across
+//! short lines and tabs. `caret_ranges` expands it into one range per row, which
+//! makes the regular editing operations (typing, deleting, copying) work on
+//! every row at once.
+
+use super::document::CaretRange;
+use super::history::EditKind;
+use super::{Cursor, Document, Selection, SelectionMode};
+
+impl Document {
+    /// Grow or shrink the block selection by moving its head (Alt+Shift+arrows).
+    /// Starts a new block at the primary caret if there is none.
+    pub fn extend_block_selection(&mut self, line_delta: isize, col_delta: isize) {
+        let (anchor, anchor_col, head_col) = match self.selection.mode {
+            SelectionMode::Block {
+                anchor_col,
+                head_col,
+            } => (self.selection.anchor, anchor_col, head_col),
+            SelectionMode::Normal => {
+                let col = self
+                    .buffer
+                    .visual_col(self.cursor.line, self.cursor.col, self.tab_width);
+                (self.cursor, col, col)
+            }
+        };
+
+        let last_line = self.line_count().saturating_sub(1);
+        let line = self
+            .selection
+            .head
+            .line
+            .saturating_add_signed(line_delta)
+            .min(last_line);
+        let head_col = head_col.saturating_add_signed(col_delta);
+        self.set_block(anchor, anchor_col, line, head_col);
+    }
+
+    /// Extend a block selection from its anchor (or the primary caret) to a line and
+    /// display column, e.g. while Alt+dragging the mouse
+    pub fn block_select_to(&mut self, line: usize, vcol: usize) {
+        let (anchor, anchor_col) = match self.selection.mode {
+            SelectionMode::Block { anchor_col, .. } => (self.selection.anchor, anchor_col),
+            SelectionMode::Normal => {
+                let anchor = self.selection.anchor;
+                let col = self
+                    .buffer
+                    .visual_col(anchor.line, anchor.col, self.tab_width);
+                (anchor, col)
+            }
+        };
+
+        let line = line.min(self.line_count().saturating_sub(1));
+        self.set_block(anchor, anchor_col, line, vcol);
+    }
+
+    /// Paste a block of text (one line per row) at the primary caret's display
+    /// column, padding short lines and adding lines past the end of the buffer
+    pub fn paste_block(&mut self, text: &str) {
+        let rows: Vec<&str> = text.lines().collect();
+        if rows.is_empty() || self.read_only || self.hex.is_some() {
+            return;
+        }
+
+        let first_line = self.cursor.line;
+        let vcol = self
+            .buffer
+            .visual_col(first_line, self.cursor.col, self.tab_width);
+
+        self.begin_edit(EditKind::Other);
+        self.secondary.clear();
+        let mut head = self.cursor;
+        for (i, row) in rows.iter().enumerate() {
+            let line = first_line + i;
+            if line >= self.line_count() {
+                let end = self.buffer.len_chars();
+                self.insert_at(end, "\n");
+            }
+
+            let col = self.buffer.col_for_visual(line, vcol, self.tab_width);
+            let pad = vcol.saturating_sub(self.buffer.line_visual_len(line, self.tab_width));
+            let text = format!("{}{}", " ".repeat(pad), row);
+            let idx = self.buffer.line_col_to_char(line, col);
+            self.insert_at(idx, &text);
+            head = Cursor::at(line, col + text.chars().count());
+        }
+        self.cursor = head;
+        self.selection = Selection::new(head);
+        self.end_edit();
+    }
+
+    /// Rows covered by a block selection as `(line, start_col, end_col)`, for rendering
+    pub fn block_spans(&self) -> Vec<(usize, usize, usize)> {
+        let Some((first, last, left, right)) = self.selection.block_bounds() else {
+            return Vec::new();
+        };
+        let last = last.min(self.line_count().saturating_sub(1));
+
+        (first..=last)
+            .map(|line| {
+                (
+                    line,
+                    self.buffer.col_for_visual(line, left, self.tab_width),
+                    self.buffer.col_for_visual(line, right, self.tab_width),
+                )
+            })
+            .collect()
+    }
+
+    /// Rows of a block selection as char ranges, one per line
+    pub(super) fn block_rows(&self, sel: &Selection, primary: bool) -> Vec<CaretRange> {
+        let Some((first, last, left, right)) = sel.block_bounds() else {
+            return Vec::new();
+        };
+        let last = last.min(self.line_count().saturating_sub(1));
+
+        (first..=last)
+            .map(|line| {
+                let start = self.buffer.col_for_visual(line, left, self.tab_width);
+                let end = self.buffer.col_for_visual(line, right, self.tab_width);
+                let width = self.buffer.line_visual_len(line, self.tab_width);
+                CaretRange {
+                    start: self.buffer.line_col_to_char(line, start),
+                    end: self.buffer.line_col_to_char(line, end),
+                    primary: primary && line == sel.head.line,
+                    pad: left.saturating_sub(width),
+                    block: true,
+                }
+            })
+            .collect()
+    }
+
+    /// Replace all carets with a block selection
+    fn set_block(&mut self, anchor: Cursor, anchor_col: usize, line: usize, head_col: usize) {
+        let col = self.buffer.col_for_visual(line, head_col, self.tab_width);
+        self.secondary.clear();
+        self.cursor = Cursor::at(line, col);
+        self.selection = Selection {
+            anchor,
+            head: self.cursor,
+            mode: SelectionMode::Block {
+                anchor_col,
+                head_col,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_with(text: &str) -> Document {
+        let mut doc = Document::new();
+        doc.insert_str(text);
+        doc.move_to(0, 0, false);
+        doc
+    }
+
+    #[test]
+    fn test_block_copy_and_delete() {
+        let mut doc = doc_with("abcd\nef\nghij");
+        doc.move_to(0, 1, false);
+        doc.extend_block_selection(2, 2);
+        assert_eq!(doc.selected_text(), "bc\nf\nhi");
+
+        doc.delete_selection();
+        assert_eq!(doc.buffer.to_string(), "ad\ne\ngj");
+
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "abcd\nef\nghij");
+    }
+
+    #[test]
+    fn test_select_all_ends_block_selection() {
+        let mut doc = doc_with("abcd\nef\nghij");
+        doc.move_to(0, 1, false);
+        doc.extend_block_selection(1, 1);
+        doc.select_all();
+        assert!(doc.block_spans().is_empty());
+        assert_eq!(doc.selected_text(), "abcd\nef\nghij");
+    }
+
+    #[test]
+    fn test_typing_in_zero_width_block_pads_short_lines() {
+        let mut doc = doc_with("abcd\na\nabcd");
+        doc.move_to(0, 3, false);
+        doc.extend_block_selection(2, 0);
+        doc.insert_char('|');
+        assert_eq!(doc.buffer.to_string(), "abc|d\na  |\nabc|d");
+    }
+
+    #[test]
+    fn test_paste_block() {
+        let mut doc = doc_with("ab\nc");
+        doc.move_to(0, 1, false);
+        doc.paste_block("X\nY\nZ");
+        assert_eq!(doc.buffer.to_string(), "aXb\ncY\n Z");
+
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "ab\nc");
+    }
+}
@@ -0,0 +1,114 @@
+//! Bookmarked lines of a document
+//!
+//! Bookmarks follow their lines as text is inserted and deleted; a bookmark
+//! on a line that is joined into the one above moves there.
+
+use super::Document;
+
+/// Bookmarked lines, sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bookmarks {
+    lines: Vec<usize>,
+}
+
+impl Bookmarks {
+    /// Bookmarked lines in order
+    pub fn lines(&self) -> &[usize] {
+        &self.lines
+    }
+
+    /// Replace all bookmarks
+    pub fn set(&mut self, mut lines: Vec<usize>) {
+        lines.sort_unstable();
+        lines.dedup();
+        self.lines = lines;
+    }
+
+    /// Add a bookmark on `line`, or remove the one there
+    pub fn toggle(&mut self, line: usize) {
+        match self.lines.binary_search(&line) {
+            Ok(i) => {
+                self.lines.remove(i);
+            }
+            Err(i) => self.lines.insert(i, line),
+        }
+    }
+
+    /// Nearest bookmark after `line` (or before it when not `forward`)
+    pub fn next_from(&self, line: usize, forward: bool) -> Option<usize> {
+        if forward {
+            self.lines.iter().find(|&&l| l > line).copied()
+        } else {
+            self.lines.iter().rev().find(|&&l| l < line).copied()
+        }
+    }
+
+    /// Shift bookmarks after `count` lines were inserted at `line`; the line
+    /// itself moves down when the text went in at its start
+    pub(super) fn lines_inserted(&mut self, line: usize, at_start: bool, count: usize) {
+        for l in &mut self.lines {
+            if *l > line || (*l == line && at_start) {
+                *l += count;
+            }
+        }
+    }
+
+    /// Shift bookmarks after lines `first + 1..=last` were joined into `first`
+    pub(super) fn lines_removed(&mut self, first: usize, last: usize) {
+        for l in &mut self.lines {
+            if *l > last {
+                *l -= last - first;
+            } else if *l > first {
+                *l = first;
+            }
+        }
+        self.lines.dedup();
+    }
+}
+
+impl Document {
+    /// Add or remove a bookmark on the caret's line
+    pub fn toggle_bookmark(&mut self) {
+        self.bookmarks.toggle(self.cursor.line);
+    }
+
+    /// Move to the next bookmark after the caret's line (or the previous one
+    /// before it); false if there is none in that direction
+    pub fn go_to_next_bookmark(&mut self, forward: bool) -> bool {
+        match self.bookmarks.next_from(self.cursor.line, forward) {
+            Some(line) => {
+                self.move_to(line, 0, false);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_follow_edits() {
+        let mut doc = Document::new();
+        doc.insert_str("a\nb\nc\nd");
+        doc.bookmarks.set(vec![1, 3]);
+
+        // A line inserted above moves both down
+        doc.move_to(0, 0, false);
+        doc.insert_str("new\n");
+        assert_eq!(doc.bookmarks.lines(), &[2, 4]);
+
+        // Deleting the bookmarked line leaves its bookmark on the next one
+        doc.move_to(2, 0, false);
+        doc.move_to(3, 0, true);
+        doc.backspace();
+        assert_eq!(doc.bookmarks.lines(), &[2, 3]);
+
+        doc.move_to(0, 0, false);
+        assert!(doc.go_to_next_bookmark(true));
+        assert_eq!(doc.cursor.line, 2);
+        assert!(!doc.go_to_next_bookmark(false));
+    }
+}
@@ -0,0 +1,125 @@
+//! Matching brackets
+//!
+//! The bracket next to the caret is paired with its partner by counting
+//! nesting; brackets inside strings and comments are ignored.
+
+use super::Document;
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Lines searched in each direction before giving up on a match
+const MAX_SCAN_LINES: usize = 10_000;
+
+impl Document {
+    /// The bracket at the primary caret (else the one before it) and its
+    /// match, as (line, col) positions
+    pub fn matching_bracket(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (line, col) = (self.cursor.line, self.cursor.col);
+        let chars: Vec<char> = self.buffer.line_text(line).chars().collect();
+        std::iter::once(col)
+            .chain(col.checked_sub(1))
+            .find_map(|col| {
+                let found = self.find_match(line, col, *chars.get(col)?)?;
+                Some(((line, col), found))
+            })
+    }
+
+    /// Position of the bracket matching `ch` at `line`/`col`
+    fn find_match(&self, line: usize, col: usize, ch: char) -> Option<(usize, usize)> {
+        if self.in_literal(line, col) {
+            return None;
+        }
+        let (open, close, forward) = BRACKETS.iter().find_map(|&(open, close)| {
+            (ch == open || ch == close).then_some((open, close, ch == open))
+        })?;
+
+        let mut depth = 0usize;
+        let mut visit = |l: usize, c: usize, x: char| {
+            if (x != open && x != close) || self.in_literal(l, c) {
+                return false;
+            }
+            if (x == open) == forward {
+                depth += 1;
+                false
+            } else {
+                depth -= 1;
+                depth == 0
+            }
+        };
+
+        if forward {
+            let last = self.line_count().min(line + MAX_SCAN_LINES);
+            for l in line..last {
+                let skip = if l == line { col } else { 0 };
+                for (c, x) in self.buffer.line_text(l).chars().enumerate().skip(skip) {
+                    if visit(l, c, x) {
+                        return Some((l, c));
+                    }
+                }
+            }
+        } else {
+            let first = line.saturating_sub(MAX_SCAN_LINES);
+            for l in (first..=line).rev() {
+                let chars: Vec<char> = self.buffer.line_text(l).chars().collect();
+                let end = if l == line { col + 1 } else { chars.len() };
+                for c in (0..end).rev() {
+                    if visit(l, c, chars[c]) {
+                        return Some((l, c));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Move the caret to the bracket matching the one next to it
+    pub fn go_to_matching_bracket(&mut self) {
+        if let Some((_, (line, col))) = self.matching_bracket() {
+            self.move_to(line, col, false);
+        }
+    }
+
+    /// Select from the bracket next to the caret through its match
+    pub fn select_to_matching_bracket(&mut self) {
+        if let Some((from, to)) = self.matching_bracket() {
+            let (start, end) = if from < to { (from, to) } else { (to, from) };
+            self.move_to(start.0, start.1, false);
+            self.move_to(end.0, end.1 + 1, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_bracket_skips_nesting() {
+        let mut doc = Document::new();
+        doc.insert_str("f(a[0], {\n  b(c)\n})");
+        doc.move_to(0, 1, false);
+        assert_eq!(doc.matching_bracket(), Some(((0, 1), (2, 1))));
+
+        // The bracket before the caret is used when none is at it
+        doc.move_to(1, 6, false);
+        assert_eq!(doc.matching_bracket(), Some(((1, 5), (1, 3))));
+
+        doc.move_to(0, 8, false);
+        doc.select_to_matching_bracket();
+        assert_eq!(doc.selected_text(), "{\n  b(c)\n}");
+        doc.move_to(0, 8, false);
+        doc.go_to_matching_bracket();
+        assert_eq!((doc.cursor.line, doc.cursor.col), (2, 0));
+    }
+
+    #[test]
+    fn test_brackets_in_strings_are_ignored() {
+        let mut highlighting = crate::highlighting::HighlightingManager::new();
+        let mut doc = Document::new();
+        doc.filetype = "Python".to_string();
+        doc.insert_str("f(\")\", x)");
+        doc.scan_literals(&mut highlighting, 0);
+        doc.move_to(0, 1, false);
+        assert_eq!(doc.matching_bracket(), Some(((0, 1), (0, 8))));
+    }
+}
@@ -1,5 +1,9 @@
+use super::document::LineEnding;
+use super::encoding::TextEncoding;
 use ropey::Rope;
 use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 /// A text buffer backed by a rope data structure
 #[derive(Debug, Clone)]
@@ -21,16 +25,28 @@ impl Buffer {
         }
     }
 
+    /// Create a buffer from a rope
+    pub fn from_rope(rope: Rope) -> Self {
+        Self { rope }
+    }
+
     /// Load buffer content from a file
     pub fn from_file(path: &Path) -> std::io::Result<Self> {
         let text = std::fs::read_to_string(path)?;
         Ok(Self::from_str(&text))
     }
 
-    /// Save buffer content to a file
-    pub fn save_to_file(&self, path: &Path) -> std::io::Result<()> {
-        let text = self.rope.to_string();
-        std::fs::write(path, text)
+    /// Save buffer content to a file in the given encoding and line ending,
+    /// replacing it atomically and optionally keeping a `file~` backup
+    pub fn save_to_file(
+        &self,
+        path: &Path,
+        encoding: &TextEncoding,
+        line_ending: LineEnding,
+        backup: bool,
+    ) -> std::io::Result<()> {
+        let bytes = encoding.encode(&line_ending.apply(&self.rope.to_string()))?;
+        crate::utils::fs::write_atomic(path, &bytes, backup)
     }
 
     /// Get the total number of lines
@@ -43,6 +59,11 @@ impl Buffer {
         self.rope.len_chars()
     }
 
+    /// Get the total size in bytes (as UTF-8)
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
     /// Check if the buffer is empty
     pub fn is_empty(&self) -> bool {
         self.rope.len_chars() == 0
@@ -72,6 +93,70 @@ impl Buffer {
         }
     }
 
+    /// Text of a line without its newline
+    pub fn line_text(&self, line_idx: usize) -> String {
+        match self.line(line_idx) {
+            Some(line) => line.chars().take(self.line_len(line_idx)).collect(),
+            None => String::new(),
+        }
+    }
+
+    /// Grapheme clusters of a line laid out in display columns
+    pub fn graphemes(&self, line_idx: usize, tab_width: usize) -> Vec<Grapheme> {
+        layout_line(&self.line_text(line_idx), tab_width)
+    }
+
+    /// Display column of a character column, expanding tabs to `tab_width`
+    pub fn visual_col(&self, line_idx: usize, col: usize, tab_width: usize) -> usize {
+        let graphemes = self.graphemes(line_idx, tab_width);
+        match graphemes.iter().find(|g| g.end_col > col) {
+            Some(g) => g.x,
+            None => graphemes.last().map_or(0, |g| g.x + g.width),
+        }
+    }
+
+    /// Display width of a line (excluding the newline)
+    pub fn line_visual_len(&self, line_idx: usize, tab_width: usize) -> usize {
+        self.graphemes(line_idx, tab_width)
+            .last()
+            .map_or(0, |g| g.x + g.width)
+    }
+
+    /// Character column of the first grapheme starting at or after display column
+    /// `vcol` (the end of the line if it is shorter)
+    pub fn col_for_visual(&self, line_idx: usize, vcol: usize, tab_width: usize) -> usize {
+        self.graphemes(line_idx, tab_width)
+            .iter()
+            .find(|g| g.x >= vcol)
+            .map_or(self.line_len(line_idx), |g| g.col)
+    }
+
+    /// Character column of the grapheme covering display column `vcol` (the end of
+    /// the line if it is shorter)
+    pub fn col_at_visual(&self, line_idx: usize, vcol: usize, tab_width: usize) -> usize {
+        self.graphemes(line_idx, tab_width)
+            .iter()
+            .find(|g| vcol < g.x + g.width)
+            .map_or(self.line_len(line_idx), |g| g.col)
+    }
+
+    /// Column after the grapheme at `col`
+    pub fn next_grapheme_col(&self, line_idx: usize, col: usize) -> usize {
+        layout_line(&self.line_text(line_idx), 1)
+            .iter()
+            .find(|g| g.end_col > col)
+            .map_or(self.line_len(line_idx), |g| g.end_col)
+    }
+
+    /// Column of the grapheme before `col`
+    pub fn prev_grapheme_col(&self, line_idx: usize, col: usize) -> usize {
+        layout_line(&self.line_text(line_idx), 1)
+            .iter()
+            .rev()
+            .find(|g| g.col < col)
+            .map_or(0, |g| g.col)
+    }
+
     /// Convert a (line, column) position to a character index
     pub fn line_col_to_char(&self, line: usize, col: usize) -> usize {
         if line >= self.len_lines() {
@@ -153,6 +238,63 @@ impl Buffer {
     }
 }
 
+/// A grapheme cluster placed on a display line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grapheme {
+    /// Character column where the cluster starts
+    pub col: usize,
+    /// Character column just past the cluster
+    pub end_col: usize,
+    /// Display column where the cluster starts
+    pub x: usize,
+    /// Number of display cells the cluster occupies
+    pub width: usize,
+    pub text: String,
+}
+
+impl Grapheme {
+    /// Text to draw for this cluster, exactly `width` cells wide
+    pub fn display_text(&self) -> String {
+        if self.text == "\t" {
+            " ".repeat(self.width)
+        } else if self.text.chars().any(char::is_control) {
+            "\u{fffd}".to_string()
+        } else if self.text.width() == 0 {
+            // Give zero-width clusters (lone combining marks) a base to sit on
+            format!(" {}", self.text)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// Split a line into grapheme clusters and assign each its display columns;
+/// tabs advance to the next multiple of `tab_width`
+pub fn layout_line(text: &str, tab_width: usize) -> Vec<Grapheme> {
+    let tab_width = tab_width.max(1);
+    let mut col = 0;
+    let mut x = 0;
+    text.graphemes(true)
+        .map(|g| {
+            let width = if g == "\t" {
+                tab_width - x % tab_width
+            } else {
+                g.width().clamp(1, 2)
+            };
+            let grapheme = Grapheme {
+                col,
+                end_col: col + g.chars().count(),
+                x,
+                width,
+                text: g.to_string(),
+            };
+            col = grapheme.end_col;
+            x += width;
+            grapheme
+        })
+        .collect()
+}
+
 impl Default for Buffer {
     fn default() -> Self {
         Self::new()
@@ -206,4 +348,30 @@ mod tests {
         assert_eq!(buf.char_to_line_col(6), (1, 0));
         assert_eq!(buf.char_to_line_col(11), (1, 5));
     }
+
+    #[test]
+    fn test_visual_columns_expand_tabs() {
+        let buf = Buffer::from_str("\tab\tc\nx");
+        assert_eq!(buf.visual_col(0, 1, 4), 4);
+        assert_eq!(buf.visual_col(0, 4, 4), 8);
+        assert_eq!(buf.line_visual_len(0, 4), 9);
+
+        assert_eq!(buf.col_for_visual(0, 2, 4), 1);
+        assert_eq!(buf.col_at_visual(0, 2, 4), 0);
+        assert_eq!(buf.col_for_visual(0, 8, 4), 4);
+        assert_eq!(buf.col_for_visual(1, 5, 4), 1);
+    }
+
+    #[test]
+    fn test_wide_and_combining_graphemes() {
+        let buf = Buffer::from_str("a\u{4e2d}e\u{301}b");
+        assert_eq!(buf.visual_col(0, 1, 4), 1);
+        assert_eq!(buf.visual_col(0, 2, 4), 3);
+        assert_eq!(buf.visual_col(0, 4, 4), 4);
+        assert_eq!(buf.line_visual_len(0, 4), 5);
+
+        assert_eq!(buf.col_at_visual(0, 2, 4), 1);
+        assert_eq!(buf.next_grapheme_col(0, 2), 4);
+        assert_eq!(buf.prev_grapheme_col(0, 4), 2);
+    }
 }
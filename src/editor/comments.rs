@@ -0,0 +1,191 @@
+//! Commenting lines and selections out and back in
+//!
+//! Each filetype has a line comment prefix and/or block comment delimiters;
+//! the config can replace them per filetype.
+
+use super::document::Change;
+use super::history::EditKind;
+use super::indent::leading_indent;
+use super::lines::LineEdit;
+use super::Document;
+
+/// How a filetype writes comments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommentTokens {
+    /// Line comment prefix, e.g. `//`
+    pub line: Option<String>,
+    /// Block comment delimiters, e.g. `/*` and `*/`
+    pub block: Option<(String, String)>,
+}
+
+impl CommentTokens {
+    /// Built-in tokens for a filetype (none for plain text and unknown types)
+    pub fn for_filetype(filetype: &str) -> Self {
+        let (line, block): (Option<&str>, Option<(&str, &str)>) = match filetype {
+            "Rust" | "C" | "C++" | "C#" | "Java" | "Go" | "JavaScript" | "TypeScript"
+            | "JavaScript (React)" | "TypeScript (React)" | "PHP" | "Swift" | "Kotlin"
+            | "Scala" | "Dart" | "V" | "Gradle" | "SCSS" | "Less" => {
+                (Some("//"), Some(("/*", "*/")))
+            }
+            "Zig" => (Some("//"), None),
+            "F#" => (Some("//"), Some(("(*", "*)"))),
+            "Python" | "Shell" | "Perl" | "R" | "Ruby" | "Crystal" | "Elixir" | "YAML" | "TOML"
+            | "Makefile" | "CMake" | "Dockerfile" => (Some("#"), None),
+            "Julia" => (Some("#"), Some(("#=", "=#"))),
+            "Nim" => (Some("#"), Some(("#[", "]#"))),
+            "PowerShell" => (Some("#"), Some(("<#", "#>"))),
+            "Lua" => (Some("--"), Some(("--[[", "]]"))),
+            "SQL" => (Some("--"), Some(("/*", "*/"))),
+            "Haskell" => (Some("--"), Some(("{-", "-}"))),
+            "Erlang" | "LaTeX" => (Some("%"), None),
+            "Clojure" => (Some(";"), None),
+            "Vim Script" => (Some("\""), None),
+            "OCaml" => (None, Some(("(*", "*)"))),
+            "CSS" => (None, Some(("/*", "*/"))),
+            "HTML" | "XML" | "Markdown" => (None, Some(("<!--", "-->"))),
+            _ => (None, None),
+        };
+        Self {
+            line: line.map(str::to_string),
+            block: block.map(|(open, close)| (open.to_string(), close.to_string())),
+        }
+    }
+}
+
+impl Document {
+    /// Comment out the selected lines (the caret's line without a selection),
+    /// or uncomment them if they all are; filetypes without line comments get
+    /// a block comment instead
+    pub fn toggle_line_comment(&mut self) {
+        let Some(token) = self.comment_tokens.line.clone() else {
+            self.toggle_block_comment();
+            return;
+        };
+        let lines: Vec<(usize, String)> = self
+            .selected_lines()
+            .into_iter()
+            .map(|line| (line, self.buffer.line_text(line)))
+            .filter(|(_, text)| !text.trim().is_empty())
+            .collect();
+        if lines.is_empty() {
+            return;
+        }
+
+        let commented = lines
+            .iter()
+            .all(|(_, text)| text.trim_start_matches([' ', '\t']).starts_with(&token));
+        let edits = if commented {
+            lines
+                .iter()
+                .map(|(line, text)| {
+                    let (col, _) = leading_indent(text, self.tab_width);
+                    let rest = &text.trim_start_matches([' ', '\t'])[token.len()..];
+                    let space = usize::from(rest.starts_with(' '));
+                    LineEdit {
+                        line: *line,
+                        col,
+                        remove: token.chars().count() + space,
+                        insert: String::new(),
+                    }
+                })
+                .collect()
+        } else {
+            // Line the tokens up at the least indented line
+            let width = lines
+                .iter()
+                .map(|(_, text)| leading_indent(text, self.tab_width).1)
+                .min()
+                .unwrap_or(0);
+            lines
+                .iter()
+                .map(|(line, text)| LineEdit {
+                    line: *line,
+                    col: self.col_at_indent(text, width),
+                    remove: 0,
+                    insert: format!("{} ", token),
+                })
+                .collect()
+        };
+        self.apply_line_edits(edits);
+    }
+
+    /// Char column in `text` where its indentation reaches `width` columns
+    fn col_at_indent(&self, text: &str, width: usize) -> usize {
+        let (indent, _) = leading_indent(text, self.tab_width);
+        (0..=indent)
+            .find(|&chars| {
+                let prefix: String = text.chars().take(chars).collect();
+                leading_indent(&prefix, self.tab_width).1 >= width
+            })
+            .unwrap_or(indent)
+    }
+
+    /// Wrap each selection (the caret's line without one) in a block
+    /// comment, or unwrap it if it already is one; filetypes without block
+    /// comments get line comments instead
+    pub fn toggle_block_comment(&mut self) {
+        let Some((open, close)) = self.comment_tokens.block.clone() else {
+            if self.comment_tokens.line.is_some() {
+                self.toggle_line_comment();
+            }
+            return;
+        };
+        let changes = self.changes_for_each(|buffer, range| {
+            let (start, end) = if range.start == range.end && !range.block {
+                // The line's text without its indentation
+                let (line, _) = buffer.char_to_line_col(range.start);
+                let text = buffer.line_text(line);
+                let indent = text.chars().count() - text.trim_start().chars().count();
+                let start = buffer.line_col_to_char(line, indent);
+                (start, start + text.trim().chars().count())
+            } else {
+                (range.start, range.end)
+            };
+            let text = buffer.slice_to_string(start, end);
+            let new_text = match text
+                .strip_prefix(open.as_str())
+                .and_then(|t| t.strip_suffix(close.as_str()))
+            {
+                Some(inner) => {
+                    let inner = inner.strip_prefix(' ').unwrap_or(inner);
+                    inner.strip_suffix(' ').unwrap_or(inner).to_string()
+                }
+                None => format!("{} {} {}", open, text, close),
+            };
+            let caret = new_text.chars().count();
+            Change::replace(start, end, new_text, caret, range.primary)
+        });
+        self.apply_changes(EditKind::Other, changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_line_comment_aligns_and_restores() {
+        let mut doc = Document::new();
+        doc.comment_tokens = CommentTokens::for_filetype("Python");
+        doc.insert_str("if x:\n    y()\n\n  z()\n");
+        doc.move_to(1, 0, false);
+        doc.move_to(4, 0, true);
+        doc.toggle_line_comment();
+        assert_eq!(doc.buffer.to_string(), "if x:\n  #   y()\n\n  # z()\n");
+        assert_eq!(doc.selected_text(), "  #   y()\n\n  # z()\n");
+
+        doc.toggle_line_comment();
+        assert_eq!(doc.buffer.to_string(), "if x:\n    y()\n\n  z()\n");
+    }
+
+    #[test]
+    fn test_toggle_block_comment() {
+        let mut doc = Document::new();
+        doc.comment_tokens = CommentTokens::for_filetype("CSS");
+        doc.insert_str("  a { color: red; }");
+        doc.toggle_block_comment();
+        assert_eq!(doc.buffer.to_string(), "  /* a { color: red; } */");
+        doc.toggle_line_comment();
+        assert_eq!(doc.buffer.to_string(), "  a { color: red; }");
+    }
+}
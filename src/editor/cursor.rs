@@ -5,8 +5,9 @@ pub struct Cursor {
     pub line: usize,
     /// Column number (0-based, in characters)
     pub col: usize,
-    /// The "wanted" column - preserved when moving up/down through shorter lines
-    pub wanted_col: usize,
+    /// The "wanted" display column - preserved when moving up/down through shorter
+    /// lines (None until the cursor first moves vertically)
+    pub wanted_col: Option<usize>,
 }
 
 impl Cursor {
@@ -15,7 +16,7 @@ impl Cursor {
         Self {
             line: 0,
             col: 0,
-            wanted_col: 0,
+            wanted_col: None,
         }
     }
 
@@ -24,7 +25,7 @@ impl Cursor {
         Self {
             line,
             col,
-            wanted_col: col,
+            wanted_col: None,
         }
     }
 
@@ -32,19 +33,19 @@ impl Cursor {
     pub fn move_to(&mut self, line: usize, col: usize) {
         self.line = line;
         self.col = col;
-        self.wanted_col = col;
+        self.wanted_col = None;
     }
 
     /// Move right by one character
     pub fn move_right(&mut self, line_len: usize, total_lines: usize) {
         if self.col < line_len {
             self.col += 1;
-            self.wanted_col = self.col;
+            self.wanted_col = None;
         } else if self.line + 1 < total_lines {
             // Move to beginning of next line
             self.line += 1;
             self.col = 0;
-            self.wanted_col = 0;
+            self.wanted_col = None;
         }
     }
 
@@ -52,60 +53,53 @@ impl Cursor {
     pub fn move_left(&mut self, prev_line_len: impl Fn(usize) -> usize) {
         if self.col > 0 {
             self.col -= 1;
-            self.wanted_col = self.col;
+            self.wanted_col = None;
         } else if self.line > 0 {
             // Move to end of previous line
             self.line -= 1;
             self.col = prev_line_len(self.line);
-            self.wanted_col = self.col;
-        }
-    }
-
-    /// Move up by one line
-    pub fn move_up(&mut self, line_len: impl Fn(usize) -> usize) {
-        if self.line > 0 {
-            self.line -= 1;
-            let len = line_len(self.line);
-            self.col = self.wanted_col.min(len);
-        }
-    }
-
-    /// Move down by one line
-    pub fn move_down(&mut self, total_lines: usize, line_len: impl Fn(usize) -> usize) {
-        if self.line + 1 < total_lines {
-            self.line += 1;
-            let len = line_len(self.line);
-            self.col = self.wanted_col.min(len);
+            self.wanted_col = None;
         }
     }
 
     /// Move to the beginning of the current line
     pub fn move_to_line_start(&mut self) {
         self.col = 0;
-        self.wanted_col = 0;
+        self.wanted_col = None;
     }
 
     /// Move to the end of the current line
     pub fn move_to_line_end(&mut self, line_len: usize) {
         self.col = line_len;
-        self.wanted_col = self.col;
+        self.wanted_col = None;
     }
 
     /// Move to the beginning of the document
     pub fn move_to_start(&mut self) {
         self.line = 0;
         self.col = 0;
-        self.wanted_col = 0;
+        self.wanted_col = None;
     }
 
     /// Move to the end of the document
     pub fn move_to_end(&mut self, total_lines: usize, last_line_len: usize) {
         self.line = total_lines.saturating_sub(1);
         self.col = last_line_len;
-        self.wanted_col = self.col;
+        self.wanted_col = None;
     }
 }
 
+/// How a selection covers the text between its anchor and head
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionMode {
+    /// Stream selection running from anchor to head
+    #[default]
+    Normal,
+    /// Rectangular selection between two display columns (tabs expanded).
+    /// The columns may lie past the end of short lines.
+    Block { anchor_col: usize, head_col: usize },
+}
+
 /// Represents a text selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Selection {
@@ -113,20 +107,60 @@ pub struct Selection {
     pub anchor: Cursor,
     /// Head position (where cursor currently is)
     pub head: Cursor,
+    /// Stream or rectangular selection
+    pub mode: SelectionMode,
 }
 
 impl Selection {
     /// Create a new selection with the same anchor and head (no selection)
     pub fn new(cursor: Cursor) -> Self {
+        Self::range(cursor, cursor)
+    }
+
+    /// Create a stream selection from `anchor` to `head`
+    pub fn range(anchor: Cursor, head: Cursor) -> Self {
         Self {
-            anchor: cursor,
-            head: cursor,
+            anchor,
+            head,
+            mode: SelectionMode::Normal,
         }
     }
 
     /// Check if there is an actual selection (anchor != head)
+    ///
+    /// A block selection only counts when it is at least one column wide; a
+    /// zero-width block is a column of carets.
     pub fn has_selection(&self) -> bool {
-        self.anchor.line != self.head.line || self.anchor.col != self.head.col
+        match self.mode {
+            SelectionMode::Normal => {
+                self.anchor.line != self.head.line || self.anchor.col != self.head.col
+            }
+            SelectionMode::Block {
+                anchor_col,
+                head_col,
+            } => anchor_col != head_col,
+        }
+    }
+
+    /// Whether this is a rectangular selection
+    pub fn is_block(&self) -> bool {
+        matches!(self.mode, SelectionMode::Block { .. })
+    }
+
+    /// For a block selection: (first line, last line, left display column, right display column)
+    pub fn block_bounds(&self) -> Option<(usize, usize, usize, usize)> {
+        match self.mode {
+            SelectionMode::Normal => None,
+            SelectionMode::Block {
+                anchor_col,
+                head_col,
+            } => Some((
+                self.anchor.line.min(self.head.line),
+                self.anchor.line.max(self.head.line),
+                anchor_col.min(head_col),
+                anchor_col.max(head_col),
+            )),
+        }
     }
 
     /// Get the start and end of the selection (ordered)
@@ -151,8 +185,11 @@ impl Selection {
     }
 
     /// Check if a position is within the selection
+    ///
+    /// Only meaningful for stream selections; block selections depend on tab
+    /// expansion and are resolved per row by the document.
     pub fn contains(&self, line: usize, col: usize) -> bool {
-        if !self.has_selection() {
+        if !self.has_selection() || self.is_block() {
             return false;
         }
 
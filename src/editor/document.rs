@@ -1,5 +1,16 @@
-use super::{Buffer, Cursor, Selection};
-use std::path::PathBuf;
+use super::bookmarks::Bookmarks;
+use super::comments::CommentTokens;
+use super::editorconfig::Editorconfig;
+use super::encoding::TextEncoding;
+use super::folding::FoldState;
+use super::hex::{is_binary, HexView};
+use super::history::{Edit, EditKind, History, Snapshot};
+use super::indent::IndentRules;
+use super::literals::LiteralRanges;
+use super::reload::DiskState;
+use super::{Buffer, Cursor, IndentStyle, Selection, SelectionMode};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Line ending style
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -27,16 +38,62 @@ impl LineEnding {
         }
     }
 
-    /// Detect line ending from text
+    /// Detect line ending from text (the most common one if they are mixed)
     pub fn detect(text: &str) -> Self {
-        if text.contains("\r\n") {
+        Self::most_common(Self::counts(text))
+    }
+
+    /// Line ending with the most of the given LF, CRLF and CR counts
+    pub(super) fn most_common([lf, crlf, cr]: [usize; 3]) -> Self {
+        if crlf > lf && crlf >= cr {
             LineEnding::CrLf
-        } else if text.contains('\r') {
+        } else if cr > lf && cr > crlf {
             LineEnding::Cr
         } else {
             LineEnding::Lf
         }
     }
+
+    /// Whether text uses more than one kind of line ending
+    pub fn is_mixed(text: &str) -> bool {
+        Self::mixed(Self::counts(text))
+    }
+
+    /// Whether the given LF, CRLF and CR counts include more than one kind
+    pub(super) fn mixed(counts: [usize; 3]) -> bool {
+        counts.iter().filter(|&&n| n > 0).count() > 1
+    }
+
+    /// Number of LF, CRLF and CR line breaks in text
+    pub(super) fn counts(text: &str) -> [usize; 3] {
+        let mut counts = [0; 3];
+        let mut bytes = text.bytes().peekable();
+        while let Some(b) = bytes.next() {
+            match b {
+                b'\n' => counts[0] += 1,
+                b'\r' if bytes.peek() == Some(&b'\n') => {
+                    bytes.next();
+                    counts[1] += 1;
+                }
+                b'\r' => counts[2] += 1,
+                _ => {}
+            }
+        }
+        counts
+    }
+
+    /// Convert every line break in text to LF, the form kept in the buffer
+    pub fn to_lf(text: &str) -> String {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    }
+
+    /// Convert LF line breaks to this line ending, for writing to disk
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            _ => text.replace('\n', self.as_str()),
+        }
+    }
 }
 
 /// A document represents an open file with its buffer, cursor, and metadata
@@ -48,14 +105,18 @@ pub struct Document {
     pub cursor: Cursor,
     /// Current selection (may be collapsed to cursor)
     pub selection: Selection,
+    /// Additional carets for multi-cursor editing (each selection's head is the caret)
+    pub secondary: Vec<Selection>,
     /// File path (None for new untitled documents)
     pub path: Option<PathBuf>,
     /// Whether the document has unsaved changes
     pub modified: bool,
-    /// Line ending style
+    /// Line ending style the file is saved with (the buffer always uses LF)
     pub line_ending: LineEnding,
-    /// Character encoding (we only support UTF-8 for now)
-    pub encoding: String,
+    /// Whether the file was opened with mixed line endings
+    pub mixed_line_endings: bool,
+    /// Character encoding the file is read and saved with
+    pub encoding: TextEncoding,
     /// Detected or set filetype
     pub filetype: String,
     /// Vertical scroll offset (first visible line)
@@ -64,6 +125,48 @@ pub struct Document {
     pub scroll_x: usize,
     /// Insert mode (true) or overwrite mode (false)
     pub insert_mode: bool,
+    /// Display width of a tab character
+    pub tab_width: usize,
+    /// Whether Tab inserts a tab or spaces
+    pub indent_style: IndentStyle,
+    /// Columns per indentation level
+    pub indent_width: usize,
+    /// Start new lines with the indentation of the previous one
+    pub auto_indent: bool,
+    /// Where the filetype's indentation changes
+    pub indent_rules: IndentRules,
+    /// Brackets and quotes closed automatically, as (opener, closer)
+    pub auto_pairs: Vec<(char, char)>,
+    /// Line and block comment tokens of the filetype
+    pub comment_tokens: CommentTokens,
+    /// Bookmarked lines
+    pub bookmarks: Bookmarks,
+    /// Properties from the file's `.editorconfig` files
+    pub editorconfig: Editorconfig,
+    /// Width to soft-wrap lines at (None = no wrapping), set by the view
+    pub wrap_width: Option<usize>,
+    /// Keep the previous contents as `file~` when saving
+    pub backup_on_save: bool,
+    /// Refuse edits and saves (e.g. a file too large to load completely)
+    pub read_only: bool,
+    /// Large-file mode: expensive whole-document features are turned off
+    pub large_file: bool,
+    /// Undo/redo history
+    pub history: History,
+    /// Counter bumped on every buffer change
+    pub version: u64,
+    /// Unique within this gterm process (names the document's swap file)
+    pub id: u64,
+    /// Bytes of a binary file shown in the hex view (the buffer is unused)
+    pub hex: Option<HexView>,
+    /// Fold regions and which are collapsed
+    pub(super) folds: FoldState,
+    /// Where the strings and comments are
+    pub(super) literals: LiteralRanges,
+    /// Encoding and line ending of the file as last read or saved
+    pub(super) saved_format: (TextEncoding, LineEnding),
+    /// Modification time and size of the file as last read or saved
+    pub(super) disk_state: Option<DiskState>,
 }
 
 impl Document {
@@ -73,61 +176,105 @@ impl Document {
             buffer: Buffer::new(),
             cursor: Cursor::new(),
             selection: Selection::default(),
+            secondary: Vec::new(),
             path: None,
             modified: false,
             line_ending: LineEnding::default(),
-            encoding: "UTF-8".to_string(),
+            mixed_line_endings: false,
+            encoding: TextEncoding::default(),
             filetype: "Plain Text".to_string(),
             scroll_y: 0,
             scroll_x: 0,
             insert_mode: true,
+            tab_width: 4,
+            indent_style: IndentStyle::Spaces,
+            indent_width: 4,
+            auto_indent: true,
+            indent_rules: IndentRules::default(),
+            auto_pairs: Vec::new(),
+            comment_tokens: CommentTokens::default(),
+            bookmarks: Bookmarks::default(),
+            editorconfig: Editorconfig::default(),
+            wrap_width: None,
+            backup_on_save: false,
+            read_only: false,
+            large_file: false,
+            history: History::new(),
+            version: 0,
+            id: next_document_id(),
+            hex: None,
+            folds: FoldState::default(),
+            literals: LiteralRanges::default(),
+            saved_format: Default::default(),
+            disk_state: None,
         }
     }
 
     /// Create a document from a string (for new unsaved documents)
     pub fn from_str(text: &str) -> Self {
-        let line_ending = LineEnding::detect(text);
         Self {
-            buffer: Buffer::from_str(text),
-            cursor: Cursor::new(),
-            selection: Selection::default(),
-            path: None,
+            buffer: Buffer::from_str(&LineEnding::to_lf(text)),
             modified: true,
-            line_ending,
-            encoding: "UTF-8".to_string(),
-            filetype: "Plain Text".to_string(),
-            scroll_y: 0,
-            scroll_x: 0,
-            insert_mode: true,
+            line_ending: LineEnding::detect(text),
+            mixed_line_endings: LineEnding::is_mixed(text),
+            history: History::unsaved(),
+            ..Self::new()
         }
     }
 
     /// Open a document from a file
     pub fn open(path: PathBuf) -> std::io::Result<Self> {
-        let text = std::fs::read_to_string(&path)?;
+        let bytes = std::fs::read(&path)?;
+        let mut doc = Self::from_bytes(path, &bytes);
+        doc.record_disk_state();
+        Ok(doc)
+    }
+
+    /// Create a document from the contents of a file
+    pub fn from_bytes(path: PathBuf, bytes: &[u8]) -> Self {
+        if is_binary(bytes) {
+            return Self::hex(path, bytes.to_vec());
+        }
+        let (text, encoding) = TextEncoding::detect(bytes);
+        let buffer = Buffer::from_str(&LineEnding::to_lf(&text));
         let line_ending = LineEnding::detect(&text);
-        let filetype = detect_filetype(&path);
+        Self::from_buffer(
+            path,
+            buffer,
+            encoding,
+            line_ending,
+            LineEnding::is_mixed(&text),
+        )
+    }
 
-        Ok(Self {
-            buffer: Buffer::from_str(&text),
-            cursor: Cursor::new(),
-            selection: Selection::default(),
+    /// Create a document from decoded file contents with LF line breaks
+    pub(super) fn from_buffer(
+        path: PathBuf,
+        buffer: Buffer,
+        encoding: TextEncoding,
+        line_ending: LineEnding,
+        mixed_line_endings: bool,
+    ) -> Self {
+        Self {
+            buffer,
+            filetype: detect_filetype(&path),
             path: Some(path),
-            modified: false,
             line_ending,
-            encoding: "UTF-8".to_string(),
-            filetype,
-            scroll_y: 0,
-            scroll_x: 0,
-            insert_mode: true,
-        })
+            mixed_line_endings,
+            encoding,
+            saved_format: (encoding, line_ending),
+            ..Self::new()
+        }
     }
 
     /// Save the document to its file path
     pub fn save(&mut self) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
         if let Some(path) = &self.path {
-            self.buffer.save_to_file(path)?;
-            self.modified = false;
+            self.write_to(path)?;
+            self.mark_saved();
             Ok(())
         } else {
             Err(std::io::Error::new(
@@ -139,13 +286,57 @@ impl Document {
 
     /// Save the document to a new path
     pub fn save_as(&mut self, path: PathBuf) -> std::io::Result<()> {
-        self.buffer.save_to_file(&path)?;
-        self.filetype = detect_filetype(&path);
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        self.write_to(&path)?;
+        if self.hex.is_none() {
+            self.filetype = detect_filetype(&path);
+        }
         self.path = Some(path);
-        self.modified = false;
+        self.mark_saved();
         Ok(())
     }
 
+    /// Write the contents to a file: the hex view's bytes, or the encoded text
+    fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        match &self.hex {
+            Some(hex) => crate::utils::fs::write_atomic(path, &hex.bytes, self.backup_on_save),
+            None => self.buffer.save_to_file(
+                path,
+                &self.encoding,
+                self.line_ending,
+                self.backup_on_save,
+            ),
+        }
+    }
+
+    /// Record that the buffer now matches the file on disk
+    pub(super) fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.hex_mark_saved();
+        self.record_disk_state();
+        self.saved_format = (self.encoding, self.line_ending);
+        self.mixed_line_endings = false;
+        self.modified = false;
+    }
+
+    /// Recompute `modified` from the history and the file format
+    pub(super) fn update_modified(&mut self) {
+        self.modified = self.history.is_modified()
+            || self.saved_format != (self.encoding, self.line_ending)
+            || self.hex.as_ref().is_some_and(HexView::is_modified)
+            // The file was deleted since it was read
+            || (self.path.is_some() && self.disk_state.is_none());
+    }
+
+    /// Save with a different line ending from now on (Document menu)
+    pub fn convert_line_endings(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+        self.mixed_line_endings = false;
+        self.update_modified();
+    }
+
     /// Get the document title (filename or "untitled")
     pub fn title(&self) -> String {
         if let Some(path) = &self.path {
@@ -168,299 +359,545 @@ impl Document {
         self.buffer.line_len(line)
     }
 
-    /// Insert a character at the cursor position
+    /// Insert a character at every caret
     pub fn insert_char(&mut self, ch: char) {
-        // Delete selection first if any
-        if self.selection.has_selection() {
-            self.delete_selection();
+        let kind = if is_word_char(ch) {
+            EditKind::TypeWord
+        } else {
+            EditKind::TypeOther
+        };
+        if self.skip_closer(ch) {
+            return;
         }
+        let len = self.buffer.len_chars();
+        let overwrite = !self.insert_mode;
 
-        let char_idx = self
-            .buffer
-            .line_col_to_char(self.cursor.line, self.cursor.col);
-
-        if self.insert_mode {
-            self.buffer.insert_char(char_idx, ch);
-        } else {
-            // Overwrite mode
-            if char_idx < self.buffer.len_chars() {
-                let current = self.buffer.char_at(char_idx);
-                if current != Some('\n') {
-                    self.buffer.delete_char(char_idx);
+        let changes = self.changes_for_each(|buffer, range| {
+            if !overwrite {
+                if let Some(change) = self.pair_change(buffer, &range, ch) {
+                    return change;
                 }
             }
-            self.buffer.insert_char(char_idx, ch);
-        }
+            // Overwrite mode replaces the character under the caret (but never a newline)
+            let end = if range.start == range.end
+                && overwrite
+                && range.start < len
+                && buffer.char_at(range.start) != Some('\n')
+            {
+                range.start + 1
+            } else {
+                range.end
+            };
+            // Typing a closer such as `}` alone on a line dedents it
+            if range.start == range.end && range.pad == 0 {
+                if let Some(change) = self.dedent_on_type(buffer, range.start, ch, range.primary) {
+                    return change;
+                }
+            }
+            let text = format!("{}{}", " ".repeat(range.pad), ch);
+            Change::replace(range.start, end, text, range.pad + 1, range.primary)
+        });
+        self.apply_changes(kind, changes);
+    }
 
-        // Move cursor
-        if ch == '\n' {
-            self.cursor.line += 1;
-            self.cursor.col = 0;
-            self.cursor.wanted_col = 0;
-        } else {
-            self.cursor.col += 1;
-            self.cursor.wanted_col = self.cursor.col;
+    /// Insert a string at every caret
+    pub fn insert_str(&mut self, text: &str) {
+        let changes = self.changes_for_each(|_, range| range.replace_with(text));
+        self.apply_changes(EditKind::Other, changes);
+    }
+
+    /// Paste text; with several carets (or block rows) and one line per caret,
+    /// each caret gets its own line
+    pub fn paste(&mut self, text: &str) {
+        let text = &LineEnding::to_lf(text);
+        let lines: Vec<&str> = text.trim_end_matches('\n').lines().collect();
+        let mut ranges = self.caret_ranges();
+        if ranges.len() < 2 || lines.len() != ranges.len() {
+            self.insert_str(text);
+            return;
         }
 
-        self.selection = Selection::new(self.cursor);
-        self.modified = true;
+        ranges.sort_by_key(|r| r.start);
+        let changes = ranges
+            .into_iter()
+            .zip(lines)
+            .map(|(range, line)| range.replace_with(line))
+            .collect();
+        self.apply_changes(EditKind::Other, changes);
     }
 
-    /// Insert a string at the cursor position
-    pub fn insert_str(&mut self, text: &str) {
-        if self.selection.has_selection() {
+    /// Delete the character before each caret (backspace)
+    pub fn backspace(&mut self) {
+        if self.has_selection() {
             self.delete_selection();
+            return;
         }
 
-        let char_idx = self
-            .buffer
-            .line_col_to_char(self.cursor.line, self.cursor.col);
-        self.buffer.insert_str(char_idx, text);
+        let changes = self.changes_for_each(|buffer, range| {
+            if let Some(change) = self.pair_delete(buffer, &range) {
+                return change;
+            }
+            let (line, col) = buffer.char_to_line_col(range.start);
+            // Rows of a block selection that end before the block are left alone
+            let start = if range.pad > 0 {
+                range.start
+            } else if col > 0 {
+                buffer.line_col_to_char(line, buffer.prev_grapheme_col(line, col))
+            } else {
+                range.start.saturating_sub(1)
+            };
+            Change::delete(start, range.start, range.primary)
+        });
+        self.apply_changes(EditKind::Backspace, changes);
+    }
 
-        // Update cursor position based on inserted text
-        let new_idx = char_idx + text.chars().count();
-        let (line, col) = self.buffer.char_to_line_col(new_idx);
-        self.cursor.line = line;
-        self.cursor.col = col;
-        self.cursor.wanted_col = col;
+    /// Delete the character at each caret (delete key)
+    pub fn delete(&mut self) {
+        if self.has_selection() {
+            self.delete_selection();
+            return;
+        }
 
-        self.selection = Selection::new(self.cursor);
-        self.modified = true;
+        let len = self.buffer.len_chars();
+        let changes = self.changes_for_each(|buffer, range| {
+            let (line, col) = buffer.char_to_line_col(range.start);
+            let end = if range.pad > 0 {
+                range.start
+            } else if col < buffer.line_len(line) {
+                buffer.line_col_to_char(line, buffer.next_grapheme_col(line, col))
+            } else {
+                (range.start + 1).min(len)
+            };
+            Change::delete(range.start, end, range.primary)
+        });
+        self.apply_changes(EditKind::Delete, changes);
     }
 
-    /// Delete the character before the cursor (backspace)
-    pub fn backspace(&mut self) {
-        if self.selection.has_selection() {
-            self.delete_selection();
+    /// Delete every selection
+    pub fn delete_selection(&mut self) {
+        if !self.has_selection() {
             return;
         }
 
-        if self.cursor.line == 0 && self.cursor.col == 0 {
-            return; // Nothing to delete
+        let changes =
+            self.changes_for_each(|_, range| Change::delete(range.start, range.end, range.primary));
+        self.apply_changes(EditKind::Other, changes);
+    }
+
+    /// Replace the whole text as one undo step, keeping the caret on the same
+    /// line and column where possible
+    pub fn replace_text(&mut self, text: &str) {
+        let (line, col) = (self.cursor.line, self.cursor.col);
+        let change = Change::replace(0, self.buffer.len_chars(), text.to_string(), 0, true);
+        self.apply_changes(EditKind::Other, vec![change]);
+        self.move_to(line, col, false);
+    }
+
+    /// Start a group of edits that will be undone as a single step
+    pub fn begin_group(&mut self) {
+        self.begin_edit(EditKind::Other);
+    }
+
+    /// Finish a group started with `begin_group`
+    pub fn end_group(&mut self) {
+        self.end_edit();
+    }
+
+    /// Undo the last transaction, restoring text and cursor state
+    pub fn undo(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+        if let Some(hex) = &mut self.hex {
+            let undone = hex.undo();
+            self.update_modified();
+            return undone;
         }
+        let Some(t) = self.history.undo().cloned() else {
+            return false;
+        };
+        for edit in t.edits.iter().rev() {
+            match edit {
+                Edit::Insert { idx, text } => self.buffer_remove(*idx, idx + text.chars().count()),
+                Edit::Delete { idx, text } => self.buffer_insert(*idx, text),
+            }
+        }
+        self.restore(t.before);
+        true
+    }
 
-        let char_idx = self
-            .buffer
-            .line_col_to_char(self.cursor.line, self.cursor.col);
-        if char_idx > 0 {
-            self.buffer.delete_char(char_idx - 1);
-            let (line, col) = self.buffer.char_to_line_col(char_idx - 1);
-            self.cursor.line = line;
-            self.cursor.col = col;
-            self.cursor.wanted_col = col;
-            self.selection = Selection::new(self.cursor);
-            self.modified = true;
+    /// Redo the last undone transaction
+    pub fn redo(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+        if let Some(hex) = &mut self.hex {
+            let redone = hex.redo();
+            self.update_modified();
+            return redone;
+        }
+        let Some(t) = self.history.redo().cloned() else {
+            return false;
+        };
+        for edit in &t.edits {
+            match edit {
+                Edit::Insert { idx, text } => self.buffer_insert(*idx, text),
+                Edit::Delete { idx, text } => self.buffer_remove(*idx, idx + text.chars().count()),
+            }
         }
+        self.restore(t.after);
+        true
     }
 
-    /// Delete the character at the cursor (delete key)
-    pub fn delete(&mut self) {
-        if self.selection.has_selection() {
-            self.delete_selection();
-            return;
+    /// Capture the cursor state for the history
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cursor: self.cursor,
+            selection: self.selection,
+            secondary: self.secondary.clone(),
         }
+    }
 
-        let char_idx = self
-            .buffer
-            .line_col_to_char(self.cursor.line, self.cursor.col);
-        if char_idx < self.buffer.len_chars() {
-            self.buffer.delete_char(char_idx);
-            self.modified = true;
+    /// Restore a cursor state captured by `snapshot`
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.cursor = snapshot.cursor;
+        self.selection = snapshot.selection;
+        self.secondary = snapshot.secondary;
+        self.update_modified();
+        self.reveal_carets();
+    }
+
+    /// Open a history transaction around a mutation
+    pub(super) fn begin_edit(&mut self, kind: EditKind) {
+        let before = self.snapshot();
+        self.history.begin(kind, before);
+    }
+
+    /// Close the history transaction opened by `begin_edit`
+    pub(super) fn end_edit(&mut self) {
+        let after = self.snapshot();
+        self.history.end(after);
+        self.update_modified();
+    }
+
+    /// Insert text into the buffer and record it in the history
+    pub(super) fn insert_at(&mut self, char_idx: usize, text: &str) {
+        if text.is_empty() {
+            return;
         }
+        let idx = char_idx.min(self.buffer.len_chars());
+        self.buffer_insert(idx, text);
+        self.history.record(Edit::Insert {
+            idx,
+            text: text.to_string(),
+        });
     }
 
-    /// Delete the current selection
-    pub fn delete_selection(&mut self) {
-        if !self.selection.has_selection() {
+    /// Remove a range from the buffer and record it in the history
+    pub(super) fn remove_range(&mut self, start: usize, end: usize) {
+        let text = self.buffer.slice_to_string(start, end);
+        if text.is_empty() {
             return;
         }
+        let idx = start.min(self.buffer.len_chars());
+        self.buffer_remove(start, end);
+        self.history.record(Edit::Delete { idx, text });
+    }
 
-        let (start, end) = self.selection.ordered();
-        let start_idx = self.buffer.line_col_to_char(start.line, start.col);
-        let end_idx = self.buffer.line_col_to_char(end.line, end.col);
+    /// Insert into the buffer, keeping collapsed folds and bookmarks on their lines
+    fn buffer_insert(&mut self, idx: usize, text: &str) {
+        let (line, col) = self.buffer.char_to_line_col(idx);
+        self.buffer.insert_str(idx, text);
+        let added = self.buffer.char_to_line_col(idx + text.chars().count()).0 - line;
+        self.folds.lines_inserted(line, added);
+        self.bookmarks.lines_inserted(line, col == 0, added);
+        self.literals.edited(line);
+        self.version += 1;
+    }
 
-        self.buffer.delete_range(start_idx, end_idx);
+    /// Remove from the buffer, keeping collapsed folds and bookmarks on their lines
+    fn buffer_remove(&mut self, start: usize, end: usize) {
+        let (first, _) = self.buffer.char_to_line_col(start);
+        let (last, _) = self.buffer.char_to_line_col(end);
+        self.buffer.delete_range(start, end);
+        self.folds.lines_removed(first, last);
+        self.bookmarks.lines_removed(first, last);
+        self.literals.edited(first);
+        self.version += 1;
+    }
 
-        self.cursor = start;
-        self.selection = Selection::new(self.cursor);
-        self.modified = true;
+    /// Number of carets (primary plus secondary)
+    pub fn caret_count(&self) -> usize {
+        1 + self.secondary.len()
     }
 
-    /// Get the selected text
-    pub fn selected_text(&self) -> String {
-        if !self.selection.has_selection() {
-            return String::new();
-        }
+    /// All selections, primary first
+    pub fn selections(&self) -> Vec<Selection> {
+        let mut all = Vec::with_capacity(self.caret_count());
+        all.push(self.selection);
+        all.extend(self.secondary.iter().copied());
+        all
+    }
 
-        let (start, end) = self.selection.ordered();
-        let start_idx = self.buffer.line_col_to_char(start.line, start.col);
-        let end_idx = self.buffer.line_col_to_char(end.line, end.col);
+    /// Whether any caret has a non-empty selection
+    pub fn has_selection(&self) -> bool {
+        self.selection.has_selection() || self.secondary.iter().any(|s| s.has_selection())
+    }
 
-        self.buffer.slice_to_string(start_idx, end_idx)
+    /// Char ranges covered by every caret; a block selection yields one range per row
+    pub(super) fn caret_ranges(&self) -> Vec<CaretRange> {
+        let mut ranges = Vec::with_capacity(self.caret_count());
+        for (i, sel) in self.selections().iter().enumerate() {
+            if sel.is_block() {
+                ranges.extend(self.block_rows(sel, i == 0));
+                continue;
+            }
+            let (start, end) = sel.ordered();
+            ranges.push(CaretRange {
+                start: self.buffer.line_col_to_char(start.line, start.col),
+                end: self.buffer.line_col_to_char(end.line, end.col),
+                primary: i == 0,
+                pad: 0,
+                block: false,
+            });
+        }
+        ranges
     }
 
-    /// Move cursor right
-    pub fn move_right(&mut self, extend_selection: bool) {
-        let line_len = self.line_len(self.cursor.line);
-        let total_lines = self.line_count();
-        self.cursor.move_right(line_len, total_lines);
+    /// Build one change per caret range
+    pub(super) fn changes_for_each(
+        &self,
+        mut f: impl FnMut(&Buffer, CaretRange) -> Change,
+    ) -> Vec<Change> {
+        self.caret_ranges()
+            .into_iter()
+            .map(|range| f(&self.buffer, range))
+            .collect()
+    }
 
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
+    /// Apply per-caret changes as one history transaction and reposition the carets
+    pub(super) fn apply_changes(&mut self, kind: EditKind, mut changes: Vec<Change>) {
+        // Binary documents are only edited through the hex view
+        if self.read_only || self.hex.is_some() {
+            return;
+        }
+        changes.sort_by_key(|c| (c.start, c.end));
+
+        // Drop changes that overlap an earlier one (e.g. two carets on the same spot)
+        let mut kept: Vec<Change> = Vec::with_capacity(changes.len());
+        for change in changes {
+            match kept.last_mut() {
+                Some(prev) if change.start < prev.end || change.start == prev.start => {
+                    prev.primary |= change.primary;
+                }
+                _ => kept.push(change),
+            }
         }
-    }
 
-    /// Move cursor left
-    pub fn move_left(&mut self, extend_selection: bool) {
-        self.cursor.move_left(|line| self.buffer.line_len(line));
+        self.begin_edit(kind);
 
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
+        // Apply back to front so earlier indices stay valid
+        for change in kept.iter().rev() {
+            self.remove_range(change.start, change.end);
+            self.insert_at(change.start, &change.text);
+        }
+
+        // Walk front to back, tracking how far earlier changes shifted later text
+        let mut delta: isize = 0;
+        let mut carets = Vec::with_capacity(kept.len());
+        let mut primary = 0;
+        for (i, change) in kept.iter().enumerate() {
+            let start = (change.start as isize + delta) as usize;
+            carets.push(start + change.caret);
+            if change.primary {
+                primary = i;
+            }
+            delta += change.text.chars().count() as isize - (change.end - change.start) as isize;
         }
+        self.set_carets(&carets, primary);
+
+        self.end_edit();
     }
 
-    /// Move cursor up
-    pub fn move_up(&mut self, extend_selection: bool) {
-        self.cursor.move_up(|line| self.buffer.line_len(line));
+    /// Replace all carets with collapsed carets at the given char indices
+    fn set_carets(&mut self, carets: &[usize], primary: usize) {
+        self.secondary.clear();
+        for (i, &idx) in carets.iter().enumerate() {
+            let (line, col) = self.buffer.char_to_line_col(idx);
+            let cursor = Cursor::at(line, col);
+            if i == primary {
+                self.cursor = cursor;
+                self.selection = Selection::new(cursor);
+            } else {
+                self.secondary.push(Selection::new(cursor));
+            }
+        }
+        self.merge_carets();
+    }
+
+    /// Get the selected text; multiple selections (and block rows) are joined with newlines
+    pub fn selected_text(&self) -> String {
+        let mut ranges = self.caret_ranges();
+        ranges.sort_by_key(|r| r.start);
+        ranges
+            .into_iter()
+            .filter(|r| r.start < r.end || r.block)
+            .map(|r| self.buffer.slice_to_string(r.start, r.end))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
+    /// Move every caret with `f`, extending or collapsing their selections
+    ///
+    /// Extending a block selection turns it back into a stream selection.
+    pub(super) fn move_carets(&mut self, extend_selection: bool, f: impl Fn(&Buffer, &mut Cursor)) {
+        f(&self.buffer, &mut self.cursor);
         if extend_selection {
             self.selection.head = self.cursor;
+            self.selection.mode = SelectionMode::Normal;
         } else {
             self.selection = Selection::new(self.cursor);
         }
+
+        for sel in &mut self.secondary {
+            let mut head = sel.head;
+            f(&self.buffer, &mut head);
+            if extend_selection {
+                sel.head = head;
+            } else {
+                *sel = Selection::new(head);
+            }
+        }
+        self.merge_carets();
+    }
+
+    /// Move cursor right by one grapheme
+    pub fn move_right(&mut self, extend_selection: bool) {
+        self.move_carets(extend_selection, |buffer, cursor| {
+            let len = buffer.line_len(cursor.line);
+            if cursor.col < len {
+                let col = buffer.next_grapheme_col(cursor.line, cursor.col);
+                cursor.move_to(cursor.line, col);
+            } else {
+                cursor.move_right(len, buffer.len_lines());
+            }
+        });
+    }
+
+    /// Move cursor left by one grapheme
+    pub fn move_left(&mut self, extend_selection: bool) {
+        self.move_carets(extend_selection, |buffer, cursor| {
+            if cursor.col > 0 {
+                let col = buffer.prev_grapheme_col(cursor.line, cursor.col);
+                cursor.move_to(cursor.line, col);
+            } else {
+                cursor.move_left(|line| buffer.line_len(line));
+            }
+        });
+    }
+
+    /// Move cursor up one screen row
+    pub fn move_up(&mut self, extend_selection: bool) {
+        self.move_vertically(extend_selection, -1);
     }
 
-    /// Move cursor down
+    /// Move cursor down one screen row
     pub fn move_down(&mut self, extend_selection: bool) {
-        let total_lines = self.line_count();
-        self.cursor
-            .move_down(total_lines, |line| self.buffer.line_len(line));
+        self.move_vertically(extend_selection, 1);
+    }
 
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+    /// Move every caret by `rows` screen rows, keeping its display column
+    fn move_vertically(&mut self, extend_selection: bool, rows: isize) {
+        let layout = self.row_layout();
+        self.move_carets(extend_selection, |buffer, cursor| {
+            *cursor = layout.move_by_rows(buffer, *cursor, rows);
+        });
     }
 
     /// Move cursor to line start
     pub fn move_to_line_start(&mut self, extend_selection: bool) {
-        self.cursor.move_to_line_start();
-
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+        self.move_carets(extend_selection, |_, cursor| cursor.move_to_line_start());
     }
 
     /// Move cursor to line end
     pub fn move_to_line_end(&mut self, extend_selection: bool) {
-        let line_len = self.line_len(self.cursor.line);
-        self.cursor.move_to_line_end(line_len);
-
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+        self.move_carets(extend_selection, |buffer, cursor| {
+            cursor.move_to_line_end(buffer.line_len(cursor.line))
+        });
     }
 
     /// Move cursor to document start
     pub fn move_to_start(&mut self, extend_selection: bool) {
-        self.cursor.move_to_start();
-
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+        self.move_carets(extend_selection, |_, cursor| cursor.move_to_start());
     }
 
     /// Move cursor to document end
     pub fn move_to_end(&mut self, extend_selection: bool) {
-        let total_lines = self.line_count();
-        let last_line_len = self.line_len(total_lines.saturating_sub(1));
-        self.cursor.move_to_end(total_lines, last_line_len);
-
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+        self.move_carets(extend_selection, |buffer, cursor| {
+            let total_lines = buffer.len_lines();
+            cursor.move_to_end(total_lines, buffer.line_len(total_lines.saturating_sub(1)))
+        });
     }
 
-    /// Move cursor to a specific line and column
+    /// Move cursor to a specific line and column (drops any secondary carets)
     pub fn move_to(&mut self, line: usize, col: usize, extend_selection: bool) {
+        self.secondary.clear();
         let line = line.min(self.line_count().saturating_sub(1));
         let col = col.min(self.line_len(line));
         self.cursor.move_to(line, col);
 
         if extend_selection {
             self.selection.head = self.cursor;
+            self.selection.mode = SelectionMode::Normal;
         } else {
             self.selection = Selection::new(self.cursor);
         }
+        self.reveal_carets();
     }
 
     /// Page up
     pub fn page_up(&mut self, page_size: usize, extend_selection: bool) {
-        if self.cursor.line > page_size {
-            self.cursor.line -= page_size;
-        } else {
-            self.cursor.line = 0;
-        }
-        let line_len = self.line_len(self.cursor.line);
-        self.cursor.col = self.cursor.wanted_col.min(line_len);
-
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+        self.move_vertically(extend_selection, -(page_size as isize));
     }
 
     /// Page down
     pub fn page_down(&mut self, page_size: usize, extend_selection: bool) {
-        let total_lines = self.line_count();
-        self.cursor.line = (self.cursor.line + page_size).min(total_lines.saturating_sub(1));
-        let line_len = self.line_len(self.cursor.line);
-        self.cursor.col = self.cursor.wanted_col.min(line_len);
-
-        if extend_selection {
-            self.selection.head = self.cursor;
-        } else {
-            self.selection = Selection::new(self.cursor);
-        }
+        self.move_vertically(extend_selection, page_size as isize);
     }
 
     /// Select all text
     pub fn select_all(&mut self) {
-        self.selection.anchor = Cursor::new();
+        self.secondary.clear();
         let total_lines = self.line_count();
         let last_line_len = self.line_len(total_lines.saturating_sub(1));
         self.cursor.move_to_end(total_lines, last_line_len);
-        self.selection.head = self.cursor;
+        self.selection = Selection::range(Cursor::new(), self.cursor);
     }
 
     /// Ensure the cursor is visible in the viewport
     pub fn ensure_cursor_visible(&mut self, visible_lines: usize, visible_cols: usize) {
-        // Vertical scrolling
-        if self.cursor.line < self.scroll_y {
+        if self.wrap_width.is_some() {
+            // Wrapped lines never scroll horizontally
+            self.scroll_x = 0;
+            self.ensure_row_visible(visible_lines);
+            return;
+        }
+        if self.has_folds() {
+            self.ensure_row_visible(visible_lines);
+        } else if self.cursor.line < self.scroll_y {
             self.scroll_y = self.cursor.line;
         } else if self.cursor.line >= self.scroll_y + visible_lines {
             self.scroll_y = self.cursor.line - visible_lines + 1;
         }
 
-        // Horizontal scrolling
-        if self.cursor.col < self.scroll_x {
-            self.scroll_x = self.cursor.col;
-        } else if self.cursor.col >= self.scroll_x + visible_cols {
-            self.scroll_x = self.cursor.col - visible_cols + 1;
+        // Horizontal scrolling works in display columns
+        let x = self
+            .buffer
+            .visual_col(self.cursor.line, self.cursor.col, self.tab_width);
+        if x < self.scroll_x {
+            self.scroll_x = x;
+        } else if x >= self.scroll_x + visible_cols {
+            self.scroll_x = x - visible_cols + 1;
         }
     }
 
@@ -476,6 +913,73 @@ impl Default for Document {
     }
 }
 
+/// A fresh document id
+fn next_document_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Error for saving a read-only document
+fn read_only_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "the document is read-only",
+    )
+}
+
+/// A replacement at one caret: `start..end` becomes `text`, leaving the caret
+/// `caret` characters into the inserted text
+#[derive(Debug, Clone)]
+pub(super) struct Change {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub caret: usize,
+    pub primary: bool,
+}
+
+impl Change {
+    pub fn replace(start: usize, end: usize, text: String, caret: usize, primary: bool) -> Self {
+        Self {
+            start,
+            end,
+            text,
+            caret,
+            primary,
+        }
+    }
+
+    pub fn delete(start: usize, end: usize, primary: bool) -> Self {
+        Self::replace(start, end, String::new(), 0, primary)
+    }
+}
+
+/// The text covered by one caret
+#[derive(Debug, Clone, Copy)]
+pub(super) struct CaretRange {
+    pub start: usize,
+    pub end: usize,
+    pub primary: bool,
+    /// Spaces to insert first so text lands at a block selection's column on a short line
+    pub pad: usize,
+    /// Whether this range is a row of a block selection
+    pub block: bool,
+}
+
+impl CaretRange {
+    /// Replace the range with `text`, leaving the caret after it
+    pub fn replace_with(&self, text: &str) -> Change {
+        let text = format!("{}{}", " ".repeat(self.pad), text);
+        let caret = text.chars().count();
+        Change::replace(self.start, self.end, text, caret, self.primary)
+    }
+}
+
+/// Whether a character is part of a word (identifier)
+pub(super) fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
 /// Detect filetype from file extension
 fn detect_filetype(path: &std::path::Path) -> String {
     let ext = path
@@ -539,3 +1043,119 @@ fn detect_filetype(path: &std::path::Path) -> String {
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_groups_typed_words() {
+        let mut doc = Document::new();
+        for ch in "hello world".chars() {
+            doc.insert_char(ch);
+        }
+        assert!(doc.modified);
+
+        assert!(doc.undo());
+        assert_eq!(doc.buffer.to_string(), "hello ");
+        assert_eq!(doc.cursor.col, 6);
+
+        assert!(doc.undo());
+        assert_eq!(doc.buffer.to_string(), "");
+        assert!(!doc.modified);
+
+        assert!(doc.redo());
+        assert!(doc.redo());
+        assert_eq!(doc.buffer.to_string(), "hello world");
+        assert_eq!(doc.cursor.col, 11);
+    }
+
+    #[test]
+    fn test_undo_restores_selection() {
+        let mut doc = Document::new();
+        doc.insert_str("one two");
+        doc.move_to(0, 4, false);
+        doc.move_to_line_end(true);
+        doc.delete_selection();
+        assert_eq!(doc.buffer.to_string(), "one ");
+
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "one two");
+        assert_eq!(doc.selected_text(), "two");
+    }
+
+    #[test]
+    fn test_group_is_single_undo_step() {
+        let mut doc = Document::new();
+        doc.insert_str("a a a");
+        doc.begin_group();
+        for col in [4, 2, 0] {
+            doc.move_to(0, col, false);
+            doc.move_right(true);
+            doc.insert_str("b");
+        }
+        doc.end_group();
+        assert_eq!(doc.buffer.to_string(), "b b b");
+
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "a a a");
+    }
+
+    #[test]
+    fn test_save_point_tracking() {
+        let mut doc = Document::new();
+        doc.insert_str("saved");
+        doc.history.mark_saved();
+        doc.modified = false;
+
+        doc.insert_char('!');
+        assert!(doc.modified);
+        doc.undo();
+        assert!(!doc.modified);
+        doc.undo();
+        assert!(doc.modified);
+        doc.redo();
+        assert!(!doc.modified);
+
+        // Branching off before the save point makes it unreachable
+        doc.undo();
+        doc.insert_char('x');
+        assert!(doc.modified);
+    }
+
+    #[test]
+    fn test_vertical_movement_keeps_display_column() {
+        let mut doc = Document::new();
+        doc.insert_str("\tx\nabcdefgh\n\u{4e2d}\u{6587}yz");
+        doc.move_to(1, 5, false);
+
+        doc.move_up(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (0, 2));
+        doc.move_down(false);
+        doc.move_down(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (2, 3));
+        doc.move_left(false);
+        doc.move_up(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (1, 4));
+    }
+
+    #[test]
+    fn test_save_normalizes_line_endings() {
+        let path = std::env::temp_dir().join(format!("gterm-eol-{}.txt", std::process::id()));
+        std::fs::write(&path, "a\r\nb\nc\r\n").unwrap();
+
+        let mut doc = Document::open(path.clone()).unwrap();
+        assert_eq!(doc.line_ending, LineEnding::CrLf);
+        assert!(doc.mixed_line_endings);
+        assert_eq!(doc.buffer.to_string(), "a\nb\nc\n");
+
+        doc.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\r\nb\r\nc\r\n");
+
+        doc.convert_line_endings(LineEnding::Cr);
+        assert!(doc.modified);
+        doc.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\rb\rc\r");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+//! EditorConfig (`.editorconfig`) support
+//!
+//! The `.editorconfig` files in a document's directory and its parents are
+//! read up to the first one marked `root = true`. Sections whose glob matches
+//! the file apply in order, and files closer to the document override those
+//! further up. See <https://editorconfig.org> for the format.
+
+use super::document::LineEnding;
+use super::{Document, IndentStyle, SaveActions, TextEncoding};
+use std::path::{Path, PathBuf};
+
+/// Largest `{n..m}` range expanded when matching globs
+const MAX_RANGE: i64 = 1000;
+
+/// A section's glob and its `(key, value)` pairs
+type Section = (String, Vec<(String, String)>);
+
+/// Indentation width: a number of columns, or one tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentSize {
+    Columns(usize),
+    Tab,
+}
+
+/// Properties that apply to one file (None = not set)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Editorconfig {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<IndentSize>,
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<LineEnding>,
+    pub charset: Option<TextEncoding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    pub max_line_length: Option<usize>,
+    /// The `.editorconfig` files that were read, nearest first
+    pub files: Vec<PathBuf>,
+}
+
+impl Editorconfig {
+    /// Properties for `path` from the `.editorconfig` files above it
+    pub fn for_file(path: &Path) -> Self {
+        let mut config = Self::default();
+        let Some(path) = absolute(path) else {
+            return config;
+        };
+
+        // Collect from the nearest file up to the root, then apply the
+        // farthest first so nearer files win
+        let mut found = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            let file = dir.join(".editorconfig");
+            let Ok(text) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            let (root, sections) = parse(&text);
+            found.push((file, dir.to_path_buf(), sections));
+            if root {
+                break;
+            }
+        }
+
+        for (_, dir, sections) in found.iter().rev() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            for (glob, pairs) in sections {
+                if section_matches(glob, &relative) {
+                    for (key, value) in pairs {
+                        config.set(key, value);
+                    }
+                }
+            }
+        }
+        config.files = found.into_iter().map(|(file, _, _)| file).collect();
+        config
+    }
+
+    /// Whether any property is set
+    pub fn is_empty(&self) -> bool {
+        Self {
+            files: Vec::new(),
+            ..self.clone()
+        } == Self::default()
+    }
+
+    /// Apply one `key = value` pair; unknown keys and values are ignored and
+    /// `unset` clears a property
+    fn set(&mut self, key: &str, value: &str) {
+        let unset = value == "unset";
+        let number = value.parse::<usize>().ok().filter(|n| *n > 0);
+        let flag = match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match key {
+            "indent_style" => {
+                self.indent_style = match value {
+                    "tab" => Some(IndentStyle::Tabs),
+                    "space" => Some(IndentStyle::Spaces),
+                    _ if unset => None,
+                    _ => self.indent_style,
+                }
+            }
+            "indent_size" => {
+                self.indent_size = match value {
+                    "tab" => Some(IndentSize::Tab),
+                    _ if unset => None,
+                    _ => number.map(IndentSize::Columns).or(self.indent_size),
+                }
+            }
+            "tab_width" if unset => self.tab_width = None,
+            "tab_width" => self.tab_width = number.or(self.tab_width),
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(LineEnding::Lf),
+                    "crlf" => Some(LineEnding::CrLf),
+                    "cr" => Some(LineEnding::Cr),
+                    _ if unset => None,
+                    _ => self.end_of_line,
+                }
+            }
+            "charset" => {
+                self.charset = match value {
+                    "utf-8-bom" => TextEncoding::for_label("utf-8", true),
+                    "latin1" | "utf-8" | "utf-16be" | "utf-16le" => {
+                        // UTF-16 is always written with a byte order mark
+                        TextEncoding::for_label(value, value.starts_with("utf-16"))
+                    }
+                    _ if unset => None,
+                    _ => self.charset,
+                }
+            }
+            "trim_trailing_whitespace" if unset => self.trim_trailing_whitespace = None,
+            "trim_trailing_whitespace" => {
+                self.trim_trailing_whitespace = flag.or(self.trim_trailing_whitespace)
+            }
+            "insert_final_newline" if unset => self.insert_final_newline = None,
+            "insert_final_newline" => {
+                self.insert_final_newline = flag.or(self.insert_final_newline)
+            }
+            "max_line_length" if value == "off" || unset => self.max_line_length = None,
+            "max_line_length" => self.max_line_length = number.or(self.max_line_length),
+            _ => {}
+        }
+    }
+
+    /// Tab width implied by the properties
+    pub fn effective_tab_width(&self) -> Option<usize> {
+        match (self.tab_width, self.indent_size) {
+            (Some(width), _) => Some(width),
+            (None, Some(IndentSize::Columns(size))) => Some(size),
+            _ => None,
+        }
+    }
+
+    /// `base` with the whitespace properties taking precedence, and the line
+    /// ending and encoding to save with
+    pub fn save_actions(&self, base: SaveActions) -> SaveActions {
+        SaveActions {
+            line_ending: self.end_of_line.or(base.line_ending),
+            encoding: self.charset.or(base.encoding),
+            strip_trailing_whitespace: self
+                .trim_trailing_whitespace
+                .unwrap_or(base.strip_trailing_whitespace),
+            final_newline: self.insert_final_newline.or(base.final_newline),
+            ..base
+        }
+    }
+}
+
+impl Document {
+    /// Use the properties from the document's `.editorconfig` files for tab
+    /// width and indentation; the rest apply when saving, through
+    /// `save_actions`
+    pub fn apply_editorconfig(&mut self, config: Editorconfig) {
+        if let Some(width) = config.effective_tab_width() {
+            self.tab_width = width;
+        }
+        if let Some(style) = config.indent_style {
+            self.indent_style = style;
+        }
+        match config.indent_size {
+            Some(IndentSize::Columns(size)) => self.indent_width = size,
+            Some(IndentSize::Tab) => self.indent_width = self.tab_width,
+            None if config.tab_width.is_some() && self.indent_style == IndentStyle::Tabs => {
+                self.indent_width = self.tab_width
+            }
+            None => {}
+        }
+
+        self.editorconfig = config;
+    }
+}
+
+/// Parse `.editorconfig` text into whether it is marked root and its
+/// sections of lowercased `(key, value)` pairs
+fn parse(text: &str) -> (bool, Vec<Section>) {
+    let mut root = false;
+    let mut sections: Vec<Section> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((glob.to_string(), Vec::new()));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_lowercase();
+        match sections.last_mut() {
+            Some((_, pairs)) => pairs.push((key, value)),
+            None if key == "root" => root = value == "true",
+            None => {}
+        }
+    }
+    (root, sections)
+}
+
+/// Whether a section glob matches a path relative to its `.editorconfig`;
+/// globs without a slash match the file name in any directory
+fn section_matches(glob: &str, relative: &str) -> bool {
+    let (glob, text) = if glob.contains('/') {
+        (glob.strip_prefix('/').unwrap_or(glob), relative)
+    } else {
+        (glob, relative.rsplit('/').next().unwrap_or(relative))
+    };
+    expand_braces(glob).iter().any(|pattern| {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    })
+}
+
+/// Expand `{a,b}` alternatives and `{n..m}` ranges into plain globs
+fn expand_braces(glob: &str) -> Vec<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let escaped = |i: usize| i > 0 && chars[i - 1] == '\\';
+    let Some(open) = (0..chars.len()).find(|&i| chars[i] == '{' && !escaped(i)) else {
+        return vec![glob.to_string()];
+    };
+
+    // Find the matching close brace and the top-level commas
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut close = None;
+    for (i, &c) in chars.iter().enumerate().skip(open) {
+        if escaped(i) {
+            continue;
+        }
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            ',' if depth == 1 => commas.push(i),
+            _ => {}
+        }
+    }
+    let Some(close) = close else {
+        // An unclosed brace is literal
+        return vec![glob.to_string()];
+    };
+
+    let prefix: String = chars[..open].iter().collect();
+    let inner: String = chars[open + 1..close].iter().collect();
+    let suffix: String = chars[close + 1..].iter().collect();
+
+    let alternatives: Vec<String> = if commas.is_empty() {
+        match range(&inner) {
+            Some((from, to)) => (from..=to).map(|n| n.to_string()).collect(),
+            // A single alternative is literal
+            None => {
+                return expand_braces(&suffix)
+                    .into_iter()
+                    .map(|rest| format!("{}{{{}}}{}", prefix, inner, rest))
+                    .collect()
+            }
+        }
+    } else {
+        let mut parts = Vec::new();
+        let mut start = open + 1;
+        for &comma in commas.iter().chain(std::iter::once(&close)) {
+            parts.push(chars[start..comma].iter().collect());
+            start = comma + 1;
+        }
+        parts
+    };
+
+    alternatives
+        .iter()
+        .flat_map(|alt| expand_braces(&format!("{}{}{}", prefix, alt, suffix)))
+        .collect()
+}
+
+/// Parse `n..m` (with n <= m and a sane length)
+fn range(text: &str) -> Option<(i64, i64)> {
+    let (from, to) = text.split_once("..")?;
+    let (from, to) = (from.parse().ok()?, to.parse().ok()?);
+    (from <= to && to - from <= MAX_RANGE).then_some((from, to))
+}
+
+/// Match a brace-free glob: `*` (within a path segment), `**` (across
+/// segments), `?`, `[set]`, `[!set]` and `\` escapes
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let Some(&first) = pattern.first() else {
+        return text.is_empty();
+    };
+    match first {
+        '*' if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        '*' => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        '?' => text.first().is_some_and(|&c| c != '/') && glob_match(&pattern[1..], &text[1..]),
+        '[' => match class_end(pattern) {
+            Some(end) => {
+                let Some(&c) = text.first() else {
+                    return false;
+                };
+                class_matches(&pattern[1..end], c) && glob_match(&pattern[end + 1..], &text[1..])
+            }
+            None => text.first() == Some(&'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        '\\' if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        _ => text.first() == Some(&first) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Index of the `]` closing a character class starting at `pattern[0]`
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let start = if pattern.get(1) == Some(&'!') { 2 } else { 1 };
+    // A `]` right after the opening bracket is part of the set
+    (start + 1..pattern.len()).find(|&i| pattern[i] == ']')
+}
+
+/// Whether `c` is in a character class body such as `a-z0` or `!ab`
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated && c != '/'
+}
+
+/// Absolute form of a path, without `.` and `..` components
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().ok()?.join(path)
+    };
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                clean.pop();
+            }
+            other => clean.push(other),
+        }
+    }
+    Some(clean)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(section_matches("*", "src/main.rs"));
+        assert!(section_matches("*.{rs,toml}", "src/main.rs"));
+        assert!(section_matches("Makefile", "sub/Makefile"));
+        assert!(!section_matches("*.py", "main.rs"));
+        assert!(section_matches("src/*.rs", "src/main.rs"));
+        assert!(!section_matches("src/*.rs", "src/editor/mod.rs"));
+        assert!(section_matches("/src/**.rs", "src/editor/mod.rs"));
+        assert!(section_matches("file[0-9].txt", "file7.txt"));
+        assert!(!section_matches("file[!0-9].txt", "file7.txt"));
+        assert!(section_matches("v{1..12}.md", "v10.md"));
+        assert!(!section_matches("v{1..12}.md", "v13.md"));
+        assert!(section_matches("a?c", "abc"));
+    }
+
+    #[test]
+    fn test_nearer_files_override_and_root_stops() {
+        let base = std::env::temp_dir().join(format!("gterm-ec-{}", std::process::id()));
+        let sub = base.join("project").join("src");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(base.join(".editorconfig"), "[*]\nmax_line_length = 80\n").unwrap();
+        std::fs::write(
+            base.join("project").join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = lf\n\n\
+             [*.md]\ntrim_trailing_whitespace = false\n",
+        )
+        .unwrap();
+        std::fs::write(
+            sub.join(".editorconfig"),
+            "# tabs here\n[*.rs]\nindent_style = tab\ntab_width = 8\n",
+        )
+        .unwrap();
+
+        let config = Editorconfig::for_file(&sub.join("main.rs"));
+        assert_eq!(config.indent_style, Some(IndentStyle::Tabs));
+        assert_eq!(config.effective_tab_width(), Some(8));
+        assert_eq!(config.indent_size, Some(IndentSize::Columns(4)));
+        assert_eq!(config.end_of_line, Some(LineEnding::Lf));
+        // The file above the root is not read
+        assert_eq!((config.max_line_length, config.files.len()), (None, 2));
+
+        let readme = Editorconfig::for_file(&base.join("project").join("README.md"));
+        assert_eq!(readme.indent_style, Some(IndentStyle::Spaces));
+        assert_eq!(readme.trim_trailing_whitespace, Some(false));
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_format_applies_on_save_only() {
+        let mut doc = Document::from_bytes(PathBuf::from("a.txt"), b"a\r\nb\r\n");
+        let config = Editorconfig {
+            end_of_line: Some(LineEnding::Lf),
+            charset: TextEncoding::for_label("latin1", false),
+            ..Editorconfig::default()
+        };
+        doc.apply_editorconfig(config);
+        assert!(!doc.modified);
+        assert_eq!(doc.line_ending, LineEnding::CrLf);
+
+        let actions = doc.editorconfig.save_actions(SaveActions::default());
+        doc.apply_save_actions(&actions);
+        assert_eq!(doc.line_ending, LineEnding::Lf);
+        assert_eq!(doc.encoding.encoding.name(), "windows-1252");
+
+        // A line ending chosen since the file was read is kept
+        doc.convert_line_endings(LineEnding::Cr);
+        doc.apply_save_actions(&actions);
+        assert_eq!(doc.line_ending, LineEnding::Cr);
+    }
+}
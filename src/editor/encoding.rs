@@ -0,0 +1,262 @@
+//! Character encodings for reading and writing files
+//!
+//! Files are decoded to UTF-8 for editing and encoded back on save with the
+//! encoding (and byte order mark) they were read with. Detection checks for a
+//! BOM first, then for UTF-16 without one, then valid UTF-8, and finally
+//! guesses a legacy charset from the byte statistics.
+
+use super::document::LineEnding;
+use super::Document;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Encodings offered in the encoding dialog, by WHATWG name
+pub const ENCODINGS: &[&str] = &[
+    "UTF-8",
+    "UTF-16LE",
+    "UTF-16BE",
+    "windows-1252",
+    "ISO-8859-15",
+    "ISO-8859-2",
+    "windows-1250",
+    "windows-1251",
+    "KOI8-R",
+    "ISO-8859-7",
+    "windows-1253",
+    "windows-1254",
+    "windows-1256",
+    "Shift_JIS",
+    "EUC-JP",
+    "GBK",
+    "gb18030",
+    "Big5",
+    "EUC-KR",
+];
+
+/// Bytes of a file examined when guessing a legacy charset
+const DETECT_SAMPLE: usize = 1024 * 1024;
+
+/// The encoding of a file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
+impl TextEncoding {
+    /// Look up an encoding by name or label (e.g. "latin1")
+    pub fn for_label(label: &str, bom: bool) -> Option<Self> {
+        let encoding = Encoding::for_label(label.as_bytes())?;
+        Some(Self { encoding, bom })
+    }
+
+    /// Name for the status bar, e.g. "UTF-8" or "UTF-16LE BOM"
+    pub fn display_name(&self) -> String {
+        if self.bom {
+            format!("{} BOM", self.encoding.name())
+        } else {
+            self.encoding.name().to_string()
+        }
+    }
+
+    /// Whether the encoding can represent all of Unicode
+    fn is_unicode(&self) -> bool {
+        self.encoding == UTF_8 || self.encoding == UTF_16LE || self.encoding == UTF_16BE
+    }
+
+    /// Detect the encoding of file contents and decode them
+    pub fn detect(bytes: &[u8]) -> (String, Self) {
+        let encoding = Self::detect_start(bytes, true);
+        (encoding.decode(bytes), encoding)
+    }
+
+    /// Detect the encoding from the start of a file; unless `complete` (the
+    /// bytes are the whole file) they may end inside a character
+    pub fn detect_start(bytes: &[u8], complete: bool) -> Self {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            return Self {
+                encoding,
+                bom: true,
+            };
+        }
+
+        let whole_units = if complete {
+            bytes
+        } else {
+            &bytes[..bytes.len() & !1]
+        };
+        let utf8 = match std::str::from_utf8(bytes) {
+            Ok(_) => true,
+            Err(e) => !complete && e.error_len().is_none(),
+        };
+        let encoding = match detect_utf16(whole_units) {
+            Some(encoding) => encoding,
+            None if utf8 => UTF_8,
+            None => {
+                // A sample is enough to guess from, and keeps large files fast
+                let sample = &bytes[..bytes.len().min(DETECT_SAMPLE)];
+                let mut detector = chardetng::EncodingDetector::new();
+                detector.feed(sample, complete && sample.len() == bytes.len());
+                detector.guess(None, false)
+            }
+        };
+        Self {
+            encoding,
+            bom: false,
+        }
+    }
+
+    /// Decode file contents with a chosen encoding, noting whether they start
+    /// with its BOM
+    pub fn decode_as(bytes: &[u8], encoding: &'static Encoding) -> (String, Self) {
+        let bom = Encoding::for_bom(bytes).is_some_and(|(e, _)| e == encoding);
+        let encoding = Self { encoding, bom };
+        (encoding.decode(bytes), encoding)
+    }
+
+    /// Decode file contents with this encoding; a BOM is skipped when present
+    /// and invalid sequences become U+FFFD
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let bytes = match Encoding::for_bom(bytes) {
+            Some((encoding, len)) if encoding == self.encoding => &bytes[len..],
+            _ => bytes,
+        };
+        let (text, _) = self.encoding.decode_without_bom_handling(bytes);
+        text.into_owned()
+    }
+
+    /// Encode text for writing, failing if a character cannot be represented
+    pub fn encode(&self, text: &str) -> std::io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // encoding_rs only decodes UTF-16
+            let little_endian = self.encoding == UTF_16LE;
+            let units = std::iter::once(0xFEFF)
+                .filter(|_| self.bom)
+                .chain(text.encode_utf16());
+            for unit in units {
+                if little_endian {
+                    bytes.extend_from_slice(&unit.to_le_bytes());
+                } else {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            return Ok(bytes);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        let (encoded, _, had_errors) = self.encoding.encode(text);
+        if had_errors && !self.is_unicode() {
+            let bad = text
+                .chars()
+                .find(|ch| {
+                    let mut buf = [0; 4];
+                    self.encoding.encode(ch.encode_utf8(&mut buf)).2
+                })
+                .unwrap_or('?');
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "'{}' (U+{:04X}) cannot be saved as {}",
+                    bad,
+                    bad as u32,
+                    self.encoding.name()
+                ),
+            ));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+impl Document {
+    /// Save with a different encoding from now on (marks the document modified)
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        self.encoding = encoding;
+        self.update_modified();
+    }
+
+    /// Re-read the file decoding it with `encoding`; the replaced text can be
+    /// brought back with undo
+    pub fn reopen_with_encoding(&mut self, encoding: &'static Encoding) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No file path set",
+            ));
+        };
+        let bytes = std::fs::read(path)?;
+        let (text, encoding) = TextEncoding::decode_as(&bytes, encoding);
+
+        self.replace_text(&LineEnding::to_lf(&text));
+        self.encoding = encoding;
+        self.line_ending = LineEnding::detect(&text);
+        self.mark_saved();
+        self.mixed_line_endings = LineEnding::is_mixed(&text);
+        Ok(())
+    }
+}
+
+/// Recognize BOM-less UTF-16 from the zero high bytes of ASCII text
+pub(super) fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    // Mostly-ASCII UTF-16 has a zero in every other byte and almost none in
+    // the rest
+    if odd_zeros * 10 >= pairs * 7 && even_zeros * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 7 && odd_zeros * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_bom_and_utf16() {
+        let (text, enc) = TextEncoding::detect(b"\xEF\xBB\xBFhi");
+        assert_eq!(
+            (text.as_str(), enc.display_name()),
+            ("hi", "UTF-8 BOM".into())
+        );
+
+        let (text, enc) = TextEncoding::detect(b"h\0i\0!\0\n\0");
+        assert_eq!((text.as_str(), enc.encoding), ("hi!\n", UTF_16LE));
+        assert_eq!(enc.encode(&text).unwrap(), b"h\0i\0!\0\n\0");
+
+        let (_, enc) = TextEncoding::detect(b"\xFE\xFF\0h\0i");
+        assert_eq!(enc.encode("hi").unwrap(), b"\xFE\xFF\0h\0i");
+
+        let (text, enc) = TextEncoding::decode_as(b"\xFE\xFF\0h\0i", UTF_16BE);
+        assert_eq!((text.as_str(), enc.bom), ("hi", true));
+    }
+
+    #[test]
+    fn test_legacy_fallback_round_trips() {
+        let bytes = b"Caf\xE9 cr\xE8me br\xFBl\xE9e, d\xE9j\xE0 vu\n";
+        let (text, enc) = TextEncoding::detect(bytes);
+        assert_eq!(text, "Café crème brûlée, déjà vu\n");
+        assert_eq!(enc.encode(&text).unwrap(), bytes);
+        assert!(enc.encode("日本").is_err());
+    }
+}
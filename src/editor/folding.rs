@@ -0,0 +1,388 @@
+//! Code folding for `Document`
+//!
+//! Fold regions come from indentation (a line followed by more-indented lines)
+//! and, when the view can supply string/comment ranges from the syntax
+//! highlighter, from bracket pairs spanning several lines. A region keeps its
+//! first line visible and hides the rest. Regions are recomputed lazily after
+//! edits; collapsed regions are shifted as lines are inserted and removed.
+
+use super::{Buffer, Document};
+use std::ops::Range;
+
+/// Lines `start + 1..=end` can be hidden under the header line `start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldRegion {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl FoldRegion {
+    /// Whether this region hides `line` when collapsed
+    fn hides(&self, line: usize) -> bool {
+        line > self.start && line <= self.end
+    }
+
+    fn contains(&self, line: usize) -> bool {
+        line >= self.start && line <= self.end
+    }
+}
+
+/// Fold regions of a document and which of them are collapsed
+#[derive(Debug, Clone, Default)]
+pub struct FoldState {
+    /// Foldable regions, sorted by start line
+    regions: Vec<FoldRegion>,
+    /// Document version `regions` were computed for
+    version: Option<u64>,
+    /// Collapsed regions
+    collapsed: Vec<FoldRegion>,
+}
+
+impl FoldState {
+    /// Shift collapsed regions after `count` lines were inserted below `line`
+    pub(super) fn lines_inserted(&mut self, line: usize, count: usize) {
+        if count == 0 {
+            return;
+        }
+        for region in &mut self.collapsed {
+            if region.start > line {
+                region.start += count;
+                region.end += count;
+            } else if region.end >= line {
+                region.end += count;
+            }
+        }
+    }
+
+    /// Shift collapsed regions after lines `first + 1..=last` were joined into `first`;
+    /// regions overlapping the removed lines are expanded
+    pub(super) fn lines_removed(&mut self, first: usize, last: usize) {
+        let count = last - first;
+        if count == 0 {
+            return;
+        }
+        self.collapsed
+            .retain(|r| r.end < first || r.start > last || (r.start <= first && r.end >= last));
+        for region in &mut self.collapsed {
+            if region.start > last {
+                region.start -= count;
+                region.end -= count;
+            } else if region.end >= last {
+                region.end -= count;
+            }
+        }
+        self.collapsed.retain(|r| r.end > r.start);
+    }
+}
+
+/// Regions from indentation: each line heads the following lines that are
+/// blank or indented deeper than it
+fn indent_regions(buffer: &Buffer, tab_width: usize) -> Vec<FoldRegion> {
+    let mut regions = Vec::new();
+    // Open candidates as (indent, start line)
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut last_text_line = 0;
+
+    for line in 0..buffer.len_lines() {
+        let Some(indent) = indent_width(&buffer.line_text(line), tab_width) else {
+            continue;
+        };
+        while let Some(&(open_indent, start)) = stack.last() {
+            if open_indent < indent {
+                break;
+            }
+            stack.pop();
+            if last_text_line > start {
+                regions.push(FoldRegion {
+                    start,
+                    end: last_text_line,
+                });
+            }
+        }
+        stack.push((indent, line));
+        last_text_line = line;
+    }
+    for (_, start) in stack {
+        if last_text_line > start {
+            regions.push(FoldRegion {
+                start,
+                end: last_text_line,
+            });
+        }
+    }
+    regions
+}
+
+/// Indentation of a line in columns, or None for a blank line
+fn indent_width(text: &str, tab_width: usize) -> Option<usize> {
+    let mut width = 0;
+    for ch in text.chars() {
+        match ch {
+            ' ' => width += 1,
+            '\t' => width += tab_width,
+            c if c.is_whitespace() => {}
+            _ => return Some(width),
+        }
+    }
+    None
+}
+
+/// Regions from bracket pairs spanning several lines; the closing line stays
+/// visible. Characters inside `literals` (strings and comments, as char ranges
+/// per line) are skipped.
+fn bracket_regions(buffer: &Buffer, literals: &[Vec<Range<usize>>]) -> Vec<FoldRegion> {
+    let mut regions = Vec::new();
+    let mut stack: Vec<(char, usize)> = Vec::new();
+
+    for line in 0..buffer.len_lines() {
+        let skip = literals.get(line).map(Vec::as_slice).unwrap_or(&[]);
+        for (col, ch) in buffer.line_text(line).chars().enumerate() {
+            if skip.iter().any(|r| r.contains(&col)) {
+                continue;
+            }
+            match ch {
+                '{' | '[' | '(' => stack.push((ch, line)),
+                '}' | ']' | ')' => {
+                    let open = match ch {
+                        '}' => '{',
+                        ']' => '[',
+                        _ => '(',
+                    };
+                    if let Some(i) = stack.iter().rposition(|&(c, _)| c == open) {
+                        let (_, start) = stack[i];
+                        stack.truncate(i);
+                        if line > start + 1 {
+                            regions.push(FoldRegion {
+                                start,
+                                end: line - 1,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    regions
+}
+
+impl Document {
+    /// Whether the fold regions need recomputing (after an edit)
+    pub fn folds_outdated(&self) -> bool {
+        self.folds.version != Some(self.version)
+    }
+
+    /// Recompute fold regions; bracket folding needs the string/comment
+    /// ranges of the whole document (see `scan_literals`)
+    pub fn update_folds(&mut self) {
+        let mut regions = indent_regions(&self.buffer, self.tab_width);
+        if self.literals_complete() {
+            regions.extend(bracket_regions(&self.buffer, &self.literals.lines));
+        }
+
+        // One region per header line, the largest wins
+        regions.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+        regions.dedup_by_key(|r| r.start);
+
+        let line_count = self.line_count();
+        self.folds.collapsed.retain(|r| r.end < line_count);
+        self.folds.regions = regions;
+        self.folds.version = Some(self.version);
+    }
+
+    /// Whether `line` is hidden inside a collapsed region
+    pub fn is_line_hidden(&self, line: usize) -> bool {
+        self.folds.collapsed.iter().any(|r| r.hides(line))
+    }
+
+    /// First line at or after `line` that is not hidden
+    pub fn next_visible_line(&self, mut line: usize) -> usize {
+        while let Some(region) = self.folds.collapsed.iter().find(|r| r.hides(line)) {
+            line = region.end + 1;
+        }
+        line
+    }
+
+    /// Scroll the view by `delta` visible lines, stepping over folded ones
+    pub fn scroll_lines(&mut self, delta: isize) {
+        let last_line = self.line_count().saturating_sub(1);
+        for _ in 0..delta.unsigned_abs() {
+            if delta > 0 {
+                let next = self.next_visible_line(self.scroll_y + 1);
+                if next > last_line {
+                    break;
+                }
+                self.scroll_y = next;
+            } else {
+                let Some(mut line) = self.scroll_y.checked_sub(1) else {
+                    break;
+                };
+                while self.is_line_hidden(line) {
+                    line -= 1;
+                }
+                self.scroll_y = line;
+            }
+        }
+    }
+
+    /// Whether any region is collapsed
+    pub fn has_folds(&self) -> bool {
+        !self.folds.collapsed.is_empty()
+    }
+
+    /// Gutter marker for a line: Some(collapsed) if a region starts there;
+    /// expanded regions are not marked while the regions are outdated
+    pub fn fold_marker(&self, line: usize) -> Option<bool> {
+        if self.folds.collapsed.iter().any(|r| r.start == line) {
+            return Some(true);
+        }
+        if self.folds_outdated() {
+            return None;
+        }
+        self.folds
+            .regions
+            .iter()
+            .any(|r| r.start == line)
+            .then_some(false)
+    }
+
+    /// Collapse the innermost region containing `line`
+    pub fn fold_at(&mut self, line: usize) {
+        let Some(region) = self
+            .folds
+            .regions
+            .iter()
+            .filter(|r| r.contains(line))
+            .max_by_key(|r| r.start)
+            .copied()
+        else {
+            return;
+        };
+        if !self.folds.collapsed.contains(&region) {
+            self.folds.collapsed.push(region);
+        }
+        self.move_carets_out_of_folds();
+    }
+
+    /// Expand every collapsed region containing `line`
+    pub fn unfold_at(&mut self, line: usize) {
+        self.folds.collapsed.retain(|r| !r.contains(line));
+    }
+
+    /// Collapse or expand the region headed by `line` (gutter click)
+    pub fn toggle_fold(&mut self, line: usize) {
+        if self.folds.collapsed.iter().any(|r| r.start == line) {
+            self.folds.collapsed.retain(|r| r.start != line);
+        } else if let Some(&region) = self.folds.regions.iter().find(|r| r.start == line) {
+            self.folds.collapsed.push(region);
+            self.move_carets_out_of_folds();
+        }
+    }
+
+    /// Collapse every region
+    pub fn fold_all(&mut self) {
+        self.folds.collapsed = self.folds.regions.clone();
+        self.move_carets_out_of_folds();
+    }
+
+    /// Expand every region
+    pub fn unfold_all(&mut self) {
+        self.folds.collapsed.clear();
+    }
+
+    /// Expand any collapsed region hiding a caret, e.g. after the caret moved
+    /// into it
+    pub(super) fn reveal_carets(&mut self) {
+        if self.folds.collapsed.is_empty() {
+            return;
+        }
+        let lines: Vec<usize> = self.selections().iter().map(|s| s.head.line).collect();
+        self.folds
+            .collapsed
+            .retain(|r| !lines.iter().any(|&line| r.hides(line)));
+    }
+
+    /// Put the primary caret on the header of a region that now hides it, and
+    /// drop hidden secondary carets
+    fn move_carets_out_of_folds(&mut self) {
+        let hidden: Vec<bool> = self
+            .secondary
+            .iter()
+            .map(|s| self.is_line_hidden(s.head.line))
+            .collect();
+        let mut hidden = hidden.into_iter();
+        self.secondary.retain(|_| !hidden.next().unwrap_or(false));
+
+        if let Some(header) = self
+            .folds
+            .collapsed
+            .iter()
+            .filter(|r| r.hides(self.cursor.line))
+            .map(|r| r.start)
+            .min()
+        {
+            let col = self.line_len(header);
+            self.move_to(header, col, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_with(text: &str) -> Document {
+        let mut doc = Document::new();
+        doc.insert_str(text);
+        doc.move_to(0, 0, false);
+        doc.update_folds();
+        doc
+    }
+
+    #[test]
+    fn test_indent_regions() {
+        let doc = doc_with("def a():\n    x = 1\n\n    y = 2\nb = 3\n");
+        assert_eq!(doc.folds.regions, vec![FoldRegion { start: 0, end: 3 }]);
+    }
+
+    #[test]
+    fn test_bracket_regions_skip_literals() {
+        let buffer = Buffer::from_str("let a = [\n  \"{\",\n  2,\n];");
+        let literals = vec![vec![], vec![2..5]];
+        assert_eq!(
+            bracket_regions(&buffer, &literals),
+            vec![FoldRegion { start: 0, end: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_fold_hides_lines_and_movement_reveals() {
+        let mut doc = doc_with("fn a() {\n    1;\n    2;\n}\nfn b() {}");
+        doc.move_to(2, 0, false);
+        doc.fold_at(2);
+        assert!(doc.is_line_hidden(1) && doc.is_line_hidden(2));
+        assert_eq!(doc.cursor.line, 0);
+        assert_eq!(doc.next_visible_line(1), 3);
+
+        doc.move_down(false);
+        assert!(!doc.has_folds());
+    }
+
+    #[test]
+    fn test_collapsed_regions_follow_edits() {
+        let mut doc = doc_with("a\nb:\n  c\n  d\ne");
+        doc.toggle_fold(1);
+        assert_eq!(doc.fold_marker(1), Some(true));
+
+        doc.move_to(0, 0, false);
+        doc.insert_str("new\n");
+        assert_eq!(doc.fold_marker(2), Some(true));
+        assert!(doc.is_line_hidden(3) && doc.is_line_hidden(4));
+
+        // Expanded regions are only marked again once recomputed
+        doc.unfold_all();
+        assert_eq!(doc.fold_marker(2), None);
+        doc.update_folds();
+        assert_eq!(doc.fold_marker(2), Some(false));
+    }
+}
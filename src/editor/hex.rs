@@ -0,0 +1,326 @@
+//! Hex view for binary files
+//!
+//! A binary document keeps its bytes in a `HexView` instead of the text
+//! buffer. The view shows rows of offset, hex bytes and ASCII; editing
+//! overwrites bytes in place (the file never grows or shrinks), typing hex
+//! digits in the hex column or characters in the ASCII column.
+
+use super::encoding::detect_utf16;
+use super::Document;
+use encoding_rs::Encoding;
+use std::path::PathBuf;
+
+/// Bytes shown on each row
+pub const BYTES_PER_ROW: usize = 16;
+
+/// Bytes examined when deciding whether a file is binary
+const DETECT_SAMPLE: usize = 8192;
+
+/// Whether file contents look binary rather than text: a NUL byte or many
+/// control characters near the start, unless they are UTF-16 text
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(DETECT_SAMPLE)];
+    if Encoding::for_bom(sample).is_some() {
+        return false;
+    }
+    let even = &sample[..sample.len() & !1];
+    if detect_utf16(even).is_some() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Bytes of a binary document and the hex cursor
+#[derive(Debug, Clone, Default)]
+pub struct HexView {
+    pub bytes: Vec<u8>,
+    /// Offset of the byte under the cursor
+    pub cursor: usize,
+    /// Whether the next hex digit sets the low half of the byte
+    pub low_nibble: bool,
+    /// Whether typing goes to the ASCII column instead of the hex column
+    pub ascii: bool,
+    /// First visible row
+    pub scroll_row: usize,
+    /// Overwrites as (offset, previous byte), most recent last
+    undo: Vec<(usize, u8)>,
+    redo: Vec<(usize, u8)>,
+    /// Undo depth matching the file on disk (None once that state is gone)
+    saved_depth: Option<usize>,
+}
+
+impl HexView {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            saved_depth: Some(0),
+            ..Self::default()
+        }
+    }
+
+    /// Move the cursor by `delta` bytes, staying inside the file
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.bytes.len().saturating_sub(1) as isize;
+        self.move_to((self.cursor as isize + delta).clamp(0, last.max(0)) as usize);
+    }
+
+    /// Move the cursor to an offset
+    pub fn move_to(&mut self, offset: usize) {
+        self.cursor = offset.min(self.bytes.len().saturating_sub(1));
+        self.low_nibble = false;
+    }
+
+    /// Move to the first or last byte of the cursor's row
+    pub fn move_to_row_edge(&mut self, end: bool) {
+        let start = self.cursor - self.cursor % BYTES_PER_ROW;
+        self.move_to(if end {
+            start + BYTES_PER_ROW - 1
+        } else {
+            start
+        });
+    }
+
+    /// Switch typing between the hex and ASCII columns
+    pub fn toggle_column(&mut self) {
+        self.ascii = !self.ascii;
+        self.low_nibble = false;
+    }
+
+    /// Overwrite the byte under the cursor from a typed key: a hex digit in
+    /// the hex column, a printable ASCII character in the ASCII column.
+    /// Returns whether the key was used.
+    pub fn type_char(&mut self, ch: char) -> bool {
+        if self.cursor >= self.bytes.len() {
+            return false;
+        }
+        let old = self.bytes[self.cursor];
+        let new = if self.ascii {
+            if !(ch.is_ascii_graphic() || ch == ' ') {
+                return false;
+            }
+            ch as u8
+        } else {
+            let Some(digit) = ch.to_digit(16) else {
+                return false;
+            };
+            if self.low_nibble {
+                (old & 0xF0) | digit as u8
+            } else {
+                (old & 0x0F) | (digit as u8) << 4
+            }
+        };
+        // The two digits of a byte are one undo step, unless the file was
+        // saved in between
+        let merge = !self.ascii
+            && self.low_nibble
+            && self
+                .undo
+                .last()
+                .is_some_and(|&(offset, _)| offset == self.cursor)
+            && self.saved_depth != Some(self.undo.len());
+        if merge {
+            self.bytes[self.cursor] = new;
+        } else {
+            self.write(self.cursor, new);
+        }
+
+        if self.ascii || self.low_nibble {
+            self.move_by(1);
+        } else {
+            self.low_nibble = true;
+        }
+        true
+    }
+
+    /// Overwrite one byte, recording it for undo
+    fn write(&mut self, offset: usize, byte: u8) {
+        // Changes made after an undo can never return to the saved state
+        // through history if it lay in the discarded redo steps
+        if self
+            .saved_depth
+            .is_some_and(|depth| depth > self.undo.len())
+        {
+            self.saved_depth = None;
+        }
+        self.redo.clear();
+        self.undo.push((offset, self.bytes[offset]));
+        self.bytes[offset] = byte;
+    }
+
+    /// Revert the last overwrite
+    pub fn undo(&mut self) -> bool {
+        let Some((offset, byte)) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push((offset, self.bytes[offset]));
+        self.bytes[offset] = byte;
+        self.move_to(offset);
+        true
+    }
+
+    /// Reapply the last undone overwrite
+    pub fn redo(&mut self) -> bool {
+        let Some((offset, byte)) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push((offset, self.bytes[offset]));
+        self.bytes[offset] = byte;
+        self.move_to(offset);
+        true
+    }
+
+    /// Whether the bytes differ from the file as last read or saved
+    pub fn is_modified(&self) -> bool {
+        self.saved_depth != Some(self.undo.len())
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved_depth = Some(self.undo.len());
+    }
+
+    /// Scroll so the cursor's row is among `rows` visible rows
+    pub fn ensure_cursor_visible(&mut self, rows: usize) {
+        let row = self.cursor / BYTES_PER_ROW;
+        if row < self.scroll_row {
+            self.scroll_row = row;
+        } else if rows > 0 && row >= self.scroll_row + rows {
+            self.scroll_row = row + 1 - rows;
+        }
+    }
+
+    /// Search query as bytes: hex pairs such as `DE AD be ef`, otherwise the
+    /// query's own (UTF-8) bytes
+    pub fn parse_pattern(query: &str) -> Vec<u8> {
+        let digits: String = query.chars().filter(|c| !c.is_whitespace()).collect();
+        if !digits.is_empty()
+            && digits.len().is_multiple_of(2)
+            && digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return (0..digits.len())
+                .step_by(2)
+                .filter_map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+                .collect();
+        }
+        query.as_bytes().to_vec()
+    }
+
+    /// Move to the next (or previous) occurrence of `pattern` after (or
+    /// before) the cursor, wrapping around the file
+    pub fn find(&mut self, pattern: &[u8], forward: bool) -> bool {
+        if pattern.is_empty() || pattern.len() > self.bytes.len() {
+            return false;
+        }
+        let starts: Vec<usize> = self
+            .bytes
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, w)| *w == pattern)
+            .map(|(i, _)| i)
+            .collect();
+        let found = if forward {
+            starts.iter().find(|&&i| i > self.cursor).or(starts.first())
+        } else {
+            starts
+                .iter()
+                .rev()
+                .find(|&&i| i < self.cursor)
+                .or(starts.last())
+        };
+        match found {
+            Some(&offset) => {
+                self.move_to(offset);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Document {
+    /// Create a document showing a binary file's bytes in the hex view
+    pub fn hex(path: PathBuf, bytes: Vec<u8>) -> Self {
+        let mut doc = Document::new();
+        doc.path = Some(path);
+        doc.filetype = "Binary".to_string();
+        doc.hex = Some(HexView::new(bytes));
+        doc
+    }
+
+    /// Open a file in the hex view whatever its contents
+    pub fn open_hex(path: PathBuf) -> std::io::Result<Self> {
+        let bytes = std::fs::read(&path)?;
+        let mut doc = Self::hex(path, bytes);
+        doc.record_disk_state();
+        Ok(doc)
+    }
+
+    /// Type into the hex view (ignored in read-only documents)
+    pub fn hex_type_char(&mut self, ch: char) {
+        if self.read_only {
+            return;
+        }
+        if let Some(hex) = &mut self.hex {
+            hex.type_char(ch);
+        }
+        self.update_modified();
+    }
+
+    pub(super) fn hex_mark_saved(&mut self) {
+        if let Some(hex) = &mut self.hex {
+            hex.mark_saved();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_detection() {
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\0\0\0"));
+        assert!(!is_binary(b"plain text\n"));
+        assert!(!is_binary(b"h\0i\0!\0\n\0"));
+    }
+
+    #[test]
+    fn test_overwrite_undo_and_search() {
+        let mut hex = HexView::new(vec![0x00, 0x11, 0xDE, 0xAD]);
+        assert!(hex.type_char('a') && hex.type_char('B'));
+        assert_eq!((hex.bytes[0], hex.cursor), (0xAB, 1));
+        assert!(!hex.type_char('g'));
+        assert!(hex.is_modified());
+
+        hex.undo();
+        assert_eq!(hex.bytes[0], 0x00);
+        assert!(!hex.is_modified());
+
+        assert!(hex.find(&HexView::parse_pattern("de ad"), true));
+        assert_eq!(hex.cursor, 2);
+        assert_eq!(HexView::parse_pattern("ELF"), b"ELF");
+    }
+
+    #[test]
+    fn test_typed_byte_is_one_undo_step() {
+        let mut hex = HexView::new(vec![0x00, 0x11]);
+        assert!(hex.type_char('a') && hex.type_char('b'));
+        assert!(hex.type_char('c'));
+        assert_eq!(hex.bytes, vec![0xAB, 0xC1]);
+
+        hex.undo();
+        assert_eq!(hex.bytes, vec![0xAB, 0x11]);
+        hex.undo();
+        assert_eq!(hex.bytes, vec![0x00, 0x11]);
+        assert!(!hex.undo());
+
+        hex.redo();
+        assert_eq!(hex.bytes, vec![0xAB, 0x11]);
+    }
+}
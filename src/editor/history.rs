@@ -0,0 +1,204 @@
+use super::{Cursor, Selection};
+
+/// A single reversible buffer mutation, expressed in character indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// `text` was inserted at `idx`
+    Insert { idx: usize, text: String },
+    /// `text` was removed starting at `idx`
+    Delete { idx: usize, text: String },
+}
+
+/// What kind of user action produced a transaction (used for grouping)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    /// Typing a word character (letters, digits, underscore)
+    TypeWord,
+    /// Typing any other character (whitespace, punctuation, newline)
+    TypeOther,
+    /// Backspace without a selection
+    Backspace,
+    /// Delete key without a selection
+    Delete,
+    /// Anything else (paste, cut, replace, ...) - never merged
+    Other,
+}
+
+impl EditKind {
+    /// Whether an edit of kind `next` may be folded into a transaction of kind `self`
+    fn merges_with(self, next: EditKind) -> bool {
+        matches!(
+            (self, next),
+            (EditKind::TypeWord, EditKind::TypeWord)
+                | (EditKind::TypeWord, EditKind::TypeOther)
+                | (EditKind::TypeOther, EditKind::TypeOther)
+                | (EditKind::Backspace, EditKind::Backspace)
+                | (EditKind::Delete, EditKind::Delete)
+        )
+    }
+}
+
+/// Cursor and selection state captured around a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub cursor: Cursor,
+    pub selection: Selection,
+    pub secondary: Vec<Selection>,
+}
+
+/// A group of edits that is undone/redone as one step
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    /// Unique id, used to detect the save point
+    id: u64,
+    kind: EditKind,
+    pub edits: Vec<Edit>,
+    pub before: Snapshot,
+    pub after: Snapshot,
+}
+
+/// Undo/redo history for a document
+#[derive(Debug, Clone)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    /// Transaction currently being recorded
+    pending: Option<Transaction>,
+    /// Nesting depth of `begin`/`end` calls
+    depth: usize,
+    next_id: u64,
+    /// Id of the state that matches the file on disk (0 = empty history)
+    saved_id: Option<u64>,
+}
+
+impl History {
+    /// Create an empty history whose initial state is the saved state
+    pub fn new() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            pending: None,
+            depth: 0,
+            next_id: 1,
+            saved_id: Some(0),
+        }
+    }
+
+    /// Create an empty history whose initial state has never been saved
+    pub fn unsaved() -> Self {
+        Self {
+            saved_id: None,
+            ..Self::new()
+        }
+    }
+
+    /// Start recording a transaction. Calls may nest; the outermost kind wins.
+    pub fn begin(&mut self, kind: EditKind, before: Snapshot) {
+        self.depth += 1;
+        if self.depth > 1 {
+            return;
+        }
+
+        // Continue the previous transaction if this edit picks up exactly where it left off
+        let can_merge = self.redo_stack.is_empty()
+            && self
+                .undo_stack
+                .last()
+                .is_some_and(|t| t.kind.merges_with(kind) && t.after == before);
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.pending = if can_merge {
+            self.undo_stack.pop().map(|mut t| {
+                // A fresh id keeps the save point from matching the extended transaction
+                t.id = id;
+                t.kind = kind;
+                t
+            })
+        } else {
+            Some(Transaction {
+                id,
+                kind,
+                edits: Vec::new(),
+                after: before.clone(),
+                before,
+            })
+        };
+    }
+
+    /// Record a mutation into the pending transaction
+    pub fn record(&mut self, edit: Edit) {
+        if let Some(t) = &mut self.pending {
+            t.edits.push(edit);
+        }
+    }
+
+    /// Finish the current transaction
+    pub fn end(&mut self, after: Snapshot) {
+        if self.depth == 0 {
+            return;
+        }
+        self.depth -= 1;
+        if self.depth > 0 {
+            return;
+        }
+
+        if let Some(mut t) = self.pending.take() {
+            if t.edits.is_empty() {
+                return;
+            }
+            t.after = after;
+            self.undo_stack.push(t);
+            if !self.redo_stack.is_empty() {
+                // The saved state is no longer reachable if it lived on the redo stack
+                if self.redo_stack.iter().any(|r| Some(r.id) == self.saved_id) {
+                    self.saved_id = None;
+                }
+                self.redo_stack.clear();
+            }
+        }
+    }
+
+    /// Prevent the next edit from merging into the last transaction
+    pub fn seal(&mut self) {
+        if let Some(t) = self.undo_stack.last_mut() {
+            t.kind = EditKind::Other;
+        }
+    }
+
+    /// Pop the most recent transaction for undoing
+    pub fn undo(&mut self) -> Option<&Transaction> {
+        let t = self.undo_stack.pop()?;
+        self.redo_stack.push(t);
+        self.redo_stack.last()
+    }
+
+    /// Pop the most recently undone transaction for redoing
+    pub fn redo(&mut self) -> Option<&Transaction> {
+        let t = self.redo_stack.pop()?;
+        self.undo_stack.push(t);
+        self.undo_stack.last()
+    }
+
+    /// Mark the current state as matching the file on disk
+    pub fn mark_saved(&mut self) {
+        self.seal();
+        self.saved_id = Some(self.current_id());
+    }
+
+    /// Whether the current state differs from the saved state
+    pub fn is_modified(&self) -> bool {
+        self.saved_id != Some(self.current_id())
+    }
+
+    fn current_id(&self) -> u64 {
+        self.undo_stack.last().map(|t| t.id).unwrap_or(0)
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,442 @@
+//! Indentation: detecting a file's style, indenting with Tab and Enter, and
+//! re-indenting the whole file
+//!
+//! Smart indentation follows per-filetype rules: a line ending in an opener
+//! (`{`, `:` in Python, `then` in shell) indents the next line one level, and
+//! typing a closer (`}`, `fi`) alone on a line dedents it.
+
+use super::document::{is_word_char, Change};
+use super::history::EditKind;
+use super::lines::LineEdit;
+use super::{Buffer, Document, IndentStyle};
+
+/// Lines examined when detecting indentation
+const DETECT_LINES: usize = 10_000;
+
+/// Indentation widths considered when detecting spaces
+const SPACE_WIDTHS: [usize; 4] = [2, 4, 8, 3];
+
+/// Where a filetype's indentation changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndentRules {
+    /// Line endings that indent the next line, e.g. `{` or `then`
+    pub indent_after: Vec<String>,
+    /// Line contents that dedent the line when typed, e.g. `}` or `fi`
+    pub dedent_on: Vec<String>,
+}
+
+impl IndentRules {
+    /// Built-in rules for a filetype (none for prose and unknown types)
+    pub fn for_filetype(filetype: &str) -> Self {
+        let (indent_after, dedent_on): (&[&str], &[&str]) = match filetype {
+            "Rust" | "C" | "C++" | "C#" | "Java" | "Go" | "JavaScript" | "TypeScript"
+            | "JavaScript (React)" | "TypeScript (React)" | "PHP" | "Swift" | "Kotlin"
+            | "Scala" | "Dart" | "Zig" | "V" | "Perl" | "R" | "CSS" | "SCSS" | "Less" | "JSON" => {
+                (&["{", "(", "["], &["}", ")", "]"])
+            }
+            "Python" => (
+                &[":", "(", "[", "{"],
+                &["else:", "elif", "except", "finally:", ")", "]", "}"],
+            ),
+            "Shell" => (
+                &["then", "do", "else", "{", "("],
+                &["fi", "done", "esac", "else", "elif", "}", ")"],
+            ),
+            "Ruby" | "Crystal" => (
+                &["do", "then", "else", "{", "(", "["],
+                &["end", "else", "elsif", "when", "}", ")", "]"],
+            ),
+            "Lua" => (
+                &["then", "do", "else", "{", "("],
+                &["end", "else", "elseif", "}", ")"],
+            ),
+            "YAML" => (&[":", "-"], &[]),
+            _ => (&[], &[]),
+        };
+        Self {
+            indent_after: indent_after.iter().map(|s| s.to_string()).collect(),
+            dedent_on: dedent_on.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Whether a line (up to the caret) ends in an opener
+    pub fn opens(&self, text: &str) -> bool {
+        let text = text.trim_end();
+        self.indent_after
+            .iter()
+            .any(|token| !token.is_empty() && ends_with_token(text, token))
+    }
+
+    /// Whether a line's content is exactly a closer
+    pub fn closes(&self, content: &str) -> bool {
+        self.dedent_on.iter().any(|token| token == content)
+    }
+
+    /// The closer made of punctuation that `text` starts with, if any
+    fn leading_closer(&self, text: &str) -> Option<&str> {
+        self.dedent_on
+            .iter()
+            .find(|token| {
+                !token.is_empty()
+                    && !token.chars().any(is_word_char)
+                    && text.starts_with(token.as_str())
+            })
+            .map(String::as_str)
+    }
+}
+
+/// Whether `text` ends with `token` as a whole word (for word tokens)
+fn ends_with_token(text: &str, token: &str) -> bool {
+    let Some(before) = text.strip_suffix(token) else {
+        return false;
+    };
+    let word_token = token.chars().next().is_some_and(is_word_char);
+    !word_token || !before.chars().next_back().is_some_and(is_word_char)
+}
+
+/// Guess how a file is indented from the lines that are: mostly tabs, or the
+/// most common step between the widths of space-indented lines
+fn detect_indentation(buffer: &Buffer) -> Option<(IndentStyle, usize)> {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+
+    for line in 0..buffer.len_lines().min(DETECT_LINES) {
+        let text = buffer.line_text(line);
+        let content = text.trim_start_matches([' ', '\t']);
+        // Blank lines and block comment continuations (" * ...") say nothing
+        if content.is_empty() || content.starts_with('*') {
+            continue;
+        }
+        if text.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+        let spaces = text.len() - content.len();
+        if spaces > 0 {
+            space_lines += 1;
+        }
+        let step = spaces.abs_diff(previous);
+        if step > 0 && step < steps.len() {
+            steps[step] += 1;
+        }
+        previous = spaces;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    if tab_lines > space_lines {
+        return Some((IndentStyle::Tabs, 0));
+    }
+    // Ties go to the width listed first
+    let width = SPACE_WIDTHS
+        .iter()
+        .copied()
+        .filter(|&w| steps[w] > 0)
+        .max_by_key(|&w| (steps[w], std::cmp::Reverse(w)))?;
+    Some((IndentStyle::Spaces, width))
+}
+
+/// Characters of leading whitespace in `text` and their width in columns
+pub(super) fn leading_indent(text: &str, tab_width: usize) -> (usize, usize) {
+    let tab_width = tab_width.max(1);
+    text.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .fold((0, 0), |(chars, width), c| {
+            let width = if c == '\t' {
+                (width / tab_width + 1) * tab_width
+            } else {
+                width + 1
+            };
+            (chars + 1, width)
+        })
+}
+
+impl Document {
+    /// Use the indentation detected in the text, if any
+    pub fn detect_indentation(&mut self) {
+        match detect_indentation(&self.buffer) {
+            Some((IndentStyle::Tabs, _)) => {
+                self.indent_style = IndentStyle::Tabs;
+                self.indent_width = self.tab_width;
+            }
+            Some((IndentStyle::Spaces, width)) => {
+                self.indent_style = IndentStyle::Spaces;
+                self.indent_width = width;
+            }
+            None => {}
+        }
+    }
+
+    /// Leading whitespace `width` columns wide in `style`
+    pub(super) fn indent_text(&self, width: usize, style: IndentStyle) -> String {
+        let tab_width = self.tab_width.max(1);
+        match style {
+            IndentStyle::Spaces => " ".repeat(width),
+            IndentStyle::Tabs => "\t".repeat(width / tab_width) + &" ".repeat(width % tab_width),
+        }
+    }
+
+    /// Insert one level of indentation at every caret: a tab, or spaces up to
+    /// the next indentation stop. A selection over several lines indents
+    /// those lines instead.
+    pub fn insert_indent(&mut self) {
+        let multi_line = self.selections().iter().any(|sel| {
+            let (start, end) = sel.ordered();
+            !sel.is_block() && start.line != end.line
+        });
+        if multi_line {
+            self.indent_lines();
+            return;
+        }
+        let (style, width, tab_width) =
+            (self.indent_style, self.indent_width.max(1), self.tab_width);
+        let changes = self.changes_for_each(|buffer, range| {
+            let text = match style {
+                IndentStyle::Tabs => "\t".to_string(),
+                IndentStyle::Spaces => {
+                    let (line, col) = buffer.char_to_line_col(range.start);
+                    let vcol = buffer.visual_col(line, col, tab_width) + range.pad;
+                    " ".repeat(width - vcol % width)
+                }
+            };
+            range.replace_with(&text)
+        });
+        self.apply_changes(EditKind::Other, changes);
+    }
+
+    /// Indent every non-blank line touched by a caret or selection one level,
+    /// as one undo step that keeps the selection on the same lines
+    pub fn indent_lines(&mut self) {
+        let indent = self.indent_text(self.indent_width.max(1), self.indent_style);
+        let edits = self
+            .selected_lines()
+            .into_iter()
+            .filter(|&line| self.line_len(line) > 0)
+            .map(|line| LineEdit {
+                line,
+                col: 0,
+                remove: 0,
+                insert: indent.clone(),
+            })
+            .collect();
+        self.apply_line_edits(edits);
+    }
+
+    /// Remove one level of indentation (a tab, or up to a level of spaces)
+    /// from every line touched by a caret or selection, as one undo step
+    pub fn unindent_lines(&mut self) {
+        let level = self.indent_width.max(1);
+        let edits = self
+            .selected_lines()
+            .into_iter()
+            .filter_map(|line| {
+                let text = self.buffer.line_text(line);
+                let remove = if text.starts_with('\t') {
+                    1
+                } else {
+                    text.chars().take(level).take_while(|&c| c == ' ').count()
+                };
+                (remove > 0).then(|| LineEdit {
+                    line,
+                    col: 0,
+                    remove,
+                    insert: String::new(),
+                })
+            })
+            .collect();
+        self.apply_line_edits(edits);
+    }
+
+    /// Break the line at every caret. With auto-indent the new line starts
+    /// with the indentation of the old one, one level deeper after an opener;
+    /// a closer right after the caret moves to a line of its own.
+    pub fn insert_newline(&mut self) {
+        if !self.auto_indent {
+            self.insert_char('\n');
+            return;
+        }
+        let level = self.indent_width.max(1);
+        let changes = self.changes_for_each(|buffer, range| {
+            let (line, col) = buffer.char_to_line_col(range.start);
+            let (end_line, end_col) = buffer.char_to_line_col(range.end);
+            let before: String = buffer.line_text(line).chars().take(col).collect();
+            let after: String = buffer.line_text(end_line).chars().skip(end_col).collect();
+            let (_, width) = leading_indent(&before, self.tab_width);
+
+            let opens = self.indent_rules.opens(&before);
+            let inner = self.indent_text(width + if opens { level } else { 0 }, self.indent_style);
+            let mut text = format!("{}\n{}", " ".repeat(range.pad), inner);
+            let caret = text.chars().count();
+            if opens
+                && self
+                    .indent_rules
+                    .leading_closer(after.trim_start())
+                    .is_some()
+            {
+                text.push('\n');
+                text.push_str(&self.indent_text(width, self.indent_style));
+            }
+            Change::replace(range.start, range.end, text, caret, range.primary)
+        });
+        self.apply_changes(EditKind::TypeOther, changes);
+    }
+
+    /// The change for typing `ch` at `pos` when that makes the line's content
+    /// a closer: the line is dedented to match its opener
+    pub(super) fn dedent_on_type(
+        &self,
+        buffer: &Buffer,
+        pos: usize,
+        ch: char,
+        primary: bool,
+    ) -> Option<Change> {
+        if !self.auto_indent || self.indent_rules.dedent_on.is_empty() {
+            return None;
+        }
+        let (line, col) = buffer.char_to_line_col(pos);
+        let text = buffer.line_text(line);
+        let before: String = text.chars().take(col).collect();
+        let after: String = text.chars().skip(col).collect();
+        let content = format!("{}{}", before.trim_start(), ch);
+        if line == 0 || !after.trim().is_empty() || !self.indent_rules.closes(&content) {
+            return None;
+        }
+
+        // One level less than the line above, or level with it if it opened
+        // the block just closed
+        let above = (0..line)
+            .rev()
+            .map(|l| buffer.line_text(l))
+            .find(|text| !text.trim().is_empty())?;
+        let (_, above_width) = leading_indent(&above, self.tab_width);
+        let target = if self.indent_rules.opens(&above) {
+            above_width
+        } else {
+            above_width.saturating_sub(self.indent_width.max(1))
+        };
+        let (_, width) = leading_indent(&before, self.tab_width);
+        if width <= target {
+            return None;
+        }
+
+        let text = self.indent_text(target, self.indent_style) + &content;
+        let caret = text.chars().count();
+        let start = buffer.line_col_to_char(line, 0);
+        Some(Change::replace(start, pos, text, caret, primary))
+    }
+
+    /// Switch the whole file to `style` indented `width` columns per level,
+    /// rewriting every line's leading whitespace as one undo step
+    pub fn reindent(&mut self, style: IndentStyle, width: usize) {
+        let old_width = match self.indent_style {
+            IndentStyle::Tabs => self.tab_width,
+            IndentStyle::Spaces => self.indent_width,
+        }
+        .max(1);
+        let new_width = match style {
+            IndentStyle::Tabs => self.tab_width,
+            IndentStyle::Spaces => width,
+        }
+        .max(1);
+
+        let mut changes = Vec::new();
+        for line in 0..self.line_count() {
+            let text = self.buffer.line_text(line);
+            let (chars, columns) = leading_indent(&text, self.tab_width);
+            // Whole levels change width; a partial level stays as spaces
+            let levels = columns / old_width;
+            let converted =
+                self.indent_text(levels * new_width, style) + &" ".repeat(columns % old_width);
+            if text.chars().take(chars).ne(converted.chars()) {
+                let start = self.buffer.line_col_to_char(line, 0);
+                changes.push(Change::replace(start, start + chars, converted, 0, false));
+            }
+        }
+        self.apply_changes_in_place(changes);
+        self.indent_style = style;
+        self.indent_width = new_width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_indentation() {
+        let two = Buffer::from_str("fn a() {\n  if x {\n    y();\n  }\n}\n");
+        assert_eq!(detect_indentation(&two), Some((IndentStyle::Spaces, 2)));
+        let tabs = Buffer::from_str("a:\n\tb\n\tc\n  /*\n   * d\n   */\n");
+        assert_eq!(
+            detect_indentation(&tabs).map(|(s, _)| s),
+            Some(IndentStyle::Tabs)
+        );
+        assert_eq!(detect_indentation(&Buffer::from_str("flat\ntext\n")), None);
+    }
+
+    #[test]
+    fn test_smart_indent_and_dedent() {
+        let mut doc = Document::new();
+        doc.indent_rules = IndentRules::for_filetype("Rust");
+        doc.insert_str("fn a() {}");
+        doc.move_left(false);
+        doc.insert_newline();
+        assert_eq!(doc.buffer.to_string(), "fn a() {\n    \n}");
+        doc.insert_str("if x {");
+        doc.insert_newline();
+        doc.insert_str("y();");
+        doc.insert_newline();
+        doc.insert_char('}');
+        assert_eq!(
+            doc.buffer.to_string(),
+            "fn a() {\n    if x {\n        y();\n    }\n}"
+        );
+
+        let mut doc = Document::new();
+        doc.indent_rules = IndentRules::for_filetype("Shell");
+        doc.insert_str("if x; then");
+        doc.insert_newline();
+        doc.insert_str("undo");
+        doc.insert_newline();
+        doc.insert_str("f");
+        doc.insert_char('i');
+        assert_eq!(doc.buffer.to_string(), "if x; then\n    undo\nfi");
+    }
+
+    #[test]
+    fn test_newline_and_reindent_follow_style() {
+        let mut doc = Document::new();
+        doc.insert_str("fn a() {\n  x;");
+        doc.detect_indentation();
+        doc.insert_newline();
+        doc.insert_str("y;");
+        assert_eq!(doc.buffer.to_string(), "fn a() {\n  x;\n  y;");
+
+        doc.reindent(IndentStyle::Spaces, 4);
+        assert_eq!(doc.buffer.to_string(), "fn a() {\n    x;\n    y;");
+        doc.reindent(IndentStyle::Tabs, 0);
+        assert_eq!(doc.buffer.to_string(), "fn a() {\n\tx;\n\ty;");
+        assert_eq!((doc.cursor.line, doc.cursor.col), (2, 3));
+    }
+
+    #[test]
+    fn test_block_indent_keeps_selection() {
+        let mut doc = Document::new();
+        doc.insert_str("a\n\n  b\nc");
+        doc.move_to(0, 1, false);
+        doc.move_to(2, 3, true);
+        doc.insert_indent();
+        assert_eq!(doc.buffer.to_string(), "    a\n\n      b\nc");
+        assert_eq!(doc.selected_text(), "\n\n      b");
+
+        doc.unindent_lines();
+        doc.unindent_lines();
+        assert_eq!(doc.buffer.to_string(), "a\n\nb\nc");
+        assert_eq!(doc.selected_text(), "\n\nb");
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "a\n\n  b\nc");
+    }
+}
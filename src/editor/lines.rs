@@ -0,0 +1,85 @@
+//! Edits to whole lines covered by the carets
+//!
+//! Commenting and indenting change the start of each selected line; the
+//! carets and selections move with the text so a selection stays on the same
+//! lines and can be edited again.
+
+use super::history::EditKind;
+use super::{Cursor, Document};
+
+/// Replace `remove` chars at `col` of `line` with `insert`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct LineEdit {
+    pub line: usize,
+    pub col: usize,
+    pub remove: usize,
+    pub insert: String,
+}
+
+impl LineEdit {
+    /// Where a caret at `col` on this edit's line ends up
+    fn shift(&self, col: usize) -> usize {
+        let inserted = self.insert.chars().count();
+        if col <= self.col {
+            col
+        } else if col < self.col + self.remove {
+            self.col + inserted
+        } else {
+            col - self.remove + inserted
+        }
+    }
+}
+
+impl Document {
+    /// Lines covered by any caret or selection, in order; a selection ending at
+    /// the start of a line does not include that line
+    pub(super) fn selected_lines(&self) -> Vec<usize> {
+        let mut lines: Vec<usize> = self
+            .selections()
+            .iter()
+            .flat_map(|sel| {
+                let (start, end) = sel.ordered();
+                let last = if end.col == 0 && end.line > start.line {
+                    end.line - 1
+                } else {
+                    end.line
+                };
+                start.line..=last
+            })
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    /// Apply edits on distinct lines as one undo step, moving carets and
+    /// selections along with the text
+    pub(super) fn apply_line_edits(&mut self, mut edits: Vec<LineEdit>) {
+        if edits.is_empty() || self.read_only || self.hex.is_some() {
+            return;
+        }
+        edits.sort_by_key(|e| e.line);
+
+        self.begin_edit(EditKind::Other);
+        for edit in edits.iter().rev() {
+            let start = self.buffer.line_col_to_char(edit.line, edit.col);
+            self.remove_range(start, start + edit.remove);
+            self.insert_at(start, &edit.insert);
+        }
+
+        let shift = |pos: &mut Cursor| {
+            if let Some(edit) = edits.iter().find(|e| e.line == pos.line) {
+                pos.col = edit.shift(pos.col);
+            }
+        };
+        shift(&mut self.cursor);
+        shift(&mut self.selection.anchor);
+        shift(&mut self.selection.head);
+        for sel in &mut self.secondary {
+            shift(&mut sel.anchor);
+            shift(&mut sel.head);
+        }
+
+        self.end_edit();
+    }
+}
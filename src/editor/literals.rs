@@ -0,0 +1,132 @@
+//! Where a document's strings and comments are
+//!
+//! The ranges come from the syntax highlighter. They are scanned from the top
+//! only as far as a feature needs them, and an edit throws away the scan from
+//! the edited line on; the highlighter keeps the parser state at the start of
+//! every scanned line, so the next scan resumes there instead of at the top. Bracket
+//! folding, auto-closing pairs and bracket matching skip characters inside
+//! them.
+
+use super::Document;
+use crate::highlighting::HighlightingManager;
+use std::ops::Range;
+
+/// Largest document (in bytes) whose strings and comments are scanned; in
+/// bigger ones every character counts as code
+pub const MAX_LITERAL_SCAN_BYTES: usize = 1024 * 1024;
+
+/// String and comment char ranges of the lines scanned so far
+#[derive(Debug, Clone, Default)]
+pub(super) struct LiteralRanges {
+    /// Filetype the scan is for; another filetype starts over
+    filetype: String,
+    /// Ranges of the scanned lines, from the top
+    pub(super) lines: Vec<Vec<Range<usize>>>,
+    /// The filetype has no syntax: nothing is scanned
+    unavailable: bool,
+}
+
+impl LiteralRanges {
+    /// Forget the scan from `line` on, after an edit there
+    pub(super) fn edited(&mut self, line: usize) {
+        self.lines.truncate(line);
+    }
+}
+
+impl Document {
+    /// Scan strings and comments through line `through`, resuming where the
+    /// last scan or the first edit since stopped. Large files, hex views,
+    /// documents over `MAX_LITERAL_SCAN_BYTES` and filetypes without a syntax
+    /// are not scanned.
+    pub fn scan_literals(&mut self, highlighting: &mut HighlightingManager, through: usize) {
+        if self.literals.filetype != self.filetype {
+            self.literals = LiteralRanges {
+                filetype: self.filetype.clone(),
+                ..LiteralRanges::default()
+            };
+        }
+        let last = through.min(self.line_count().saturating_sub(1));
+        if self.literals.unavailable
+            || self.literals.lines.len() > last
+            || self.large_file
+            || self.hex.is_some()
+            || self.buffer.len_bytes() > MAX_LITERAL_SCAN_BYTES
+        {
+            return;
+        }
+        let Some(start) = highlighting.literal_state(&self.filetype) else {
+            self.literals.unavailable = true;
+            return;
+        };
+
+        // States past the first edited line are stale; none are kept if
+        // another document was scanned since, and the scan starts over
+        let (syntax_set, states) = highlighting.literal_states(self.id);
+        if states.is_empty() {
+            self.literals.lines.clear();
+        }
+        if self.literals.lines.is_empty() {
+            states.clear();
+            states.push(start);
+        }
+        states.truncate(self.literals.lines.len() + 1);
+
+        let mut state = states[self.literals.lines.len()].clone();
+        for line in self.literals.lines.len()..=last {
+            // Syntaxes expect each line with its line ending
+            let mut text = self.buffer.line_text(line);
+            text.push('\n');
+            let Some(ranges) = state.scan_line(syntax_set, &text) else {
+                self.literals.lines.clear();
+                self.literals.unavailable = true;
+                return;
+            };
+            self.literals.lines.push(ranges);
+            states.push(state.clone());
+        }
+    }
+
+    /// Whether the character at `line`/`col` is part of a string or comment;
+    /// false for lines not scanned yet
+    pub(super) fn in_literal(&self, line: usize, col: usize) -> bool {
+        self.literals
+            .lines
+            .get(line)
+            .is_some_and(|ranges| ranges.iter().any(|r| r.contains(&col)))
+    }
+
+    /// Whether a caret at `line`/`col` sits inside a string or comment (not
+    /// just before or after one)
+    pub(super) fn caret_in_literal(&self, line: usize, col: usize) -> bool {
+        col > 0 && self.in_literal(line, col - 1) && self.in_literal(line, col)
+    }
+
+    /// Whether every line's strings and comments are scanned
+    pub(super) fn literals_complete(&self) -> bool {
+        !self.literals.unavailable && self.literals.lines.len() == self.line_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_resumes_after_edit() {
+        let mut highlighting = HighlightingManager::new();
+        let mut doc = Document::new();
+        doc.filetype = "Python".to_string();
+        doc.insert_str("a = 1\nb = \"x\"\nc = 2");
+        doc.scan_literals(&mut highlighting, 0);
+        assert_eq!(doc.literals.lines.len(), 1);
+        doc.scan_literals(&mut highlighting, usize::MAX);
+        assert_eq!(doc.literals.lines, vec![vec![], vec![4..7], vec![]]);
+
+        // Opening a triple-quoted string on line 1 turns the rest into a string
+        doc.move_to(1, 4, false);
+        doc.insert_str("\"\"\"");
+        assert_eq!(doc.literals.lines.len(), 1);
+        doc.scan_literals(&mut highlighting, usize::MAX);
+        assert!(doc.in_literal(2, 0));
+    }
+}
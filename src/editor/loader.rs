@@ -0,0 +1,259 @@
+//! Background loading of large files
+//!
+//! Files above the large-file threshold are read on a worker thread so the UI
+//! stays responsive; the status bar shows progress while the read runs. Text
+//! is decoded chunk by chunk as it is read, so the raw bytes are never held
+//! whole. The resulting document is in large-file mode, and a file above the
+//! memory limit is cut at the last full line within the limit and opened
+//! read-only.
+
+use super::document::LineEnding;
+use super::encoding::TextEncoding;
+use super::hex::is_binary;
+use super::{Buffer, Document};
+use encoding_rs::{CoderResult, Decoder};
+use ropey::{Rope, RopeBuilder};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// Bytes read between progress updates
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A file being loaded in the background
+pub struct FileLoad {
+    pub path: PathBuf,
+    /// Bytes that will be read
+    total: u64,
+    /// Bytes read so far, updated by the worker
+    loaded: Arc<AtomicU64>,
+    /// Whether only part of the file is being read
+    pub truncated: bool,
+    /// Id of the open document the loaded one replaces (a reload)
+    pub replaces: Option<u64>,
+    rx: Receiver<io::Result<Document>>,
+}
+
+impl FileLoad {
+    /// Start reading `path` on a worker thread, reading at most `max_bytes`
+    pub fn start(path: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        let size = std::fs::metadata(&path)?.len();
+        let truncated = size > max_bytes;
+        let total = size.min(max_bytes);
+        let loaded = Arc::new(AtomicU64::new(0));
+        let (tx, rx) = mpsc::channel();
+
+        let worker_path = path.clone();
+        let worker_loaded = Arc::clone(&loaded);
+        std::thread::spawn(move || {
+            let result = load(&worker_path, total, truncated, &worker_loaded).map(|mut doc| {
+                doc.record_disk_state();
+                doc.large_file = true;
+                doc.read_only = truncated;
+                doc
+            });
+            // The receiver is gone if the load was abandoned
+            let _ = tx.send(result);
+        });
+
+        Ok(Self {
+            path,
+            total,
+            loaded,
+            truncated,
+            replaces: None,
+            rx,
+        })
+    }
+
+    /// Progress in percent
+    pub fn progress(&self) -> u64 {
+        if self.total == 0 {
+            return 100;
+        }
+        self.loaded.load(Ordering::Relaxed).min(self.total) * 100 / self.total
+    }
+
+    /// The loaded document once the worker has finished
+    pub fn poll(&self) -> Option<io::Result<Document>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(io::Error::other(
+                "the loading thread stopped unexpectedly",
+            ))),
+        }
+    }
+
+    /// File name for the status bar
+    pub fn title(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+/// Read up to `limit` bytes of a file in chunks, counting progress in
+/// `loaded`; text is decoded as it is read, and only whole lines are kept when
+/// the file is `truncated`
+fn load(path: &Path, limit: u64, truncated: bool, loaded: &AtomicU64) -> io::Result<Document> {
+    let mut file = File::open(path)?.take(limit);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut n = read_chunk(&mut file, &mut chunk, loaded)?;
+
+    if is_binary(&chunk[..n]) {
+        let mut bytes = Vec::new();
+        while n > 0 {
+            bytes.extend_from_slice(&chunk[..n]);
+            n = read_chunk(&mut file, &mut chunk, loaded)?;
+        }
+        return Ok(Document::hex(path.to_path_buf(), bytes));
+    }
+
+    let complete = n < CHUNK_SIZE && !truncated;
+    let encoding = TextEncoding::detect_start(&chunk[..n], complete);
+    let mut text = TextStream::new(encoding.encoding.new_decoder_with_bom_removal());
+    while n > 0 {
+        text.push(&chunk[..n], false);
+        n = read_chunk(&mut file, &mut chunk, loaded)?;
+    }
+    let (rope, counts) = text.finish(truncated);
+    Ok(Document::from_buffer(
+        path.to_path_buf(),
+        Buffer::from_rope(rope),
+        encoding,
+        LineEnding::most_common(counts),
+        LineEnding::mixed(counts),
+    ))
+}
+
+/// Fill `chunk` from `file`, stopping short only at the end of the file
+fn read_chunk(file: &mut impl Read, chunk: &mut [u8], loaded: &AtomicU64) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < chunk.len() {
+        let n = file.read(&mut chunk[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    loaded.fetch_add(filled as u64, Ordering::Relaxed);
+    Ok(filled)
+}
+
+/// File contents decoded chunk by chunk into a rope with LF line breaks
+struct TextStream {
+    decoder: Decoder,
+    rope: RopeBuilder,
+    /// Decoded text after the last complete line break
+    rest: String,
+    /// Line breaks added so far: LF, CRLF and CR
+    counts: [usize; 3],
+}
+
+impl TextStream {
+    fn new(decoder: Decoder) -> Self {
+        Self {
+            decoder,
+            rope: RopeBuilder::new(),
+            rest: String::new(),
+            counts: [0; 3],
+        }
+    }
+
+    /// Decode the next bytes, adding the lines they complete
+    fn push(&mut self, mut bytes: &[u8], last: bool) {
+        loop {
+            let (result, read, _) = self.decoder.decode_to_string(bytes, &mut self.rest, last);
+            bytes = &bytes[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+            let needed = self.decoder.max_utf8_buffer_length(bytes.len());
+            self.rest.reserve(needed.unwrap_or(CHUNK_SIZE));
+        }
+
+        // A CR at the end may be the start of a CRLF
+        let pending = if last {
+            &self.rest[..]
+        } else {
+            self.rest.strip_suffix('\r').unwrap_or(&self.rest)
+        };
+        let end = pending.rfind(['\n', '\r']).map_or(0, |i| i + 1);
+        let rest = self.rest.split_off(end);
+        let lines = std::mem::replace(&mut self.rest, rest);
+        self.add(&lines);
+    }
+
+    fn add(&mut self, text: &str) {
+        for (count, n) in self.counts.iter_mut().zip(LineEnding::counts(text)) {
+            *count += n;
+        }
+        self.rope.append(&LineEnding::to_lf(text));
+    }
+
+    /// The decoded text and its line break counts; with `whole_lines` the
+    /// text after the last line break (which may end in a partial character
+    /// or half a CRLF) is left out, unless there is no line break
+    fn finish(mut self, whole_lines: bool) -> (Rope, [usize; 3]) {
+        if !whole_lines || self.counts == [0; 3] {
+            self.push(&[], true);
+            let rest = std::mem::take(&mut self.rest);
+            self.add(&rest);
+        }
+        (self.rope.finish(), self.counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_over_limit_is_read_only_whole_lines() {
+        let path = std::env::temp_dir().join(format!("gterm-load-{}.txt", std::process::id()));
+        std::fs::write(&path, "first line\nsecond line\nthird line\n").unwrap();
+
+        let load = FileLoad::start(path.clone(), 16).unwrap();
+        let doc = loop {
+            if let Some(result) = load.poll() {
+                break result.unwrap();
+            }
+            std::thread::yield_now();
+        };
+        assert!(load.truncated && doc.read_only && doc.large_file);
+        assert_eq!(doc.buffer.to_string(), "first line\n");
+        assert_eq!(load.progress(), 100);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_over_limit_keeps_whole_utf16_lines() {
+        let path = std::env::temp_dir().join(format!("gterm-load16-{}.txt", std::process::id()));
+        let bytes: Vec<u8> = "first line\r\nsecond line\r\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        // Ends on the low byte of the second line's LF
+        let load = FileLoad::start(path.clone(), bytes.len() as u64 - 1).unwrap();
+        let doc = loop {
+            if let Some(result) = load.poll() {
+                break result.unwrap();
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(doc.encoding.display_name(), "UTF-16LE");
+        assert_eq!(doc.line_ending, LineEnding::CrLf);
+        assert!(!doc.mixed_line_endings);
+        assert_eq!(doc.buffer.to_string(), "first line\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
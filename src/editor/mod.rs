@@ -1,7 +1,38 @@
+mod block_selection;
+mod bookmarks;
+mod brackets;
 mod buffer;
+mod comments;
 mod cursor;
 mod document;
+mod editorconfig;
+mod encoding;
+mod folding;
+mod hex;
+mod history;
+mod indent;
+mod lines;
+mod literals;
+mod loader;
+mod multi_cursor;
+mod pairs;
+mod reload;
+mod transform;
+mod whitespace;
+mod word;
+mod wrap;
 
-pub use buffer::Buffer;
-pub use cursor::{Cursor, Selection};
-pub use document::Document;
+pub use buffer::{layout_line, Buffer};
+pub use comments::CommentTokens;
+pub use cursor::{Cursor, Selection, SelectionMode};
+pub use document::{Document, LineEnding};
+pub use editorconfig::Editorconfig;
+pub use encoding::{TextEncoding, ENCODINGS};
+pub use hex::{HexView, BYTES_PER_ROW};
+pub use indent::IndentRules;
+pub use loader::FileLoad;
+pub use pairs::{pairs_for_filetype, parse_pair};
+pub use reload::DiskChange;
+pub use transform::TextCase;
+pub use whitespace::{IndentStyle, SaveActions};
+pub use wrap::VisualRow;
@@ -0,0 +1,276 @@
+//! Multi-cursor commands for `Document`
+//!
+//! The primary caret lives in `Document::cursor`/`Document::selection`; any
+//! additional carets are kept in `Document::secondary`. Editing operations in
+//! `document.rs` apply at every caret, so this module only deals with creating,
+//! removing and merging carets.
+
+use super::word::word_at;
+use super::{Cursor, Document, Selection};
+
+impl Document {
+    /// Drop all secondary carets, keeping only the primary one
+    pub fn clear_secondary_cursors(&mut self) {
+        self.secondary.clear();
+    }
+
+    /// Add a caret at a position (Alt+click); clicking an existing caret removes it
+    pub fn add_cursor_at(&mut self, line: usize, col: usize) {
+        let line = line.min(self.line_count().saturating_sub(1));
+        let col = col.min(self.line_len(line));
+
+        if let Some(i) = self
+            .secondary
+            .iter()
+            .position(|s| s.head.line == line && s.head.col == col)
+        {
+            self.secondary.remove(i);
+            return;
+        }
+        if self.cursor.line == line && self.cursor.col == col {
+            // Removing the primary caret promotes the most recent secondary one
+            if let Some(sel) = self.secondary.pop() {
+                self.selection = sel;
+                self.cursor = sel.head;
+            }
+            return;
+        }
+
+        self.push_primary(Cursor::at(line, col));
+    }
+
+    /// Add a caret on the row above the topmost caret
+    pub fn add_cursor_above(&mut self) {
+        let top = self
+            .selections()
+            .into_iter()
+            .map(|s| s.head)
+            .min_by_key(|c| (c.line, c.col))
+            .unwrap_or(self.cursor);
+        self.add_cursor_rows_from(top, -1);
+    }
+
+    /// Add a caret on the row below the bottommost caret
+    pub fn add_cursor_below(&mut self) {
+        let bottom = self
+            .selections()
+            .into_iter()
+            .map(|s| s.head)
+            .max_by_key(|c| (c.line, c.col))
+            .unwrap_or(self.cursor);
+        self.add_cursor_rows_from(bottom, 1);
+    }
+
+    /// Add a caret `rows` screen rows away from `from`, if there is room
+    fn add_cursor_rows_from(&mut self, from: Cursor, rows: isize) {
+        let cursor = self.row_layout().move_by_rows(&self.buffer, from, rows);
+        if pos(cursor) != pos(from) {
+            self.push_primary(cursor);
+        }
+    }
+
+    /// Select the word under the caret, or add a selection at the next occurrence
+    /// of the selected text
+    pub fn add_next_occurrence(&mut self) {
+        if !self.selection.has_selection() {
+            self.select_word_at_cursor();
+            return;
+        }
+
+        let needle = {
+            let (start, end) = self.selection.ordered();
+            let start_idx = self.buffer.line_col_to_char(start.line, start.col);
+            let end_idx = self.buffer.line_col_to_char(end.line, end.col);
+            self.buffer.slice_to_string(start_idx, end_idx)
+        };
+        let needle_len = needle.chars().count();
+
+        // Candidate starts, ordered by distance after the primary selection (wrapping)
+        let text = self.buffer.to_string();
+        let rope = self.buffer.rope();
+        let (_, primary_end) = self.selection.ordered();
+        let from = self
+            .buffer
+            .line_col_to_char(primary_end.line, primary_end.col);
+        let total = self.buffer.len_chars();
+        let mut starts: Vec<usize> = text
+            .match_indices(needle.as_str())
+            .map(|(byte, _)| rope.byte_to_char(byte))
+            .collect();
+        starts.sort_by_key(|&idx| if idx >= from { idx - from } else { idx + total });
+
+        let taken: Vec<usize> = self
+            .caret_ranges()
+            .into_iter()
+            .map(|range| range.start)
+            .collect();
+        let Some(start) = starts.into_iter().find(|idx| !taken.contains(idx)) else {
+            return;
+        };
+
+        let (anchor_line, anchor_col) = self.buffer.char_to_line_col(start);
+        let (head_line, head_col) = self.buffer.char_to_line_col(start + needle_len);
+        self.secondary.push(self.selection);
+        self.cursor = Cursor::at(head_line, head_col);
+        self.selection = Selection::range(Cursor::at(anchor_line, anchor_col), self.cursor);
+        self.merge_carets();
+    }
+
+    /// Select the word touching the primary caret
+    fn select_word_at_cursor(&mut self) {
+        let line = self.cursor.line;
+        let col = self.cursor.col.min(self.line_len(line));
+        let Some((start, end)) = word_at(&self.buffer, line, col) else {
+            return;
+        };
+
+        self.secondary.clear();
+        self.selection.anchor = Cursor::at(line, start);
+        self.cursor = Cursor::at(line, end);
+        self.selection.head = self.cursor;
+    }
+
+    /// Make `cursor` the new primary caret, keeping the old one as secondary
+    fn push_primary(&mut self, cursor: Cursor) {
+        self.secondary.push(self.selection);
+        self.cursor = cursor;
+        self.selection = Selection::new(cursor);
+        self.merge_carets();
+    }
+
+    /// Sort secondary carets and merge any that overlap or coincide, unfolding
+    /// any fold a caret landed in
+    pub(super) fn merge_carets(&mut self) {
+        self.reveal_carets();
+        if self.secondary.is_empty() {
+            return;
+        }
+
+        let primary = self.selection;
+        let mut all = self.selections();
+        all.sort_by_key(|s| {
+            let (start, end) = s.ordered();
+            (start.line, start.col, end.line, end.col)
+        });
+
+        let mut merged: Vec<Selection> = Vec::with_capacity(all.len());
+        let mut primary_idx = 0;
+        for sel in all {
+            let is_primary = sel == primary;
+            if let Some(last) = merged.last_mut() {
+                let (last_start, last_end) = last.ordered();
+                let (start, end) = sel.ordered();
+                let overlaps = pos(start) < pos(last_end)
+                    || (pos(start) == pos(last_end)
+                        && (!sel.has_selection() || !last.has_selection()));
+                if overlaps {
+                    let new_end = if pos(end) > pos(last_end) {
+                        end
+                    } else {
+                        last_end
+                    };
+                    let forward = pos(last.anchor) <= pos(last.head);
+                    *last = if forward {
+                        Selection::range(last_start, new_end)
+                    } else {
+                        Selection::range(new_end, last_start)
+                    };
+                    if is_primary {
+                        primary_idx = merged.len() - 1;
+                    }
+                    continue;
+                }
+            }
+            if is_primary {
+                primary_idx = merged.len();
+            }
+            merged.push(sel);
+        }
+
+        self.selection = merged.remove(primary_idx);
+        self.cursor = self.selection.head;
+        self.secondary = merged;
+    }
+}
+
+/// Position of a cursor as a comparable tuple
+fn pos(cursor: Cursor) -> (usize, usize) {
+    (cursor.line, cursor.col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_with(text: &str) -> Document {
+        let mut doc = Document::new();
+        doc.insert_str(text);
+        doc.move_to(0, 0, false);
+        doc
+    }
+
+    #[test]
+    fn test_typing_at_every_caret() {
+        let mut doc = doc_with("a\nb\nc");
+        doc.add_cursor_below();
+        doc.add_cursor_below();
+        assert_eq!(doc.caret_count(), 3);
+
+        doc.insert_char('-');
+        assert_eq!(doc.buffer.to_string(), "-a\n-b\n-c");
+
+        doc.backspace();
+        assert_eq!(doc.buffer.to_string(), "a\nb\nc");
+
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "-a\n-b\n-c");
+        assert_eq!(doc.caret_count(), 3);
+    }
+
+    #[test]
+    fn test_next_occurrence_and_rename() {
+        let mut doc = doc_with("foo bar foo baz foo");
+        doc.add_next_occurrence();
+        assert_eq!(doc.selected_text(), "foo");
+
+        doc.add_next_occurrence();
+        doc.add_next_occurrence();
+        assert_eq!(doc.caret_count(), 3);
+
+        doc.insert_str("qux");
+        assert_eq!(doc.buffer.to_string(), "qux bar qux baz qux");
+
+        // Words follow the same rules as double-click selection
+        let mut doc = doc_with("cafe\u{301} + 1");
+        doc.add_next_occurrence();
+        assert_eq!(doc.selected_text(), "cafe\u{301}");
+
+        let mut doc = doc_with("let foo_bar = 1;");
+        doc.move_to(0, 6, false);
+        doc.add_next_occurrence();
+        assert_eq!(doc.selected_text(), "foo_bar");
+    }
+
+    #[test]
+    fn test_copy_and_distributed_paste() {
+        let mut doc = doc_with("one\ntwo\nthree");
+        doc.add_cursor_below();
+        doc.add_cursor_below();
+        doc.move_to_line_end(true);
+        let copied = doc.selected_text();
+        assert_eq!(copied, "one\ntwo\nthree");
+
+        doc.move_to_line_end(false);
+        doc.paste(&copied);
+        assert_eq!(doc.buffer.to_string(), "oneone\ntwotwo\nthreethree");
+    }
+
+    #[test]
+    fn test_carets_merge_when_they_meet() {
+        let mut doc = doc_with("ab\ncd");
+        doc.add_cursor_below();
+        assert_eq!(doc.caret_count(), 2);
+        doc.move_to_start(false);
+        assert_eq!(doc.caret_count(), 1);
+    }
+}
@@ -0,0 +1,179 @@
+//! Auto-closing brackets and quotes
+//!
+//! Typing an opener also inserts its closer, typing a closer that is already
+//! next to the caret steps over it, backspace between an empty pair deletes
+//! both, and an opener typed over a selection wraps it. Pairs are not closed
+//! inside strings and comments.
+
+use super::document::{is_word_char, CaretRange, Change};
+use super::{Buffer, Document};
+
+/// Pairs closed automatically for a filetype
+pub fn pairs_for_filetype(filetype: &str) -> Vec<(char, char)> {
+    let pairs: &[&str] = match filetype {
+        // `'` starts lifetimes, primes and quoted forms in these
+        "Rust" | "OCaml" | "F#" | "Haskell" | "Clojure" => &["()", "[]", "{}", "\"\""],
+        "JavaScript" | "TypeScript" | "JavaScript (React)" | "TypeScript (React)" | "Go"
+        | "Shell" => &["()", "[]", "{}", "\"\"", "''", "``"],
+        // `'` is mostly an apostrophe in prose
+        "Markdown" => &["()", "[]", "{}", "\"\"", "``"],
+        "Plain Text" => &["()", "[]", "{}", "\"\""],
+        _ => &["()", "[]", "{}", "\"\"", "''"],
+    };
+    pairs.iter().filter_map(|pair| parse_pair(pair)).collect()
+}
+
+/// A pair written as its two characters, e.g. `"()"`
+pub fn parse_pair(pair: &str) -> Option<(char, char)> {
+    let mut chars = pair.chars();
+    let pair = (chars.next()?, chars.next()?);
+    chars.next().is_none().then_some(pair)
+}
+
+impl Document {
+    /// Whether typing `ch` may open or close a pair
+    pub fn is_pair_char(&self, ch: char) -> bool {
+        self.auto_pairs.iter().any(|&(o, c)| o == ch || c == ch)
+    }
+
+    fn closer_for(&self, ch: char) -> Option<char> {
+        self.auto_pairs
+            .iter()
+            .find(|&&(open, _)| open == ch)
+            .map(|&(_, close)| close)
+    }
+
+    fn is_closer(&self, ch: char) -> bool {
+        self.auto_pairs.iter().any(|&(_, close)| close == ch)
+    }
+
+    /// Step over `ch` if every caret is right before that closer
+    pub(super) fn skip_closer(&mut self, ch: char) -> bool {
+        if !self.is_closer(ch) || self.has_selection() || !self.insert_mode {
+            return false;
+        }
+        let ranges = self.caret_ranges();
+        if ranges
+            .iter()
+            .any(|r| r.block || r.pad > 0 || self.buffer.char_at(r.start) != Some(ch))
+        {
+            return false;
+        }
+        self.move_right(false);
+        true
+    }
+
+    /// The change for typing `ch` over `range` when it opens a pair: the
+    /// selection wrapped in the pair, or the pair with the caret inside
+    pub(super) fn pair_change(
+        &self,
+        buffer: &Buffer,
+        range: &CaretRange,
+        ch: char,
+    ) -> Option<Change> {
+        if range.block || range.pad > 0 {
+            return None;
+        }
+        let close = self.closer_for(ch)?;
+        if range.start != range.end {
+            let inner = buffer.slice_to_string(range.start, range.end);
+            let text = format!("{}{}{}", ch, inner, close);
+            let caret = text.chars().count();
+            return Some(Change::replace(
+                range.start,
+                range.end,
+                text,
+                caret,
+                range.primary,
+            ));
+        }
+
+        let (line, col) = buffer.char_to_line_col(range.start);
+        if self.caret_in_literal(line, col) {
+            return None;
+        }
+        // Only close before whitespace, a closer or the end of the line
+        let next = buffer.char_at(range.start);
+        if next.is_some_and(|c| !c.is_whitespace() && !self.is_closer(c)) {
+            return None;
+        }
+        // A quote after a word is an apostrophe (don't) or a prefix (r", b')
+        let prev = range.start.checked_sub(1).and_then(|i| buffer.char_at(i));
+        if ch == close && prev.is_some_and(is_word_char) {
+            return None;
+        }
+        let text = format!("{}{}", ch, close);
+        Some(Change::replace(
+            range.start,
+            range.start,
+            text,
+            1,
+            range.primary,
+        ))
+    }
+
+    /// The change for backspace between an empty pair: delete both
+    pub(super) fn pair_delete(&self, buffer: &Buffer, range: &CaretRange) -> Option<Change> {
+        if range.start != range.end || range.pad > 0 || range.start == 0 {
+            return None;
+        }
+        let open = buffer.char_at(range.start - 1)?;
+        let close = buffer.char_at(range.start)?;
+        self.auto_pairs
+            .contains(&(open, close))
+            .then(|| Change::delete(range.start - 1, range.start + 1, range.primary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_close_skip_and_delete() {
+        let mut doc = Document::new();
+        doc.auto_pairs = pairs_for_filetype("Python");
+        doc.insert_str("f");
+        doc.insert_char('(');
+        doc.insert_char('"');
+        assert_eq!(doc.buffer.to_string(), "f(\"\")");
+        doc.insert_char('a');
+        doc.insert_char('"');
+        doc.insert_char(')');
+        assert_eq!(doc.buffer.to_string(), "f(\"a\")");
+        assert_eq!(doc.cursor.col, 6);
+
+        doc.insert_char(' ');
+        doc.insert_char('[');
+        doc.backspace();
+        assert_eq!(doc.buffer.to_string(), "f(\"a\") ");
+
+        // Apostrophes stay single
+        doc.insert_str("don");
+        doc.insert_char('\'');
+        assert_eq!(doc.buffer.to_string(), "f(\"a\") don'");
+    }
+
+    #[test]
+    fn test_no_closer_inside_strings() {
+        let mut highlighting = crate::highlighting::HighlightingManager::new();
+        let mut doc = Document::new();
+        doc.filetype = "Python".to_string();
+        doc.auto_pairs = pairs_for_filetype("Python");
+        doc.insert_str("x = \"a b\"");
+        doc.move_to(0, 6, false);
+        doc.scan_literals(&mut highlighting, 0);
+        doc.insert_char('(');
+        assert_eq!(doc.buffer.to_string(), "x = \"a( b\"");
+    }
+
+    #[test]
+    fn test_opener_wraps_selection() {
+        let mut doc = Document::new();
+        doc.auto_pairs = pairs_for_filetype("Rust");
+        doc.insert_str("x + y");
+        doc.select_all();
+        doc.insert_char('(');
+        assert_eq!(doc.buffer.to_string(), "(x + y)");
+    }
+}
@@ -0,0 +1,140 @@
+//! Noticing and taking in changes made to a document's file outside gterm
+//!
+//! A document remembers the modification time and size of its file as last
+//! read or saved; a difference means someone else changed the file.
+
+use super::document::LineEnding;
+use super::{Document, HexView, TextEncoding};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Modification time and size of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl DiskState {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+        })
+    }
+}
+
+/// What happened to a document's file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskChange {
+    /// The file has new contents
+    Modified,
+    /// The file is gone (deleted, or moved out of sight)
+    Deleted,
+}
+
+impl Document {
+    /// Remember the file's current state as the one the buffer matches
+    pub fn record_disk_state(&mut self) {
+        self.disk_state = self.path.as_deref().and_then(DiskState::of);
+    }
+
+    /// How the file differs from when it was last read or saved
+    pub fn disk_change(&self) -> Option<DiskChange> {
+        let path = self.path.as_deref()?;
+        match DiskState::of(path) {
+            None if self.disk_state.is_some() => Some(DiskChange::Deleted),
+            Some(state) if self.disk_state != Some(state) => Some(DiskChange::Modified),
+            _ => None,
+        }
+    }
+
+    /// Unified diff of the file on disk against the buffer
+    pub fn diff_against_disk(&self) -> Vec<String> {
+        let on_disk = self
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .map(|bytes| LineEnding::to_lf(&self.encoding.decode(&bytes)))
+            .unwrap_or_default();
+        crate::utils::diff::unified(&on_disk, &self.buffer.to_string(), 2)
+    }
+
+    /// The file was deleted: the buffer now counts as unsaved
+    pub fn forget_deleted_file(&mut self) {
+        self.record_disk_state();
+        self.update_modified();
+    }
+
+    /// Re-read the file, keeping the cursor and scroll position where
+    /// possible; undo brings back the replaced text
+    pub fn reload(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes = std::fs::read(path)?;
+
+        let mut mixed_line_endings = false;
+        if let Some(hex) = &mut self.hex {
+            let cursor = hex.cursor;
+            *hex = HexView::new(bytes);
+            hex.move_to(cursor);
+        } else {
+            let (scroll_y, scroll_x) = (self.scroll_y, self.scroll_x);
+            let (text, encoding) = TextEncoding::decode_as(&bytes, self.encoding.encoding);
+            let text = if text.contains('\u{FFFD}') {
+                // The file's encoding changed too
+                let (text, encoding) = TextEncoding::detect(&bytes);
+                self.encoding = encoding;
+                text
+            } else {
+                self.encoding = encoding;
+                text
+            };
+            self.replace_text(&LineEnding::to_lf(&text));
+            let counts = LineEnding::counts(&text);
+            self.line_ending = LineEnding::most_common(counts);
+            self.scroll_y = scroll_y.min(self.line_count().saturating_sub(1));
+            self.scroll_x = scroll_x;
+            mixed_line_endings = LineEnding::mixed(counts);
+        }
+        self.mark_saved();
+        self.mixed_line_endings = mixed_line_endings;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_after_external_change() {
+        let path = std::env::temp_dir().join(format!("gterm-reload-{}.txt", std::process::id()));
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let mut doc = Document::open(path.clone()).unwrap();
+        doc.move_to(1, 2, false);
+        assert_eq!(doc.disk_change(), None);
+
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(doc.disk_change(), Some(DiskChange::Modified));
+        doc.reload().unwrap();
+        assert_eq!(doc.buffer.to_string(), "one\ntwo\nthree\n");
+        assert_eq!(
+            (doc.cursor.line, doc.cursor.col, doc.modified),
+            (1, 2, false)
+        );
+        assert_eq!(doc.disk_change(), None);
+        assert!(!doc.mixed_line_endings);
+
+        std::fs::write(&path, "one\r\ntwo\nthree\r\n").unwrap();
+        doc.reload().unwrap();
+        assert_eq!(doc.buffer.to_string(), "one\ntwo\nthree\n");
+        assert_eq!(doc.line_ending, LineEnding::CrLf);
+        assert!(doc.mixed_line_endings);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(doc.disk_change(), Some(DiskChange::Deleted));
+    }
+}
@@ -0,0 +1,359 @@
+//! Line and text transformations from the Edit ▸ Format menu
+//!
+//! Line commands (sort, remove duplicates, reverse, join) work on the lines
+//! of the primary selection; case changes work on every selection, or on the
+//! caret's line without one. Each is a single undo step and leaves the result
+//! selected.
+
+use super::document::Change;
+use super::history::EditKind;
+use super::{Cursor, Document, Selection};
+
+/// Letter case a text transformation converts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextCase {
+    Upper,
+    Lower,
+    /// First letter of every word upper case, the rest lower case
+    Title,
+    /// `snake_case` for each identifier
+    Snake,
+    /// `camelCase` for each identifier
+    Camel,
+}
+
+impl TextCase {
+    /// Convert `text` to this case
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            TextCase::Upper => text.to_uppercase(),
+            TextCase::Lower => text.to_lowercase(),
+            TextCase::Title => map_runs(text, char::is_alphanumeric, |word| {
+                let mut chars = word.chars();
+                chars.next().map_or_else(String::new, |first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect()
+                })
+            }),
+            TextCase::Snake => map_runs(text, is_identifier_char, |ident| {
+                split_identifier(ident)
+                    .iter()
+                    .map(|word| word.to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join("_")
+            }),
+            TextCase::Camel => map_runs(text, is_identifier_char, |ident| {
+                split_identifier(ident)
+                    .iter()
+                    .enumerate()
+                    .map(|(i, word)| {
+                        if i == 0 {
+                            return word.to_lowercase();
+                        }
+                        let mut chars = word.chars();
+                        chars.next().map_or_else(String::new, |first| {
+                            first
+                                .to_uppercase()
+                                .chain(chars.flat_map(char::to_lowercase))
+                                .collect()
+                        })
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '-'
+}
+
+/// Replace each maximal run of chars matching `in_run` with `f` of it
+fn map_runs(text: &str, in_run: fn(char) -> bool, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+    for ch in text.chars() {
+        if in_run(ch) {
+            run.push(ch);
+            continue;
+        }
+        if !run.is_empty() {
+            out.push_str(&f(&run));
+            run.clear();
+        }
+        out.push(ch);
+    }
+    if !run.is_empty() {
+        out.push_str(&f(&run));
+    }
+    out
+}
+
+/// Words of an identifier, split at `_`, `-` and case changes
+/// (`parseHTTPResponse` -> `parse`, `HTTP`, `Response`)
+fn split_identifier(ident: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in ident.split(['_', '-']).filter(|p| !p.is_empty()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+        for (i, &ch) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|j| chars[j]);
+            let next = chars.get(i + 1);
+            let boundary = ch.is_uppercase()
+                && prev
+                    .is_some_and(|p| !p.is_uppercase() || next.is_some_and(|n| n.is_lowercase()));
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(ch);
+        }
+        words.push(word);
+    }
+    words
+}
+
+impl Document {
+    /// Sort the selected lines
+    pub fn sort_lines(&mut self) {
+        self.transform_lines(|lines| lines.sort());
+    }
+
+    /// Remove repeated lines among the selected ones, keeping the first
+    pub fn remove_duplicate_lines(&mut self) {
+        self.transform_lines(|lines| {
+            let mut seen = std::collections::HashSet::new();
+            lines.retain(|line| seen.insert(line.clone()));
+        });
+    }
+
+    /// Reverse the order of the selected lines
+    pub fn reverse_lines(&mut self) {
+        self.transform_lines(|lines| lines.reverse());
+    }
+
+    /// Join the selected lines (the caret's line and the next one without a
+    /// selection) with single spaces
+    pub fn join_lines(&mut self) {
+        let (first, mut last) = self.primary_lines();
+        let selected = last > first;
+        if !selected {
+            if first + 1 >= self.line_count() {
+                return;
+            }
+            last = first + 1;
+        }
+        let mut joined = self.buffer.line_text(first);
+        for line in first + 1..=last {
+            let text = self.buffer.line_text(line);
+            let text = text.trim_start();
+            joined.truncate(joined.trim_end().len());
+            if !text.is_empty() && !joined.is_empty() {
+                joined.push(' ');
+            }
+            joined.push_str(text);
+        }
+        // Without a selection the caret goes to the (last) join point
+        let caret = (!selected).then(|| {
+            let col = self.buffer.line_text(first).trim_end().chars().count();
+            Cursor::at(first, col)
+        });
+        self.replace_lines(first, last, vec![joined], caret);
+    }
+
+    /// Swap the characters around each caret (the two before it at the end
+    /// of a line) and move past them
+    pub fn transpose_chars(&mut self) {
+        let changes = self.changes_for_each(|buffer, range| {
+            let (line, col) = buffer.char_to_line_col(range.start);
+            let len = buffer.line_len(line);
+            if range.start != range.end || range.block || len < 2 || col == 0 {
+                return Change::replace(range.start, range.end, String::new(), 0, range.primary);
+            }
+            let start = range.start - usize::from(col == len) - 1;
+            let text: String = buffer
+                .slice_to_string(start, start + 2)
+                .chars()
+                .rev()
+                .collect();
+            Change::replace(start, start + 2, text, 2, range.primary)
+        });
+        self.apply_changes(EditKind::Other, changes);
+    }
+
+    /// Change the case of every selection (the caret's line without one)
+    pub fn change_case(&mut self, case: TextCase) {
+        self.transform_selections(|text| case.apply(text));
+    }
+
+    /// First and last line of the primary selection; a selection ending at
+    /// the start of a line does not include that line
+    fn primary_lines(&self) -> (usize, usize) {
+        let (start, end) = self.selection.ordered();
+        let last = if end.col == 0 && end.line > start.line {
+            end.line - 1
+        } else {
+            end.line
+        };
+        (start.line, last)
+    }
+
+    /// Rewrite the lines of the primary selection with `f`
+    fn transform_lines(&mut self, f: impl FnOnce(&mut Vec<String>)) {
+        let (first, last) = self.primary_lines();
+        let mut lines: Vec<String> = (first..=last).map(|l| self.buffer.line_text(l)).collect();
+        f(&mut lines);
+        self.replace_lines(first, last, lines, None);
+    }
+
+    /// Replace the text of lines `first..=last` with `lines` as one undo step;
+    /// the caret goes to `caret`, or the new lines are selected
+    fn replace_lines(
+        &mut self,
+        first: usize,
+        last: usize,
+        lines: Vec<String>,
+        caret: Option<Cursor>,
+    ) {
+        if self.read_only || self.hex.is_some() {
+            return;
+        }
+        let start = self.buffer.line_col_to_char(first, 0);
+        let end = self.buffer.line_col_to_char(last, self.line_len(last));
+        let text = lines.join("\n");
+        if self.buffer.slice_to_string(start, end) == text {
+            return;
+        }
+
+        self.begin_edit(EditKind::Other);
+        self.remove_range(start, end);
+        self.insert_at(start, &text);
+        self.secondary.clear();
+        self.selection = match caret {
+            Some(caret) => Selection::new(caret),
+            None => {
+                let last = first + lines.len().saturating_sub(1);
+                Selection::range(Cursor::at(first, 0), Cursor::at(last, self.line_len(last)))
+            }
+        };
+        self.cursor = self.selection.head;
+        self.end_edit();
+    }
+
+    /// Replace the text of every selection (the caret's line without one)
+    /// with `f` of it as one undo step, selecting the results
+    fn transform_selections(&mut self, f: impl Fn(&str) -> String) {
+        if self.read_only || self.hex.is_some() {
+            return;
+        }
+        let mut ranges: Vec<(usize, usize, bool)> = self
+            .caret_ranges()
+            .into_iter()
+            .map(|range| {
+                if range.start != range.end {
+                    return (range.start, range.end, range.primary);
+                }
+                let (line, _) = self.buffer.char_to_line_col(range.start);
+                let start = self.buffer.line_col_to_char(line, 0);
+                (start, start + self.line_len(line), range.primary)
+            })
+            .collect();
+        ranges.sort_unstable();
+        // Carets on the same line share it
+        let mut kept: Vec<(usize, usize, bool)> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match kept.last_mut() {
+                Some(prev) if range.0 < prev.1 || range.0 == prev.0 => prev.2 |= range.2,
+                _ => kept.push(range),
+            }
+        }
+        let replaced: Vec<String> = kept
+            .iter()
+            .map(|&(start, end, _)| f(&self.buffer.slice_to_string(start, end)))
+            .collect();
+
+        self.begin_edit(EditKind::Other);
+        for (&(start, end, _), text) in kept.iter().zip(&replaced).rev() {
+            self.remove_range(start, end);
+            self.insert_at(start, text);
+        }
+
+        // Select each result; earlier replacements shift the later ones
+        let mut offset = 0isize;
+        let mut selections = Vec::with_capacity(kept.len());
+        for (&(start, end, primary), text) in kept.iter().zip(&replaced) {
+            let new_start = start.saturating_add_signed(offset);
+            let new_end = new_start + text.chars().count();
+            offset += (new_end - new_start) as isize - (end - start) as isize;
+            let (line, col) = self.buffer.char_to_line_col(new_start);
+            let anchor = Cursor::at(line, col);
+            let (line, col) = self.buffer.char_to_line_col(new_end);
+            selections.push((primary, Selection::range(anchor, Cursor::at(line, col))));
+        }
+        let primary = selections.iter().position(|(p, _)| *p).unwrap_or(0);
+        self.selection = selections.remove(primary).1;
+        self.secondary = selections.into_iter().map(|(_, sel)| sel).collect();
+        self.cursor = self.selection.head;
+        self.end_edit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_conversions() {
+        let text = "parseHTTPResponse my-value user_id";
+        assert_eq!(
+            TextCase::Snake.apply(text),
+            "parse_http_response my_value user_id"
+        );
+        assert_eq!(
+            TextCase::Camel.apply(text),
+            "parseHttpResponse myValue userId"
+        );
+        assert_eq!(TextCase::Title.apply("hello wORLD"), "Hello World");
+    }
+
+    #[test]
+    fn test_line_transforms_keep_selection() {
+        let mut doc = Document::new();
+        doc.insert_str("c\na\nc\nb\nend");
+        doc.move_to(0, 0, false);
+        doc.move_to(4, 0, true);
+        doc.remove_duplicate_lines();
+        assert_eq!(doc.buffer.to_string(), "c\na\nb\nend");
+        doc.sort_lines();
+        assert_eq!(doc.buffer.to_string(), "a\nb\nc\nend");
+        assert_eq!(doc.selected_text(), "a\nb\nc");
+        doc.reverse_lines();
+        doc.join_lines();
+        assert_eq!(doc.buffer.to_string(), "c b a\nend");
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "c\nb\na\nend");
+
+        doc.move_to(1, 1, false);
+        doc.transpose_chars();
+        assert_eq!(doc.buffer.to_string(), "c\nb\na\nend");
+        doc.move_to(3, 3, false);
+        doc.transpose_chars();
+        assert_eq!(doc.buffer.to_string(), "c\nb\na\nedn");
+    }
+
+    #[test]
+    fn test_change_case_of_every_selection() {
+        let mut doc = Document::new();
+        doc.insert_str("one two\nthree");
+        doc.move_to(0, 4, false);
+        doc.move_to(0, 7, true);
+        doc.change_case(TextCase::Upper);
+        assert_eq!(doc.buffer.to_string(), "one TWO\nthree");
+        assert_eq!(doc.selected_text(), "TWO");
+
+        doc.move_to(1, 2, false);
+        doc.change_case(TextCase::Title);
+        assert_eq!(doc.buffer.to_string(), "one TWO\nThree");
+    }
+}
@@ -0,0 +1,206 @@
+//! Whitespace cleanups, run when saving or from the Document menu
+//!
+//! Each cleanup is a single undo step and leaves the carets on their lines.
+
+use super::document::{Change, LineEnding};
+use super::history::EditKind;
+use super::indent::leading_indent;
+use super::{Document, TextEncoding};
+
+/// What indentation is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+/// Cleanups to run before saving
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveActions {
+    pub strip_trailing_whitespace: bool,
+    /// Add a newline at the end if there is none (true) or remove the ones
+    /// there are (false)
+    pub final_newline: Option<bool>,
+    /// Rewrite leading whitespace in this style
+    pub convert_indentation: Option<IndentStyle>,
+    /// Line ending and encoding to save with, unless another one was chosen
+    /// since the file was read
+    pub line_ending: Option<LineEnding>,
+    pub encoding: Option<TextEncoding>,
+}
+
+impl Document {
+    /// Run the cleanups as one undo step
+    pub fn apply_save_actions(&mut self, actions: &SaveActions) {
+        self.begin_group();
+        if let Some(style) = actions.convert_indentation {
+            self.convert_indentation(style);
+        }
+        if actions.strip_trailing_whitespace {
+            self.strip_trailing_whitespace();
+        }
+        match actions.final_newline {
+            Some(true) => self.insert_final_newline(),
+            Some(false) => self.remove_final_newline(),
+            None => {}
+        }
+        self.end_group();
+
+        if self.hex.is_none() {
+            let (saved_encoding, saved_line_ending) = self.saved_format;
+            if let Some(line_ending) = actions.line_ending {
+                if self.line_ending == saved_line_ending
+                    && (line_ending != self.line_ending || self.mixed_line_endings)
+                {
+                    self.convert_line_endings(line_ending);
+                }
+            }
+            if let Some(encoding) = actions.encoding {
+                if self.encoding == saved_encoding && encoding != self.encoding {
+                    self.set_encoding(encoding);
+                }
+            }
+        }
+    }
+
+    /// Remove spaces and tabs at the end of every line
+    pub fn strip_trailing_whitespace(&mut self) {
+        let mut changes = Vec::new();
+        for line in 0..self.line_count() {
+            let text = self.buffer.line_text(line);
+            let len = text.chars().count();
+            let kept = text.trim_end_matches([' ', '\t']).chars().count();
+            if kept < len {
+                let start = self.buffer.line_col_to_char(line, kept);
+                changes.push(Change::delete(start, start + len - kept, false));
+            }
+        }
+        self.apply_changes_in_place(changes);
+    }
+
+    /// End the text with a newline if it does not already (an empty document
+    /// stays empty)
+    pub fn insert_final_newline(&mut self) {
+        let len = self.buffer.len_chars();
+        if len == 0 || self.buffer.rope().char(len - 1) == '\n' {
+            return;
+        }
+        let change = Change::replace(len, len, "\n".to_string(), 0, false);
+        self.apply_changes_in_place(vec![change]);
+    }
+
+    /// Remove the newlines at the end of the text
+    pub fn remove_final_newline(&mut self) {
+        let text = self.buffer.to_string();
+        let start = text.trim_end_matches('\n').chars().count();
+        if start < self.buffer.len_chars() {
+            let change = Change::delete(start, self.buffer.len_chars(), false);
+            self.apply_changes_in_place(vec![change]);
+        }
+    }
+
+    /// End the text with exactly one newline (an empty document stays empty)
+    pub fn ensure_final_newline(&mut self) {
+        let text = self.buffer.to_string();
+        let content = text.trim_end_matches('\n');
+        if content.is_empty() || text.len() == content.len() + 1 {
+            return;
+        }
+        let start = content.chars().count();
+        let change = Change::replace(start, self.buffer.len_chars(), "\n".to_string(), 0, false);
+        self.apply_changes_in_place(vec![change]);
+    }
+
+    /// Rewrite the leading whitespace of every line as tabs or spaces, keeping
+    /// its width (a partial tab stays as spaces)
+    pub fn convert_indentation(&mut self, style: IndentStyle) {
+        let mut changes = Vec::new();
+        for line in 0..self.line_count() {
+            let text = self.buffer.line_text(line);
+            let (chars, width) = leading_indent(&text, self.tab_width);
+            let converted = self.indent_text(width, style);
+            if text.chars().take(chars).ne(converted.chars()) {
+                let start = self.buffer.line_col_to_char(line, 0);
+                changes.push(Change::replace(start, start + chars, converted, 0, false));
+            }
+        }
+        self.apply_changes_in_place(changes);
+    }
+
+    /// Apply non-overlapping changes as one undo step, keeping each caret on
+    /// its line and column (clamped to the line's new length)
+    pub(super) fn apply_changes_in_place(&mut self, mut changes: Vec<Change>) {
+        if changes.is_empty() || self.read_only || self.hex.is_some() {
+            return;
+        }
+        changes.sort_by_key(|c| c.start);
+
+        self.begin_edit(EditKind::Other);
+        for change in changes.iter().rev() {
+            self.remove_range(change.start, change.end);
+            self.insert_at(change.start, &change.text);
+        }
+
+        let last_line = self.line_count().saturating_sub(1);
+        let clamp = |doc: &Document, pos: &mut super::Cursor| {
+            pos.line = pos.line.min(last_line);
+            pos.col = pos.col.min(doc.line_len(pos.line));
+        };
+        let (mut cursor, mut selection) = (self.cursor, self.selection);
+        clamp(self, &mut cursor);
+        clamp(self, &mut selection.anchor);
+        clamp(self, &mut selection.head);
+        let mut secondary = std::mem::take(&mut self.secondary);
+        for s in &mut secondary {
+            clamp(self, &mut s.anchor);
+            clamp(self, &mut s.head);
+        }
+        self.cursor = cursor;
+        self.selection = selection;
+        self.secondary = secondary;
+
+        self.end_edit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_actions() {
+        let mut doc = Document::new();
+        doc.insert_str("fn a() {  \n\tx; \n        y;\n}");
+        doc.apply_save_actions(&SaveActions {
+            strip_trailing_whitespace: true,
+            final_newline: Some(true),
+            convert_indentation: Some(IndentStyle::Spaces),
+            ..SaveActions::default()
+        });
+        assert_eq!(doc.buffer.to_string(), "fn a() {\n    x;\n        y;\n}\n");
+
+        doc.convert_indentation(IndentStyle::Tabs);
+        assert_eq!(doc.buffer.to_string(), "fn a() {\n\tx;\n\t\ty;\n}\n");
+
+        doc.undo();
+        doc.undo();
+        assert_eq!(doc.buffer.to_string(), "fn a() {  \n\tx; \n        y;\n}");
+    }
+
+    #[test]
+    fn test_final_newline_keeps_blank_lines() {
+        let mut doc = Document::new();
+        doc.insert_str("a\n\n");
+        doc.insert_final_newline();
+        assert_eq!(doc.buffer.to_string(), "a\n\n");
+
+        doc.remove_final_newline();
+        assert_eq!(doc.buffer.to_string(), "a");
+
+        // Only the Document menu command collapses blank lines
+        doc.move_to_end(false);
+        doc.insert_str("\n\n\n");
+        doc.ensure_final_newline();
+        assert_eq!(doc.buffer.to_string(), "a\n");
+    }
+}
@@ -0,0 +1,250 @@
+//! Word boundaries for cursor movement, deletion and word selection
+//!
+//! Lines are split into grapheme clusters (so combining marks and emoji
+//! sequences move as one unit) and each cluster is classed as part of an
+//! identifier, punctuation or whitespace. A word is a run of clusters of the
+//! same class; whitespace is skipped over rather than treated as a word.
+
+use super::document::{is_word_char, Change};
+use super::history::EditKind;
+use super::{Buffer, Cursor, Document, Selection};
+
+/// Class of a grapheme cluster for word movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    /// Identifier characters, as told by `is_word_char`
+    Word,
+    /// Anything that is neither a word character nor whitespace
+    Punctuation,
+    Whitespace,
+}
+
+impl CharClass {
+    fn of(grapheme: &str) -> Self {
+        match grapheme.chars().next() {
+            Some(ch) if is_word_char(ch) => CharClass::Word,
+            Some(ch) if ch.is_whitespace() => CharClass::Whitespace,
+            _ => CharClass::Punctuation,
+        }
+    }
+}
+
+/// Grapheme clusters of a line (without its newline) as `(start_col, end_col, class)`
+fn clusters(buffer: &Buffer, line: usize) -> Vec<(usize, usize, CharClass)> {
+    buffer
+        .graphemes(line, 1)
+        .into_iter()
+        .map(|g| (g.col, g.end_col, CharClass::of(&g.text)))
+        .collect()
+}
+
+/// Position of the next word end after `(line, col)`, crossing to the next line at line end
+pub fn next_word_boundary(buffer: &Buffer, line: usize, col: usize) -> (usize, usize) {
+    if col >= buffer.line_len(line) {
+        return if line + 1 < buffer.len_lines() {
+            (line + 1, 0)
+        } else {
+            (line, col)
+        };
+    }
+
+    let clusters = clusters(buffer, line);
+    let mut iter = clusters
+        .iter()
+        .skip_while(|&&(start, _, _)| start < col)
+        .skip_while(|c| c.2 == CharClass::Whitespace)
+        .peekable();
+    let Some(&(_, mut end, class)) = iter.next() else {
+        return (line, buffer.line_len(line));
+    };
+    while let Some(&&(_, next_end, next_class)) = iter.peek() {
+        if next_class != class {
+            break;
+        }
+        end = next_end;
+        iter.next();
+    }
+    (line, end)
+}
+
+/// Position of the previous word start before `(line, col)`, crossing to the
+/// previous line at line start
+pub fn prev_word_boundary(buffer: &Buffer, line: usize, col: usize) -> (usize, usize) {
+    if col == 0 {
+        return if line > 0 {
+            (line - 1, buffer.line_len(line - 1))
+        } else {
+            (0, 0)
+        };
+    }
+
+    let clusters = clusters(buffer, line);
+    let mut iter = clusters
+        .iter()
+        .rev()
+        .skip_while(|&&(_, end, _)| end > col)
+        .skip_while(|c| c.2 == CharClass::Whitespace)
+        .peekable();
+    let Some(&(mut start, _, class)) = iter.next() else {
+        return (line, 0);
+    };
+    while let Some(&&(prev_start, _, prev_class)) = iter.peek() {
+        if prev_class != class {
+            break;
+        }
+        start = prev_start;
+        iter.next();
+    }
+    (line, start)
+}
+
+/// Column range of the word (or run of punctuation/whitespace) under `col`;
+/// at the end of a word the word before the caret is used
+pub fn word_range_at(buffer: &Buffer, line: usize, col: usize) -> (usize, usize) {
+    run_at(buffer, line, col).map_or((col, col), |(start, end, _)| (start, end))
+}
+
+/// Column range of the word under `col` or just before it, if there is one
+pub(super) fn word_at(buffer: &Buffer, line: usize, col: usize) -> Option<(usize, usize)> {
+    run_at(buffer, line, col)
+        .filter(|run| run.2 == CharClass::Word)
+        .map(|(start, end, _)| (start, end))
+}
+
+/// The run of same-class clusters `word_range_at` picks, with its class
+fn run_at(buffer: &Buffer, line: usize, col: usize) -> Option<(usize, usize, CharClass)> {
+    let clusters = clusters(buffer, line);
+    let idx = clusters
+        .iter()
+        .position(|&(start, end, _)| col >= start && col < end)
+        .or_else(|| clusters.len().checked_sub(1))?;
+
+    // Prefer the word just before the caret when it sits right after one
+    let idx = match clusters[idx].2 {
+        CharClass::Word => idx,
+        _ if idx > 0 && clusters[idx].0 == col && clusters[idx - 1].2 == CharClass::Word => idx - 1,
+        _ => idx,
+    };
+    let class = clusters[idx].2;
+
+    let first = clusters[..idx]
+        .iter()
+        .rposition(|c| c.2 != class)
+        .map_or(0, |i| i + 1);
+    let last = clusters[idx..]
+        .iter()
+        .position(|c| c.2 != class)
+        .map_or(clusters.len(), |i| idx + i);
+    Some((clusters[first].0, clusters[last - 1].1, class))
+}
+
+impl Document {
+    /// Move every caret to the end of the next word (Ctrl+Right)
+    pub fn move_word_right(&mut self, extend_selection: bool) {
+        self.move_carets(extend_selection, |buffer, cursor| {
+            let (line, col) = next_word_boundary(buffer, cursor.line, cursor.col);
+            cursor.move_to(line, col);
+        });
+    }
+
+    /// Move every caret to the start of the previous word (Ctrl+Left)
+    pub fn move_word_left(&mut self, extend_selection: bool) {
+        self.move_carets(extend_selection, |buffer, cursor| {
+            let (line, col) = prev_word_boundary(buffer, cursor.line, cursor.col);
+            cursor.move_to(line, col);
+        });
+    }
+
+    /// Delete from each caret back to the previous word start (Ctrl+Backspace)
+    pub fn delete_word_backward(&mut self) {
+        if self.has_selection() {
+            self.delete_selection();
+            return;
+        }
+
+        let changes = self.changes_for_each(|buffer, range| {
+            let (line, col) = buffer.char_to_line_col(range.start);
+            let (line, col) = prev_word_boundary(buffer, line, col);
+            let start = buffer.line_col_to_char(line, col);
+            Change::delete(start.min(range.start), range.start, range.primary)
+        });
+        self.apply_changes(EditKind::Other, changes);
+    }
+
+    /// Delete from each caret to the next word end (Ctrl+Delete)
+    pub fn delete_word_forward(&mut self) {
+        if self.has_selection() {
+            self.delete_selection();
+            return;
+        }
+
+        let changes = self.changes_for_each(|buffer, range| {
+            let (line, col) = buffer.char_to_line_col(range.start);
+            let (line, col) = next_word_boundary(buffer, line, col);
+            let end = buffer.line_col_to_char(line, col);
+            Change::delete(range.start, end.max(range.start), range.primary)
+        });
+        self.apply_changes(EditKind::Other, changes);
+    }
+
+    /// Select the word at a position (double-click), dropping other carets
+    pub fn select_word_at(&mut self, line: usize, col: usize) {
+        let line = line.min(self.line_count().saturating_sub(1));
+        let (start, end) = word_range_at(&self.buffer, line, col.min(self.line_len(line)));
+        self.secondary.clear();
+        self.cursor = Cursor::at(line, end);
+        self.selection = Selection::range(Cursor::at(line, start), self.cursor);
+    }
+
+    /// Select a whole line including its newline (triple-click), dropping other carets
+    pub fn select_line(&mut self, line: usize) {
+        let line = line.min(self.line_count().saturating_sub(1));
+        let head = if line + 1 < self.line_count() {
+            Cursor::at(line + 1, 0)
+        } else {
+            Cursor::at(line, self.line_len(line))
+        };
+        self.secondary.clear();
+        self.cursor = head;
+        self.selection = Selection::range(Cursor::at(line, 0), head);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_boundaries() {
+        let buffer = Buffer::from_str("let foo_bar = baz(1);\nnext");
+        assert_eq!(next_word_boundary(&buffer, 0, 0), (0, 3));
+        assert_eq!(next_word_boundary(&buffer, 0, 3), (0, 11));
+        assert_eq!(next_word_boundary(&buffer, 0, 11), (0, 13));
+        assert_eq!(next_word_boundary(&buffer, 0, 17), (0, 18));
+        assert_eq!(next_word_boundary(&buffer, 0, 21), (1, 0));
+
+        assert_eq!(prev_word_boundary(&buffer, 0, 11), (0, 4));
+        assert_eq!(prev_word_boundary(&buffer, 0, 4), (0, 0));
+        assert_eq!(prev_word_boundary(&buffer, 1, 0), (0, 21));
+    }
+
+    #[test]
+    fn test_word_range_keeps_graphemes_together() {
+        let buffer = Buffer::from_str("cafe\u{301} au lait");
+        assert_eq!(word_range_at(&buffer, 0, 1), (0, 5));
+        assert_eq!(word_range_at(&buffer, 0, 5), (0, 5));
+        assert_eq!(next_word_boundary(&buffer, 0, 0), (0, 5));
+    }
+
+    #[test]
+    fn test_delete_word_backward() {
+        let mut doc = Document::new();
+        doc.insert_str("foo.bar baz");
+        doc.delete_word_backward();
+        assert_eq!(doc.buffer.to_string(), "foo.bar ");
+        doc.delete_word_backward();
+        assert_eq!(doc.buffer.to_string(), "foo.");
+        doc.delete_word_backward();
+        assert_eq!(doc.buffer.to_string(), "foo");
+    }
+}
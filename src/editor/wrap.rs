@@ -0,0 +1,312 @@
+//! Soft word wrap: splitting buffer lines into screen rows
+//!
+//! Wrapping only affects how a line is shown; the buffer keeps one line per
+//! newline. With wrapping off every line is a single row, so movement and
+//! hit-testing can go through the same row functions either way.
+
+use super::buffer::Grapheme;
+use super::{Buffer, Cursor, Document};
+
+/// A screen row showing part of a buffer line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisualRow {
+    /// Character column where the row starts
+    pub start_col: usize,
+    /// Character column just past the row
+    pub end_col: usize,
+    /// Display column (within the unwrapped line) where the row starts
+    pub start_x: usize,
+    /// Blank cells drawn before a continuation row
+    pub indent: usize,
+}
+
+impl VisualRow {
+    /// Screen x of a grapheme in this row
+    pub fn x_of(&self, g: &Grapheme) -> usize {
+        g.x - self.start_x + self.indent
+    }
+}
+
+/// How lines are laid out into screen rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowLayout {
+    pub tab_width: usize,
+    /// Text width to wrap at (None = no wrapping)
+    pub wrap_width: Option<usize>,
+}
+
+impl RowLayout {
+    /// Split a line into screen rows
+    pub fn rows(&self, buffer: &Buffer, line: usize) -> Vec<VisualRow> {
+        let graphemes = buffer.graphemes(line, self.tab_width);
+        match self.wrap_width {
+            Some(width) => wrap_graphemes(&graphemes, width),
+            None => vec![VisualRow {
+                start_col: 0,
+                end_col: graphemes.last().map_or(0, |g| g.end_col),
+                start_x: 0,
+                indent: 0,
+            }],
+        }
+    }
+
+    /// Index of the row of `rows` holding character column `col`
+    pub fn row_of(rows: &[VisualRow], col: usize) -> usize {
+        rows.iter()
+            .position(|r| col < r.end_col)
+            .unwrap_or(rows.len().saturating_sub(1))
+    }
+
+    /// Screen x of a caret at `col` within its row
+    pub fn x_in_row(&self, buffer: &Buffer, line: usize, row: &VisualRow, col: usize) -> usize {
+        buffer.visual_col(line, col, self.tab_width) - row.start_x + row.indent
+    }
+
+    /// Character column in `row` under screen x (the row end if it is shorter)
+    pub fn col_in_row(
+        &self,
+        buffer: &Buffer,
+        line: usize,
+        rows: &[VisualRow],
+        idx: usize,
+        x: usize,
+    ) -> usize {
+        let row = rows[idx];
+        let graphemes = buffer.graphemes(line, self.tab_width);
+        let in_row = graphemes
+            .iter()
+            .filter(|g| g.col >= row.start_col && g.col < row.end_col);
+        let mut last = None;
+        for g in in_row {
+            if x < row.x_of(g) + g.width {
+                return g.col;
+            }
+            last = Some(g.col);
+        }
+
+        // Past the end: continuation rows stop before their last grapheme so the
+        // caret stays on this row
+        match last {
+            Some(col) if idx + 1 < rows.len() => col,
+            _ => row.end_col,
+        }
+    }
+
+    /// Move a cursor by `delta` screen rows, keeping its wanted screen x
+    pub fn move_by_rows(&self, buffer: &Buffer, cursor: Cursor, delta: isize) -> Cursor {
+        let mut line = cursor.line;
+        let mut rows = self.rows(buffer, line);
+        let mut idx = Self::row_of(&rows, cursor.col);
+        let x = cursor
+            .wanted_col
+            .unwrap_or_else(|| self.x_in_row(buffer, line, &rows[idx], cursor.col));
+
+        let last_line = buffer.len_lines().saturating_sub(1);
+        for _ in 0..delta.unsigned_abs() {
+            if delta > 0 {
+                if idx + 1 < rows.len() {
+                    idx += 1;
+                } else if line < last_line {
+                    line += 1;
+                    rows = self.rows(buffer, line);
+                    idx = 0;
+                } else {
+                    break;
+                }
+            } else if idx > 0 {
+                idx -= 1;
+            } else if line > 0 {
+                line -= 1;
+                rows = self.rows(buffer, line);
+                idx = rows.len() - 1;
+            } else {
+                break;
+            }
+        }
+
+        Cursor {
+            line,
+            col: self.col_in_row(buffer, line, &rows, idx, x),
+            wanted_col: Some(x),
+        }
+    }
+}
+
+/// Wrap laid-out graphemes into rows at most `width` cells wide, breaking after
+/// whitespace where possible. Continuation rows are indented like the line.
+fn wrap_graphemes(graphemes: &[Grapheme], width: usize) -> Vec<VisualRow> {
+    let width = width.max(1);
+    let leading = graphemes
+        .iter()
+        .take_while(|g| g.text.chars().all(char::is_whitespace))
+        .last()
+        .map_or(0, |g| g.x + g.width);
+    // Keep at least half the width for text
+    let indent = if leading <= width / 2 { leading } else { 0 };
+
+    let mut rows = Vec::new();
+    let mut row = VisualRow {
+        start_col: 0,
+        end_col: 0,
+        start_x: 0,
+        indent: 0,
+    };
+    // Index of the row's first grapheme, and just past the last whitespace in the row
+    let mut start = 0;
+    let mut break_at: Option<usize> = None;
+    let mut i = 0;
+    while i < graphemes.len() {
+        let g = &graphemes[i];
+        if i > start && row.x_of(g) + g.width > width {
+            // Break after the last whitespace, or right here if the row has none
+            let end = break_at.filter(|&b| b > start).unwrap_or(i);
+            let next = &graphemes[end];
+            row.end_col = next.col;
+            rows.push(row);
+            row = VisualRow {
+                start_col: next.col,
+                end_col: next.col,
+                start_x: next.x,
+                indent,
+            };
+            start = end;
+            break_at = None;
+            i = end;
+            continue;
+        }
+        if g.text.chars().all(char::is_whitespace) {
+            break_at = Some(i + 1);
+        }
+        i += 1;
+    }
+
+    row.end_col = graphemes.last().map_or(0, |g| g.end_col);
+    rows.push(row);
+    rows
+}
+
+impl Document {
+    /// How this document's lines are laid out into screen rows
+    pub fn row_layout(&self) -> RowLayout {
+        RowLayout {
+            tab_width: self.tab_width,
+            wrap_width: self.wrap_width,
+        }
+    }
+
+    /// Buffer position under a screen row (counted from the top of the view)
+    /// and screen x
+    pub fn position_at_screen(&self, screen_row: usize, x: usize) -> (usize, usize) {
+        let layout = self.row_layout();
+        let last_line = self.line_count().saturating_sub(1);
+        if layout.wrap_width.is_none() && !self.has_folds() {
+            let line = (self.scroll_y + screen_row).min(last_line);
+            let col = self
+                .buffer
+                .col_at_visual(line, self.scroll_x + x, self.tab_width);
+            return (line, col);
+        }
+
+        let mut remaining = screen_row;
+        let mut line = self.next_visible_line(self.scroll_y).min(last_line);
+        loop {
+            let rows = self.screen_rows(line);
+            let next = self.next_visible_line(line + 1);
+            if remaining < rows.len() || next > last_line {
+                let idx = remaining.min(rows.len() - 1);
+                let x = if layout.wrap_width.is_some() {
+                    x
+                } else {
+                    self.scroll_x + x
+                };
+                return (line, layout.col_in_row(&self.buffer, line, &rows, idx, x));
+            }
+            remaining -= rows.len();
+            line = next;
+        }
+    }
+
+    /// Screen rows of a line; lines hidden by a fold take none
+    pub fn screen_rows(&self, line: usize) -> Vec<VisualRow> {
+        if self.is_line_hidden(line) {
+            Vec::new()
+        } else {
+            self.row_layout().rows(&self.buffer, line)
+        }
+    }
+
+    /// Scroll so the primary caret's row is on screen
+    pub(super) fn ensure_row_visible(&mut self, visible_rows: usize) {
+        let layout = self.row_layout();
+        if self.cursor.line < self.scroll_y {
+            self.scroll_y = self.cursor.line;
+            return;
+        }
+
+        // Rows from the top of the view down to and including the caret's row
+        let caret_rows = |doc: &Document| {
+            let above: usize = (doc.scroll_y..doc.cursor.line)
+                .map(|line| doc.screen_rows(line).len())
+                .sum();
+            let rows = layout.rows(&doc.buffer, doc.cursor.line);
+            above + RowLayout::row_of(&rows, doc.cursor.col) + 1
+        };
+        while self.scroll_y < self.cursor.line && caret_rows(self) > visible_rows {
+            self.scroll_y += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(text: &str, width: usize) -> Vec<(usize, usize)> {
+        let buffer = Buffer::from_str(text);
+        RowLayout {
+            tab_width: 4,
+            wrap_width: Some(width),
+        }
+        .rows(&buffer, 0)
+        .iter()
+        .map(|r| (r.start_col, r.end_col))
+        .collect()
+    }
+
+    #[test]
+    fn test_wraps_at_word_boundaries() {
+        assert_eq!(rows("hello big world", 10), vec![(0, 10), (10, 15)]);
+        assert_eq!(rows("abcdefghij", 4), vec![(0, 4), (4, 8), (8, 10)]);
+        assert_eq!(rows("short", 10), vec![(0, 5)]);
+    }
+
+    #[test]
+    fn test_continuation_rows_are_indented() {
+        let buffer = Buffer::from_str("  aaa bbb ccc");
+        let layout = RowLayout {
+            tab_width: 4,
+            wrap_width: Some(8),
+        };
+        let rows = layout.rows(&buffer, 0);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].indent, 2);
+        assert_eq!((rows[1].start_col, rows[1].end_col), (6, 10));
+    }
+
+    #[test]
+    fn test_move_by_visual_rows() {
+        let mut doc = Document::new();
+        doc.insert_str("one two three four\nx");
+        doc.wrap_width = Some(8);
+        doc.move_to(0, 1, false);
+
+        doc.move_down(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (0, 9));
+        doc.move_down(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (0, 15));
+        doc.move_down(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (1, 1));
+        doc.move_up(false);
+        assert_eq!((doc.cursor.line, doc.cursor.col), (0, 15));
+    }
+}
@@ -0,0 +1,92 @@
+//! Watching open files for changes made outside gterm
+//!
+//! The directories containing open files are watched rather than the files
+//! themselves, so a file replaced by rename (as most editors and gterm itself
+//! save) keeps being watched. Events are only collected here; the app decides
+//! what changed by comparing each document's file with what it last read or
+//! saved.
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// A file touched on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    /// Created, written, removed or otherwise changed
+    Changed(PathBuf),
+    /// Renamed within a watched directory
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+/// Watches the directories of open files
+pub struct FileWatcher {
+    /// None if the platform watcher could not be created
+    watcher: Option<RecommendedWatcher>,
+    rx: Receiver<notify::Result<notify::Event>>,
+    /// Directories currently watched
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .ok();
+        Self {
+            watcher,
+            rx,
+            dirs: HashSet::new(),
+        }
+    }
+
+    /// Watch exactly the directories containing `paths`
+    pub fn watch_files<'a>(&mut self, paths: impl Iterator<Item = &'a Path>) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let wanted: HashSet<PathBuf> = paths.map(parent_dir).collect();
+        for dir in self.dirs.difference(&wanted) {
+            let _ = watcher.unwatch(dir);
+        }
+        let mut watched = HashSet::new();
+        for dir in wanted {
+            if self.dirs.contains(&dir) || watcher.watch(&dir, RecursiveMode::NonRecursive).is_ok()
+            {
+                watched.insert(dir);
+            }
+        }
+        self.dirs = watched;
+    }
+
+    /// Events received since the last call
+    pub fn events(&self) -> Vec<FileEvent> {
+        let mut events = Vec::new();
+        for event in self.rx.try_iter().flatten() {
+            match event.kind {
+                EventKind::Access(_) => {}
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                    events.push(FileEvent::Renamed {
+                        from: event.paths[0].clone(),
+                        to: event.paths[1].clone(),
+                    });
+                }
+                _ => events.extend(event.paths.into_iter().map(FileEvent::Changed)),
+            }
+        }
+        events
+    }
+}
+
+/// The directory to watch for a file; events name files inside it the same
+/// way `path` does
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
@@ -8,9 +8,10 @@
 
 use ratatui::style::Color;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::PathBuf;
 use syntect::highlighting::{Theme, ThemeSet};
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
 
 /// Manages syntax highlighting resources
 pub struct HighlightingManager {
@@ -22,6 +23,11 @@ pub struct HighlightingManager {
     pub current_theme: String,
     /// Cache mapping filetype names to syntax references
     filetype_cache: HashMap<String, String>, // filetype -> syntax name
+    /// Parser states at the start of each scanned line of the document whose
+    /// strings and comments were scanned last, by document id. They live here
+    /// rather than in the document because syntect's parser state cannot move
+    /// between threads, and documents are loaded on worker threads.
+    literal_states: (Option<u64>, Vec<LiteralState>),
 }
 
 impl HighlightingManager {
@@ -76,6 +82,7 @@ impl HighlightingManager {
             theme_set,
             current_theme: "base16-ocean.dark".to_string(),
             filetype_cache: Self::build_filetype_cache(),
+            literal_states: (None, Vec::new()),
         }
     }
 
@@ -183,6 +190,72 @@ impl HighlightingManager {
     pub fn plain_text_syntax(&self) -> &SyntaxReference {
         self.syntax_set.find_syntax_plain_text()
     }
+
+    /// Parser state at the start of a file of this filetype, for scanning its
+    /// strings and comments line by line (None if the filetype has no syntax)
+    pub fn literal_state(&self, filetype: &str) -> Option<LiteralState> {
+        let syntax = self.syntax_for_filetype(filetype)?;
+        Some(LiteralState {
+            parse: ParseState::new(syntax),
+            stack: ScopeStack::new(),
+            string: Scope::new("string").ok()?,
+            comment: Scope::new("comment").ok()?,
+        })
+    }
+
+    /// The syntax set and the kept parser states of document `doc_id`'s
+    /// string/comment scan; the states of another document are dropped
+    pub fn literal_states(&mut self, doc_id: u64) -> (&SyntaxSet, &mut Vec<LiteralState>) {
+        if self.literal_states.0 != Some(doc_id) {
+            self.literal_states = (Some(doc_id), Vec::new());
+        }
+        (&self.syntax_set, &mut self.literal_states.1)
+    }
+}
+
+/// Where the string/comment scan of a document is at the start of a line;
+/// kept per line so a scan can resume after an edit further down
+#[derive(Debug, Clone)]
+pub struct LiteralState {
+    parse: ParseState,
+    stack: ScopeStack,
+    string: Scope,
+    comment: Scope,
+}
+
+impl LiteralState {
+    /// Character ranges inside strings and comments on one line (given with
+    /// its line ending), for code that has to skip them (e.g. bracket
+    /// matching); the state moves on to the start of the next line
+    pub fn scan_line(&mut self, syntax_set: &SyntaxSet, line: &str) -> Option<Vec<Range<usize>>> {
+        let ops = self.parse.parse_line(line, syntax_set).ok()?;
+        let (string, comment) = (self.string, self.comment);
+        let in_literal = |stack: &ScopeStack| {
+            stack
+                .as_slice()
+                .iter()
+                .any(|s| string.is_prefix_of(*s) || comment.is_prefix_of(*s))
+        };
+
+        // Ops are keyed by byte offset; ranges are in chars
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let mut ops = ops.into_iter().peekable();
+        for (col, (byte, _)) in line.char_indices().enumerate() {
+            while let Some((_, op)) = ops.next_if(|(at, _)| *at <= byte) {
+                self.stack.apply(&op).ok()?;
+            }
+            if in_literal(&self.stack) {
+                match ranges.last_mut() {
+                    Some(last) if last.end == col => last.end = col + 1,
+                    _ => ranges.push(col..col + 1),
+                }
+            }
+        }
+        for (_, op) in ops {
+            self.stack.apply(&op).ok()?;
+        }
+        Some(ranges)
+    }
 }
 
 impl Default for HighlightingManager {
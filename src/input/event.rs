@@ -1,3 +1,5 @@
+use crate::editor::TextCase;
+
 /// Application-level events (commands/actions)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AppEvent {
@@ -14,6 +16,13 @@ pub enum AppEvent {
     // View toggles
     ToggleSidebar,
     ToggleTerminal,
+    ToggleWordWrap,
+
+    // Folding
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
 
     // File operations
     NewFile,
@@ -35,6 +44,25 @@ pub enum AppEvent {
     DuplicateLine,
     MoveLineUp,
     MoveLineDown,
+    AddCursorAbove,
+    AddCursorBelow,
+    AddNextOccurrence,
+    ToggleLineComment,
+    ToggleBlockComment,
+    IndentLines,
+    UnindentLines,
+    SortLines,
+    RemoveDuplicateLines,
+    ReverseLines,
+    JoinLines,
+    ChangeCase(TextCase),
+    TransposeChars,
+
+    // Keyboard macros
+    ToggleMacroRecording,
+    PlayLastMacro,
+    PlayMacro,
+    SaveMacro,
 
     // Search
     Find,
@@ -45,6 +73,10 @@ pub enum AppEvent {
 
     // Navigation
     GoToMatchingBrace,
+    SelectToMatchingBrace,
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
     NextTab,
     PreviousTab,
     GoToTab(u8),
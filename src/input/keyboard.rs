@@ -1,4 +1,5 @@
 use super::AppEvent;
+use crate::editor::TextCase;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 /// Maps keyboard events to application events
@@ -28,6 +29,26 @@ pub fn map_key_event(key: KeyEvent) -> Option<AppEvent> {
         KeyCode::Char('a') if ctrl => Some(AppEvent::SelectAll),
         KeyCode::Char('k') if ctrl => Some(AppEvent::DeleteLine),
         KeyCode::Char('d') if ctrl => Some(AppEvent::DuplicateLine),
+        KeyCode::Char('e') | KeyCode::Char('E') if ctrl && shift => {
+            Some(AppEvent::ToggleBlockComment)
+        }
+        KeyCode::Char('e') if ctrl => Some(AppEvent::ToggleLineComment),
+        KeyCode::Char('i') if ctrl => Some(AppEvent::IndentLines),
+        KeyCode::Char('u') if ctrl => Some(AppEvent::UnindentLines),
+        KeyCode::Char('j') if ctrl => Some(AppEvent::JoinLines),
+        KeyCode::F(9) if ctrl => Some(AppEvent::RemoveDuplicateLines),
+        KeyCode::F(9) if shift => Some(AppEvent::ReverseLines),
+        KeyCode::F(9) => Some(AppEvent::SortLines),
+        KeyCode::F(7) => Some(AppEvent::ToggleMacroRecording),
+        KeyCode::F(8) if ctrl => Some(AppEvent::SaveMacro),
+        KeyCode::F(8) if shift => Some(AppEvent::PlayMacro),
+        KeyCode::F(8) => Some(AppEvent::PlayLastMacro),
+        KeyCode::Char('u') if alt => Some(AppEvent::ChangeCase(TextCase::Upper)),
+        KeyCode::Char('l') if alt => Some(AppEvent::ChangeCase(TextCase::Lower)),
+        KeyCode::Char('c') if alt => Some(AppEvent::ChangeCase(TextCase::Title)),
+        KeyCode::Char('S') if alt => Some(AppEvent::ChangeCase(TextCase::Snake)),
+        KeyCode::Char('C') if alt => Some(AppEvent::ChangeCase(TextCase::Camel)),
+        KeyCode::Char('t') if alt => Some(AppEvent::TransposeChars),
         KeyCode::Up if alt => Some(AppEvent::MoveLineUp),
         KeyCode::Down if alt => Some(AppEvent::MoveLineDown),
 
@@ -41,7 +62,11 @@ pub fn map_key_event(key: KeyEvent) -> Option<AppEvent> {
         KeyCode::Char('l') if ctrl => Some(AppEvent::GoToLine),
 
         // Navigation
+        KeyCode::Char('b') if ctrl && alt => Some(AppEvent::SelectToMatchingBrace),
         KeyCode::Char('b') if ctrl => Some(AppEvent::GoToMatchingBrace),
+        KeyCode::Char('m') if ctrl || alt => Some(AppEvent::ToggleBookmark),
+        KeyCode::Char('.') if ctrl || alt => Some(AppEvent::NextBookmark),
+        KeyCode::Char(',') if ctrl || alt => Some(AppEvent::PrevBookmark),
         KeyCode::PageDown if ctrl => Some(AppEvent::NextTab),
         KeyCode::PageUp if ctrl => Some(AppEvent::PreviousTab),
 
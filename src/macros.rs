@@ -0,0 +1,253 @@
+//! Keyboard macros
+//!
+//! Keys typed into the editor can be recorded and replayed. The last recorded
+//! macro can be saved under a name; named macros are stored in `macros.toml`
+//! in gterm's config directory as lists of key names like `"Ctrl+Left"`.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Location of the saved macros
+pub fn macros_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("gterm").join("macros.toml"))
+}
+
+/// How often to replay a macro
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Times(usize),
+    /// Until a run no longer brings the caret closer to the end of the file
+    ToEnd,
+}
+
+/// Contents of the macros file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct MacrosFile {
+    /// Key names of each macro by name
+    #[serde(default)]
+    macros: BTreeMap<String, Vec<String>>,
+}
+
+/// The macro being recorded, the last recorded one and the saved ones
+#[derive(Debug, Default)]
+pub struct Macros {
+    /// Keys recorded so far (None = not recording)
+    recording: Option<Vec<KeyEvent>>,
+    /// The last recorded macro
+    last: Vec<KeyEvent>,
+    /// Saved macros by name
+    saved: BTreeMap<String, Vec<KeyEvent>>,
+    /// Where saved macros are stored (None = not stored)
+    path: Option<PathBuf>,
+}
+
+impl Macros {
+    /// Read saved macros from `path`; a missing or unreadable file gives none,
+    /// and macros with keys that cannot be read back are left out
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let file: MacrosFile = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        let saved = file
+            .macros
+            .into_iter()
+            .filter_map(|(name, keys)| {
+                let keys = keys.iter().map(|k| parse_key(k)).collect::<Option<_>>()?;
+                Some((name, keys))
+            })
+            .collect();
+        Self {
+            saved,
+            path,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Start recording, or stop and keep what was recorded as the last macro
+    /// (an empty recording keeps the previous one)
+    pub fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(keys) if !keys.is_empty() => self.last = keys,
+            Some(_) => {}
+            None => self.recording = Some(Vec::new()),
+        }
+    }
+
+    /// Add a key to the macro being recorded, if any
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some(keys) = &mut self.recording {
+            keys.push(KeyEvent::new(key.code, key.modifiers));
+        }
+    }
+
+    /// The last recorded macro
+    pub fn last(&self) -> &[KeyEvent] {
+        &self.last
+    }
+
+    /// Names of the saved macros in order
+    pub fn names(&self) -> Vec<String> {
+        self.saved.keys().cloned().collect()
+    }
+
+    /// A saved macro
+    pub fn get(&self, name: &str) -> Option<&[KeyEvent]> {
+        self.saved.get(name).map(Vec::as_slice)
+    }
+
+    /// Save the last recorded macro under `name`, replacing any macro with
+    /// that name
+    pub fn save_last(&mut self, name: &str) -> std::io::Result<()> {
+        self.saved.insert(name.to_string(), self.last.clone());
+        self.write()
+    }
+
+    /// Delete a saved macro
+    pub fn remove(&mut self, name: &str) -> std::io::Result<()> {
+        if self.saved.remove(name).is_some() {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Write the saved macros to the macros file
+    fn write(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = MacrosFile {
+            macros: self
+                .saved
+                .iter()
+                .map(|(name, keys)| (name.clone(), keys.iter().map(key_name).collect()))
+                .collect(),
+        };
+        let text = toml::to_string(&file).map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)
+    }
+}
+
+/// Names of keys that are not characters
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::BackTab, "BackTab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Char(' '), "Space"),
+];
+
+/// Modifier prefixes of key names
+const MODIFIER_NAMES: &[(KeyModifiers, &str)] = &[
+    (KeyModifiers::CONTROL, "Ctrl+"),
+    (KeyModifiers::ALT, "Alt+"),
+    (KeyModifiers::SHIFT, "Shift+"),
+];
+
+/// Name of a key as stored in the macros file, e.g. `Ctrl+Shift+Left`
+fn key_name(key: &KeyEvent) -> String {
+    let mut name: String = MODIFIER_NAMES
+        .iter()
+        .filter(|(modifier, _)| key.modifiers.contains(*modifier))
+        .map(|(_, prefix)| *prefix)
+        .collect();
+    match KEY_NAMES.iter().find(|(code, _)| *code == key.code) {
+        Some((_, key)) => name.push_str(key),
+        None => match key.code {
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::F(n) => name.push_str(&format!("F{}", n)),
+            code => name.push_str(&format!("{:?}", code)),
+        },
+    }
+    name
+}
+
+/// Read a key name written by `key_name`
+fn parse_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    // A lone `+` is the key itself, not a modifier separator
+    while let Some((modifier, prefix)) = MODIFIER_NAMES
+        .iter()
+        .find(|(_, prefix)| rest.len() > prefix.len() && rest.starts_with(prefix))
+    {
+        modifiers |= *modifier;
+        rest = &rest[prefix.len()..];
+    }
+    let code = match KEY_NAMES.iter().find(|(_, key)| *key == rest) {
+        Some((code, _)) => *code,
+        None => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => KeyCode::F(rest.strip_prefix('F')?.parse().ok()?),
+            }
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_names_round_trip() {
+        for key in [
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('+'), KeyModifiers::CONTROL),
+            KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::F(9), KeyModifiers::SHIFT),
+        ] {
+            assert_eq!(parse_key(&key_name(&key)), Some(key));
+        }
+        assert_eq!(
+            key_name(&KeyEvent::new(KeyCode::Char('+'), KeyModifiers::CONTROL)),
+            "Ctrl++"
+        );
+        assert_eq!(parse_key("Ctrl+Nope"), None);
+    }
+
+    #[test]
+    fn test_saved_macros_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("gterm-macros-{}", std::process::id()));
+        let path = dir.join("macros.toml");
+
+        let mut macros = Macros::load(Some(path.clone()));
+        macros.toggle_recording();
+        macros.record(KeyEvent::new(KeyCode::Home, KeyModifiers::NONE));
+        macros.record(KeyEvent::new(KeyCode::Char('#'), KeyModifiers::NONE));
+        macros.record(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        macros.toggle_recording();
+        assert!(!macros.is_recording());
+        macros.save_last("comment").unwrap();
+
+        let macros = Macros::load(Some(path));
+        assert_eq!(macros.names(), vec!["comment".to_string()]);
+        assert_eq!(macros.get("comment").map(|keys| keys.len()), Some(3));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+//! Crash recovery through swap files
+//!
+//! While a document has unsaved changes its text is written every few seconds
+//! to a swap file in gterm's state directory (`~/.local/state/gterm/swap` on
+//! Linux). Swap files are removed when the document is saved or closed and
+//! when gterm exits normally, so any left behind by a gterm process that is no
+//! longer running belong to a session that died and are offered for recovery.
+//! A process holds a lock on its `<pid>.lock` file while it has swap files, so
+//! a lock that can be taken means its owner is gone.
+//! Documents in large-file mode get no swap file.
+
+use crate::editor::Document;
+use crate::utils::diff;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Directory holding swap files
+pub fn swap_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|p| p.join("gterm").join("swap"))
+}
+
+/// Line between the header of a swap file and the document text
+const TEXT_SEPARATOR: &str = "\n---\n";
+
+/// Header of a swap file, followed by the document text as is (with LF line
+/// endings) after a `---` line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SwapHeader {
+    /// File the document belongs to (None for untitled documents)
+    path: Option<PathBuf>,
+    /// Tab title when the swap was written
+    title: String,
+    /// Seconds since the Unix epoch
+    written_at: u64,
+}
+
+/// A swap file left behind by a gterm session that ended unexpectedly
+#[derive(Debug, Clone)]
+pub struct SwapFile {
+    /// Location of the swap file itself
+    pub swap_path: PathBuf,
+    /// File the unsaved text belongs to (None for untitled documents)
+    pub path: Option<PathBuf>,
+    pub title: String,
+    /// Seconds since the Unix epoch
+    pub written_at: u64,
+    pub text: String,
+}
+
+impl SwapFile {
+    /// Swap files in `dir` whose gterm process is no longer running, newest first
+    pub fn find_orphans(dir: &Path) -> Vec<SwapFile> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut orphans: Vec<SwapFile> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "swap"))
+            .filter(|path| swap_pid(path).is_some_and(|pid| !process_alive(dir, pid)))
+            .filter_map(SwapFile::read)
+            .collect();
+        orphans.sort_by_key(|swap| std::cmp::Reverse(swap.written_at));
+        orphans
+    }
+
+    /// Read a swap file
+    fn read(swap_path: PathBuf) -> Option<SwapFile> {
+        let mut contents = std::fs::read_to_string(&swap_path).ok()?;
+        // TOML strings escape line breaks, so the separator cannot be in the header
+        let split = contents.find(TEXT_SEPARATOR)?;
+        let header: SwapHeader = toml::from_str(&contents[..split]).ok()?;
+        let text = contents.split_off(split + TEXT_SEPARATOR.len());
+        Some(SwapFile {
+            swap_path,
+            path: header.path,
+            title: header.title,
+            written_at: header.written_at,
+            text,
+        })
+    }
+
+    /// Unified diff of the file on disk against the unsaved text
+    pub fn diff(&self) -> Vec<String> {
+        let on_disk = self
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .map(|bytes| {
+                let (text, _) = crate::editor::TextEncoding::detect(&bytes);
+                crate::editor::LineEnding::to_lf(&text)
+            })
+            .unwrap_or_default();
+        diff::unified(&on_disk, &self.text, 2)
+    }
+
+    /// Document with the unsaved text; undo brings back the file on disk
+    pub fn recover(&self) -> Document {
+        match &self.path {
+            Some(path) => {
+                let mut doc = Document::open(path.clone()).unwrap_or_else(|_| {
+                    let mut doc = Document::new();
+                    doc.path = Some(path.clone());
+                    doc
+                });
+                doc.replace_text(&self.text);
+                doc
+            }
+            None => Document::from_str(&self.text),
+        }
+    }
+
+    /// Delete the swap file, and the lock file of its process with the last one
+    pub fn discard(&self) {
+        let _ = std::fs::remove_file(&self.swap_path);
+        let (Some(dir), Some(pid)) = (self.swap_path.parent(), swap_pid(&self.swap_path)) else {
+            return;
+        };
+        let others = std::fs::read_dir(dir).map(|entries| {
+            entries.flatten().any(|entry| {
+                let path = entry.path();
+                path.extension().is_some_and(|ext| ext == "swap") && swap_pid(&path) == Some(pid)
+            })
+        });
+        if others.is_ok_and(|others| !others) {
+            let _ = std::fs::remove_file(lock_path(dir, pid));
+        }
+    }
+
+    /// How long ago the swap was written, e.g. "5 min ago"
+    pub fn age(&self) -> String {
+        let secs = unix_time().saturating_sub(self.written_at);
+        match secs {
+            0..=59 => format!("{} s ago", secs),
+            60..=3599 => format!("{} min ago", secs / 60),
+            3600..=86399 => format!("{} h ago", secs / 3600),
+            _ => format!("{} days ago", secs / 86400),
+        }
+    }
+}
+
+/// Keeps swap files in step with the open documents
+pub struct SwapWriter {
+    dir: Option<PathBuf>,
+    /// Time between updates (None = swap files are off)
+    interval: Option<Duration>,
+    last_update: Instant,
+    /// Document version last written, by document id
+    written: HashMap<u64, u64>,
+    /// This process's lock file, created with the first swap file
+    lock: Option<File>,
+}
+
+impl SwapWriter {
+    /// Write swap files to `dir` every `interval_secs` seconds (0 = never)
+    pub fn new(dir: Option<PathBuf>, interval_secs: u64) -> Self {
+        Self {
+            dir,
+            interval: (interval_secs > 0).then(|| Duration::from_secs(interval_secs)),
+            last_update: Instant::now(),
+            written: HashMap::new(),
+            lock: None,
+        }
+    }
+
+    /// Update the swap files if the interval has passed
+    pub fn tick(&mut self, documents: &[Document]) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        if self.last_update.elapsed() >= interval {
+            self.last_update = Instant::now();
+            self.update(documents);
+        }
+    }
+
+    /// Write swap files for modified documents that changed since the last
+    /// update, and remove those of saved or closed documents
+    pub fn update(&mut self, documents: &[Document]) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+
+        let mut keep = HashMap::new();
+        for doc in documents {
+            // Binary documents have no text to recover, and writing large
+            // files every few seconds would stall the editor
+            if !doc.modified || doc.hex.is_some() || doc.large_file {
+                continue;
+            }
+            if self.written.get(&doc.id) != Some(&doc.version) {
+                let header = SwapHeader {
+                    path: doc.path.clone(),
+                    title: doc.title(),
+                    written_at: unix_time(),
+                };
+                if self.lock.is_none() {
+                    self.lock = lock(dir);
+                }
+                let written = self.lock.is_some()
+                    && toml::to_string(&header).is_ok_and(|header| {
+                        let mut contents = header.trim_end().to_string();
+                        contents.push_str(TEXT_SEPARATOR);
+                        for chunk in doc.buffer.rope().chunks() {
+                            contents.push_str(chunk);
+                        }
+                        crate::utils::fs::write_atomic(
+                            &swap_path(dir, doc.id),
+                            contents.as_bytes(),
+                            false,
+                        )
+                        .is_ok()
+                    });
+                if !written {
+                    continue;
+                }
+            }
+            keep.insert(doc.id, doc.version);
+        }
+
+        for id in self.written.keys() {
+            if !keep.contains_key(id) {
+                let _ = std::fs::remove_file(swap_path(dir, *id));
+            }
+        }
+        self.written = keep;
+    }
+
+    /// Remove every swap file of this process (on a normal exit)
+    pub fn remove_all(&mut self) {
+        self.update(&[]);
+        if let (Some(dir), Some(_)) = (&self.dir, self.lock.take()) {
+            let _ = std::fs::remove_file(lock_path(dir, std::process::id()));
+        }
+    }
+}
+
+/// Swap file of a document of this process: `<pid>-<document id>.swap`
+fn swap_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{}-{}.swap", std::process::id(), id))
+}
+
+/// The process id a swap file name starts with
+fn swap_pid(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    stem.split('-').next()?.parse().ok()
+}
+
+/// Lock file of a gterm process: `<pid>.lock`
+fn lock_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{}.lock", pid))
+}
+
+/// Create and lock this process's lock file; it stays locked until the file is
+/// dropped or the process ends. Where locking is not supported the file is
+/// still kept, and other processes take it for a running one. None if another
+/// process holds the lock (a stale file of a reused pid), so that no swap files
+/// are written that it could take for its own.
+fn lock(dir: &Path) -> Option<File> {
+    std::fs::create_dir_all(dir).ok()?;
+    let file = File::create(lock_path(dir, std::process::id())).ok()?;
+    match file.try_lock() {
+        Ok(()) | Err(TryLockError::Error(_)) => Some(file),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Whether the gterm process with this id is running, told by the lock on its
+/// lock file (never true for ourselves, so the swap files of a previous process
+/// that had our pid are still found)
+fn process_alive(dir: &Path, pid: u32) -> bool {
+    if pid == std::process::id() {
+        return false;
+    }
+    // A running process creates its lock file before any swap file
+    let Ok(file) = File::open(lock_path(dir, pid)) else {
+        return false;
+    };
+    match file.try_lock() {
+        Ok(()) => false,
+        Err(TryLockError::WouldBlock) => true,
+        // Without locking there is no telling, and recovering the swap files
+        // of a running process would take them from it
+        Err(TryLockError::Error(_)) => true,
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_written_for_modified_and_removed_on_save() {
+        let dir = std::env::temp_dir().join(format!("gterm-swap-{}", std::process::id()));
+        let mut writer = SwapWriter::new(Some(dir.clone()), 1);
+        let mut doc = Document::new();
+        doc.insert_str("unsaved\n---\n");
+
+        writer.update(std::slice::from_ref(&doc));
+        let swap = swap_path(&dir, doc.id);
+        let contents = SwapFile::read(swap.clone()).unwrap();
+        assert_eq!(
+            (contents.path, contents.text.as_str()),
+            (None, "unsaved\n---\n")
+        );
+        // The text is stored as is, not as an escaped TOML string
+        let raw = std::fs::read_to_string(&swap).unwrap();
+        assert!(raw.ends_with("\n---\nunsaved\n---\n"));
+
+        doc.modified = false;
+        writer.update(std::slice::from_ref(&doc));
+        assert!(!swap.exists());
+
+        let mut large = Document::new();
+        large.insert_str("big\n");
+        large.large_file = true;
+        writer.update(std::slice::from_ref(&large));
+        assert!(!swap_path(&dir, large.id).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_alive_while_lock_is_held() {
+        let dir = std::env::temp_dir().join(format!("gterm-swap-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Another process's lock file, as far as `process_alive` can tell
+        let pid = std::process::id() + 1;
+        assert!(!process_alive(&dir, pid));
+
+        let file = File::create(lock_path(&dir, pid)).unwrap();
+        file.lock().unwrap();
+        assert!(process_alive(&dir, pid));
+        drop(file);
+        assert!(!process_alive(&dir, pid));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_no_swap_written_without_the_lock() {
+        let dir = std::env::temp_dir().join(format!("gterm-swap-held-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Someone else holds the lock file of our pid
+        let held = File::create(lock_path(&dir, std::process::id())).unwrap();
+        held.lock().unwrap();
+
+        let mut writer = SwapWriter::new(Some(dir.clone()), 1);
+        let mut doc = Document::new();
+        doc.insert_str("unsaved\n");
+        writer.update(std::slice::from_ref(&doc));
+        assert!(!swap_path(&dir, doc.id).exists());
+
+        drop(held);
+        writer.update(std::slice::from_ref(&doc));
+        assert!(swap_path(&dir, doc.id).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
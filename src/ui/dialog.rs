@@ -1,4 +1,7 @@
 use crate::app::App;
+use crate::editor::{IndentStyle, TextEncoding, ENCODINGS};
+use crate::macros::Repeat;
+use crate::recovery::SwapFile;
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
@@ -18,6 +21,18 @@ pub enum Dialog {
     GoToLine(GoToLineDialog),
     /// About dialog
     About(AboutDialog),
+    /// Set/reopen with encoding dialog
+    Encoding(EncodingDialog),
+    /// Unsaved changes recovered from swap files after a crash
+    Recovery(RecoveryDialog),
+    /// A file with local edits was changed by another program
+    FileChanged(FileChangedDialog),
+    /// Indentation style and width of the document
+    Indent(IndentDialog),
+    /// Which keyboard macro to replay and how often
+    PlayMacro(PlayMacroDialog),
+    /// Name to save the last recorded keyboard macro under
+    SaveMacro(SaveMacroDialog),
 }
 
 /// File open dialog state
@@ -78,6 +93,76 @@ pub struct GoToLineDialog {
     pub error: Option<String>,
 }
 
+/// Encoding dialog: pick the encoding to save with, or to re-read the file with
+#[derive(Debug, Clone)]
+pub struct EncodingDialog {
+    /// Index into `ENCODINGS`
+    pub selected: usize,
+    /// Whether to write a byte order mark (Unicode encodings only)
+    pub bom: bool,
+    /// Reopen the file with the encoding instead of saving with it
+    pub reopen: bool,
+}
+
+/// Indentation choices offered in the indentation dialog
+pub const INDENT_OPTIONS: &[(IndentStyle, usize)] = &[
+    (IndentStyle::Tabs, 0),
+    (IndentStyle::Spaces, 2),
+    (IndentStyle::Spaces, 4),
+    (IndentStyle::Spaces, 8),
+];
+
+/// Indentation dialog: indent with tabs or spaces from now on, or convert the file
+#[derive(Debug, Clone)]
+pub struct IndentDialog {
+    /// Index into `INDENT_OPTIONS`
+    pub selected: usize,
+    /// Re-indent the whole file instead of only changing what Tab inserts
+    pub convert: bool,
+}
+
+/// Recovery dialog: swap files left by a session that ended unexpectedly
+#[derive(Debug, Clone)]
+pub struct RecoveryDialog {
+    pub entries: Vec<SwapFile>,
+    pub selected: usize,
+    /// Diff of the selected entry against the file on disk
+    pub diff: Vec<String>,
+}
+
+/// File changed dialog: reload the file or keep the edited buffer
+#[derive(Debug, Clone)]
+pub struct FileChangedDialog {
+    /// `Document::id` of the edited document
+    pub doc_id: u64,
+    pub title: String,
+    /// Diff of the file on disk against the buffer, once asked for
+    pub diff: Option<Vec<String>>,
+}
+
+/// Play macro dialog: the last recorded macro or a saved one, replayed a
+/// number of times or until the end of the file
+#[derive(Debug, Clone)]
+pub struct PlayMacroDialog {
+    /// Names of the saved macros
+    pub names: Vec<String>,
+    /// 0 = the last recorded macro, otherwise `names[selected - 1]`
+    pub selected: usize,
+    /// Repeat count input
+    pub count: String,
+    /// Repeat until the end of the file instead of `count` times
+    pub to_end: bool,
+}
+
+/// Save macro dialog
+#[derive(Debug, Clone)]
+pub struct SaveMacroDialog {
+    /// Name input
+    pub name: String,
+    /// Error message if the macro could not be saved
+    pub error: Option<String>,
+}
+
 /// About dialog
 #[derive(Debug, Clone)]
 pub struct AboutDialog {
@@ -240,6 +325,214 @@ impl AboutDialog {
     }
 }
 
+impl EncodingDialog {
+    /// Create an encoding dialog with the document's current encoding selected
+    pub fn new(current: &TextEncoding, reopen: bool) -> Self {
+        let selected = ENCODINGS
+            .iter()
+            .position(|&name| name == current.encoding.name())
+            .unwrap_or(0);
+        Self {
+            selected,
+            bom: current.bom,
+            reopen,
+        }
+    }
+
+    /// Move selection up
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Move selection down
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < ENCODINGS.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Toggle writing a byte order mark
+    pub fn toggle_bom(&mut self) {
+        self.bom = !self.bom;
+    }
+
+    /// Switch between setting the encoding and reopening with it
+    pub fn toggle_mode(&mut self) {
+        self.reopen = !self.reopen;
+    }
+
+    /// The selected encoding; only Unicode encodings keep the BOM flag
+    pub fn encoding(&self) -> TextEncoding {
+        let name = ENCODINGS[self.selected];
+        let bom = self.bom && name.starts_with("UTF-");
+        TextEncoding::for_label(name, bom).unwrap_or_default()
+    }
+}
+
+impl IndentDialog {
+    /// Create an indentation dialog with the document's current indentation selected
+    pub fn new(style: IndentStyle, width: usize) -> Self {
+        let selected = INDENT_OPTIONS
+            .iter()
+            .position(|&(s, w)| s == style && (s == IndentStyle::Tabs || w == width))
+            .unwrap_or(2);
+        Self {
+            selected,
+            convert: false,
+        }
+    }
+
+    /// Move selection up
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Move selection down
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < INDENT_OPTIONS.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Switch between setting the indentation and converting the file to it
+    pub fn toggle_mode(&mut self) {
+        self.convert = !self.convert;
+    }
+}
+
+impl RecoveryDialog {
+    /// Create a recovery dialog listing `entries` (which must not be empty)
+    pub fn new(entries: Vec<SwapFile>) -> Self {
+        let diff = entries.first().map(SwapFile::diff).unwrap_or_default();
+        Self {
+            entries,
+            selected: 0,
+            diff,
+        }
+    }
+
+    /// Move selection up
+    pub fn move_up(&mut self) {
+        self.select(self.selected.saturating_sub(1));
+    }
+
+    /// Move selection down
+    pub fn move_down(&mut self) {
+        self.select(self.selected + 1);
+    }
+
+    fn select(&mut self, index: usize) {
+        let index = index.min(self.entries.len().saturating_sub(1));
+        if index != self.selected {
+            self.selected = index;
+            self.diff = self.entries[index].diff();
+        }
+    }
+
+    /// Take the selected entry out of the list
+    pub fn take_selected(&mut self) -> Option<SwapFile> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let entry = self.entries.remove(self.selected);
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.diff = self
+            .entries
+            .get(self.selected)
+            .map(SwapFile::diff)
+            .unwrap_or_default();
+        Some(entry)
+    }
+}
+
+impl PlayMacroDialog {
+    /// Create a play macro dialog with the last recorded macro selected
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            names,
+            selected: 0,
+            count: "1".to_string(),
+            to_end: false,
+        }
+    }
+
+    /// Move selection up
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Move selection down
+    pub fn move_down(&mut self) {
+        if self.selected < self.names.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Handle character input (only digits allowed)
+    pub fn handle_input(&mut self, c: char) {
+        if c.is_ascii_digit() {
+            self.count.push(c);
+            self.to_end = false;
+        }
+    }
+
+    /// Handle backspace
+    pub fn handle_backspace(&mut self) {
+        self.count.pop();
+    }
+
+    /// Switch between a repeat count and repeating to the end of the file
+    pub fn toggle_to_end(&mut self) {
+        self.to_end = !self.to_end;
+    }
+
+    /// Name of the selected saved macro (None = the last recorded one)
+    pub fn selected_name(&self) -> Option<&str> {
+        self.selected
+            .checked_sub(1)
+            .and_then(|i| self.names.get(i))
+            .map(String::as_str)
+    }
+
+    /// Take the selected saved macro out of the list
+    pub fn remove_selected(&mut self) -> Option<String> {
+        let index = self.selected.checked_sub(1)?;
+        let name = self.names.remove(index);
+        self.selected = self.selected.min(self.names.len());
+        Some(name)
+    }
+
+    /// How often to replay; an empty or zero count plays once
+    pub fn repeat(&self) -> Repeat {
+        if self.to_end {
+            Repeat::ToEnd
+        } else {
+            Repeat::Times(self.count.parse().unwrap_or(1).max(1))
+        }
+    }
+}
+
+impl SaveMacroDialog {
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            error: None,
+        }
+    }
+
+    /// Handle character input
+    pub fn handle_input(&mut self, c: char) {
+        self.name.push(c);
+        self.error = None;
+    }
+
+    /// Handle backspace
+    pub fn handle_backspace(&mut self) {
+        self.name.pop();
+        self.error = None;
+    }
+}
+
 impl GoToLineDialog {
     /// Create a new go to line dialog
     pub fn new(total_lines: usize) -> Self {
@@ -782,6 +1075,422 @@ pub fn draw_go_to_line_dialog(frame: &mut Frame, app: &App, dialog: &GoToLineDia
     frame.render_widget(help, chunks[2]);
 }
 
+/// Draw the encoding dialog
+pub fn draw_encoding_dialog(frame: &mut Frame, app: &App, dialog: &EncodingDialog) {
+    let area = frame.area();
+
+    let dialog_width = 44u16.min(area.width - 4);
+    let dialog_height = 18u16.min(area.height - 2);
+    let dialog_x = (area.width - dialog_width) / 2;
+    let dialog_y = (area.height - dialog_height) / 2;
+
+    let dialog_area = Rect {
+        x: dialog_x,
+        y: dialog_y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear area behind dialog
+    frame.render_widget(Clear, dialog_area);
+
+    let title = if dialog.reopen {
+        " Reopen with Encoding "
+    } else {
+        " Set Encoding "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border_focused))
+        .style(Style::default().bg(app.theme.sidebar_bg));
+
+    let inner = block.inner(dialog_area);
+    frame.render_widget(block, dialog_area);
+
+    // Split inner area: encoding list, BOM option, help
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Encoding list
+            Constraint::Length(1), // BOM option
+            Constraint::Length(1), // Help
+        ])
+        .split(inner);
+
+    // Scroll just enough to keep the selection in view
+    let list_height = (chunks[0].height as usize).max(1);
+    let scroll = dialog.selected.saturating_sub(list_height - 1);
+
+    let items: Vec<ListItem> = ENCODINGS
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(list_height)
+        .map(|(i, name)| {
+            let style = if i == dialog.selected {
+                Style::default()
+                    .fg(app.theme.menubar_bg)
+                    .bg(app.theme.statusbar_bg)
+            } else {
+                Style::default().fg(app.theme.fg)
+            };
+            ListItem::new(format!(" {}", name)).style(style)
+        })
+        .collect();
+    frame.render_widget(List::new(items), chunks[0]);
+
+    let bom = Paragraph::new(format!(
+        "[{}] Byte order mark (Unicode only)",
+        if dialog.bom { "x" } else { " " }
+    ))
+    .style(Style::default().fg(app.theme.fg));
+    frame.render_widget(bom, chunks[1]);
+
+    let help = Paragraph::new("Enter: Apply  Space: BOM  Tab: Set/Reopen  Esc")
+        .style(Style::default().fg(app.theme.line_number))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[2]);
+}
+
+/// Draw the indentation dialog
+pub fn draw_indent_dialog(frame: &mut Frame, app: &App, dialog: &IndentDialog) {
+    let area = frame.area();
+
+    let dialog_width = 44u16.min(area.width - 4);
+    let dialog_height = (INDENT_OPTIONS.len() as u16 + 3).min(area.height - 2);
+    let dialog_area = Rect {
+        x: (area.width - dialog_width) / 2,
+        y: (area.height - dialog_height) / 2,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear area behind dialog
+    frame.render_widget(Clear, dialog_area);
+
+    let title = if dialog.convert {
+        " Convert Indentation "
+    } else {
+        " Set Indentation "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border_focused))
+        .style(Style::default().bg(app.theme.sidebar_bg));
+
+    let inner = block.inner(dialog_area);
+    frame.render_widget(block, dialog_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let items: Vec<ListItem> = INDENT_OPTIONS
+        .iter()
+        .enumerate()
+        .map(|(i, &(style, width))| {
+            let label = match style {
+                IndentStyle::Tabs => " Tabs".to_string(),
+                IndentStyle::Spaces => format!(" {} Spaces", width),
+            };
+            let style = if i == dialog.selected {
+                Style::default()
+                    .fg(app.theme.menubar_bg)
+                    .bg(app.theme.statusbar_bg)
+            } else {
+                Style::default().fg(app.theme.fg)
+            };
+            ListItem::new(label).style(style)
+        })
+        .collect();
+    frame.render_widget(List::new(items), chunks[0]);
+
+    let help = Paragraph::new("Enter: Apply  Tab: Set/Convert  Esc")
+        .style(Style::default().fg(app.theme.line_number))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[1]);
+}
+
+/// Draw the play macro dialog
+pub fn draw_play_macro_dialog(frame: &mut Frame, app: &App, dialog: &PlayMacroDialog) {
+    let area = frame.area();
+
+    let dialog_width = 44u16.min(area.width - 4);
+    let dialog_height = (dialog.names.len() as u16 + 6).min(area.height - 2);
+    let dialog_area = Rect {
+        x: (area.width - dialog_width) / 2,
+        y: (area.height - dialog_height) / 2,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear area behind dialog
+    frame.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" Play Macro ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border_focused))
+        .style(Style::default().bg(app.theme.sidebar_bg));
+
+    let inner = block.inner(dialog_area);
+    frame.render_widget(block, dialog_area);
+
+    // Split inner area: macro list, repeat input, help
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let items: Vec<ListItem> = std::iter::once("(last recorded)")
+        .chain(dialog.names.iter().map(String::as_str))
+        .enumerate()
+        .map(|(i, name)| {
+            let style = if i == dialog.selected {
+                Style::default()
+                    .fg(app.theme.menubar_bg)
+                    .bg(app.theme.statusbar_bg)
+            } else {
+                Style::default().fg(app.theme.fg)
+            };
+            ListItem::new(format!(" {}", name)).style(style)
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(dialog.selected));
+    frame.render_stateful_widget(List::new(items), chunks[0], &mut state);
+
+    let repeat_text = if dialog.to_end {
+        "Repeat: to end of file".to_string()
+    } else {
+        format!("Repeat: {}| times", dialog.count)
+    };
+    let repeat = Paragraph::new(repeat_text)
+        .style(Style::default().fg(app.theme.fg).bg(app.theme.editor_bg));
+    frame.render_widget(repeat, chunks[1]);
+
+    let help = Paragraph::new("Enter: Play  Tab: Count/To End  Del: Delete")
+        .style(Style::default().fg(app.theme.line_number))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[2]);
+}
+
+/// Draw the save macro dialog
+pub fn draw_save_macro_dialog(frame: &mut Frame, app: &App, dialog: &SaveMacroDialog) {
+    let area = frame.area();
+
+    let dialog_width = 40u16.min(area.width - 4);
+    let dialog_height = 5u16;
+    let dialog_area = Rect {
+        x: (area.width - dialog_width) / 2,
+        y: (area.height - dialog_height) / 2,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear area behind dialog
+    frame.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" Save Macro ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border_focused))
+        .style(Style::default().bg(app.theme.sidebar_bg));
+
+    let inner = block.inner(dialog_area);
+    frame.render_widget(block, dialog_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // Input
+            Constraint::Length(1), // Error
+            Constraint::Length(1), // Help
+        ])
+        .split(inner);
+
+    let input = Paragraph::new(format!("Name: {}|", dialog.name))
+        .style(Style::default().fg(app.theme.fg).bg(app.theme.editor_bg));
+    frame.render_widget(input, chunks[0]);
+
+    if let Some(ref error) = dialog.error {
+        let error = Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red));
+        frame.render_widget(error, chunks[1]);
+    }
+
+    let help = Paragraph::new("Enter: Save  Esc: Cancel")
+        .style(Style::default().fg(app.theme.line_number))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[2]);
+}
+
+/// Diff lines colored by whether they were added or removed
+fn diff_lines<'a>(app: &App, diff: &'a [String], height: u16) -> Vec<Line<'a>> {
+    diff.iter()
+        .take(height as usize)
+        .map(|line| {
+            let color = match line.chars().next() {
+                Some('+') => Color::Green,
+                Some('-') => Color::Red,
+                _ => app.theme.line_number,
+            };
+            Line::from(Span::styled(line.as_str(), Style::default().fg(color)))
+        })
+        .collect()
+}
+
+/// Draw the crash recovery dialog
+pub fn draw_recovery_dialog(frame: &mut Frame, app: &App, dialog: &RecoveryDialog) {
+    let area = frame.area();
+
+    let dialog_width = (area.width * 4 / 5).max(40).min(area.width - 4);
+    let dialog_height = (area.height * 4 / 5).max(12).min(area.height - 2);
+    let dialog_x = (area.width - dialog_width) / 2;
+    let dialog_y = (area.height - dialog_height) / 2;
+
+    let dialog_area = Rect {
+        x: dialog_x,
+        y: dialog_y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear area behind dialog
+    frame.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" Recover Unsaved Changes ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border_focused))
+        .style(Style::default().bg(app.theme.sidebar_bg));
+
+    let inner = block.inner(dialog_area);
+    frame.render_widget(block, dialog_area);
+
+    // Split inner area: entry list, diff of the selected entry, help
+    let list_height = (dialog.entries.len() as u16).clamp(1, inner.height / 3);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(list_height), // Entries
+            Constraint::Length(1),           // Separator
+            Constraint::Min(1),              // Diff
+            Constraint::Length(1),           // Help
+        ])
+        .split(inner);
+
+    let scroll = dialog.selected.saturating_sub(list_height as usize - 1);
+    let items: Vec<ListItem> = dialog
+        .entries
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(list_height as usize)
+        .map(|(i, entry)| {
+            let style = if i == dialog.selected {
+                Style::default()
+                    .fg(app.theme.menubar_bg)
+                    .bg(app.theme.statusbar_bg)
+            } else {
+                Style::default().fg(app.theme.fg)
+            };
+            let location = entry
+                .path
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "untitled".to_string());
+            ListItem::new(format!(" {} ({}, {})", entry.title, location, entry.age())).style(style)
+        })
+        .collect();
+    frame.render_widget(List::new(items), chunks[0]);
+
+    let separator = Paragraph::new("─".repeat(chunks[1].width as usize))
+        .style(Style::default().fg(app.theme.border));
+    frame.render_widget(separator, chunks[1]);
+
+    let diff = diff_lines(app, &dialog.diff, chunks[2].height);
+    let diff = if diff.is_empty() {
+        Paragraph::new("Same as the file on disk").style(Style::default().fg(app.theme.line_number))
+    } else {
+        Paragraph::new(diff)
+    };
+    frame.render_widget(diff, chunks[2]);
+
+    let help = Paragraph::new("Enter: Recover  D: Discard  Esc: Decide later")
+        .style(Style::default().fg(app.theme.line_number))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[3]);
+}
+
+/// Draw the file changed dialog
+pub fn draw_file_changed_dialog(frame: &mut Frame, app: &App, dialog: &FileChangedDialog) {
+    let area = frame.area();
+
+    // Grow to show the diff once it is asked for
+    let (dialog_width, dialog_height) = match dialog.diff {
+        Some(_) => (
+            (area.width * 4 / 5).max(40).min(area.width - 4),
+            (area.height * 4 / 5).max(8).min(area.height - 2),
+        ),
+        None => (60u16.min(area.width - 4), 6u16),
+    };
+    let dialog_x = (area.width - dialog_width) / 2;
+    let dialog_y = (area.height - dialog_height) / 2;
+
+    let dialog_area = Rect {
+        x: dialog_x,
+        y: dialog_y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear area behind dialog
+    frame.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" File Changed on Disk ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border_focused))
+        .style(Style::default().bg(app.theme.sidebar_bg));
+
+    let inner = block.inner(dialog_area);
+    frame.render_widget(block, dialog_area);
+
+    // Split inner area: message, diff (when shown), help
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2), // Message
+            Constraint::Min(0),    // Diff
+            Constraint::Length(1), // Help
+        ])
+        .split(inner);
+
+    let message = Paragraph::new(format!(
+        "{} was changed by another program,\nbut it has unsaved changes here.",
+        dialog.title
+    ))
+    .style(Style::default().fg(app.theme.fg))
+    .alignment(Alignment::Center);
+    frame.render_widget(message, chunks[0]);
+
+    if let Some(diff) = &dialog.diff {
+        let lines = diff_lines(app, diff, chunks[1].height);
+        frame.render_widget(Paragraph::new(lines), chunks[1]);
+    }
+
+    let help = Paragraph::new("R: Reload  K/Esc: Keep mine  D: Show diff")
+        .style(Style::default().fg(app.theme.line_number))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[2]);
+}
+
 /// Draw an about dialog
 pub fn draw_about_dialog(frame: &mut Frame, app: &App, dialog: &AboutDialog) {
     let area = frame.area();
@@ -852,6 +1561,12 @@ pub fn draw_dialog(frame: &mut Frame, app: &App) {
             Dialog::Message(d) => draw_message_dialog(frame, app, d),
             Dialog::GoToLine(d) => draw_go_to_line_dialog(frame, app, d),
             Dialog::About(d) => draw_about_dialog(frame, app, d),
+            Dialog::Encoding(d) => draw_encoding_dialog(frame, app, d),
+            Dialog::Recovery(d) => draw_recovery_dialog(frame, app, d),
+            Dialog::FileChanged(d) => draw_file_changed_dialog(frame, app, d),
+            Dialog::Indent(d) => draw_indent_dialog(frame, app, d),
+            Dialog::PlayMacro(d) => draw_play_macro_dialog(frame, app, d),
+            Dialog::SaveMacro(d) => draw_save_macro_dialog(frame, app, d),
         }
     }
 }
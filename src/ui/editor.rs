@@ -1,4 +1,5 @@
 use crate::app::App;
+use crate::editor::{layout_line, VisualRow};
 use crate::highlighting::{HighlightStyle, StyledSpan};
 use ratatui::{
     prelude::*,
@@ -25,15 +26,22 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
     // Store editor area for mouse handling
     app.editor_area = Some(inner);
 
+    // Binary documents have their own view
+    if app.active_document().is_some_and(|doc| doc.hex.is_some()) {
+        super::hex_view::draw(frame, app, inner, focused);
+        return;
+    }
+
     // Copy theme colors we need
     let theme = app.theme.clone();
+    let word_wrap = app.config.editor.word_wrap;
 
     // Get visible area dimensions
     let visible_lines = inner.height as usize;
 
     // First pass: gather document info without highlighting
     let doc_info = {
-        let doc = match app.active_document_mut() {
+        let doc = match app.documents.get_mut(app.active_doc) {
             Some(d) => d,
             None => return,
         };
@@ -46,20 +54,44 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
             return;
         }
 
-        // Ensure cursor is visible
+        // Reflow to the current width, then ensure cursor is visible
+        doc.wrap_width = word_wrap.then_some(content_width);
         doc.ensure_cursor_visible(visible_lines, content_width);
 
+        // Bracket matching skips strings and comments on the lines in view
+        doc.scan_literals(&mut app.highlighting, doc.scroll_y + visible_lines);
+
         // Collect basic info
         DocInfo {
             line_count,
             scroll_y: doc.scroll_y,
             scroll_x: doc.scroll_x,
             cursor_line: doc.cursor.line,
-            cursor_col: doc.cursor.col,
-            selection: doc.selection.clone(),
+            selections: doc.selections(),
+            carets: doc
+                .selections()
+                .iter()
+                .map(|s| (s.head.line, s.head.col))
+                .chain(
+                    // A zero-width block shows a caret on every row
+                    doc.block_spans()
+                        .into_iter()
+                        .filter(|&(_, start, end)| start == end && !doc.has_selection())
+                        .map(|(line, start, _)| (line, start)),
+                )
+                .collect(),
+            block_spans: doc.block_spans(),
             gutter_width,
             content_width,
+            tab_width: doc.tab_width,
             filetype: doc.filetype.clone(),
+            large_file: doc.large_file,
+            ruler: doc.editorconfig.max_line_length,
+            brackets: doc
+                .matching_bracket()
+                .map(|(bracket, partner)| vec![bracket, partner])
+                .unwrap_or_default(),
+            bookmarks: doc.bookmarks.lines().to_vec(),
         }
     };
 
@@ -67,32 +99,55 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
     // We need to handle this carefully to work with borrow checker
     let mut line_data: Vec<LineRenderData> = Vec::new();
 
-    for row in 0..visible_lines {
-        let line_idx = doc_info.scroll_y + row;
+    let mut line_idx = doc_info.scroll_y;
+    while line_data.len() < visible_lines {
         if line_idx >= doc_info.line_count {
             line_data.push(LineRenderData::Empty);
             continue;
         }
 
-        // Get line content
-        let content = app
-            .active_document()
-            .and_then(|doc| doc.buffer.line(line_idx))
+        // Get line content and its screen rows
+        let Some(doc) = app.active_document() else {
+            return;
+        };
+        let content = doc
+            .buffer
+            .line(line_idx)
             .map(|s| {
                 let s = s.to_string();
                 s.trim_end_matches('\n').trim_end_matches('\r').to_string()
             })
             .unwrap_or_default();
+        // Lines inside a collapsed fold have no rows
+        let rows = doc.screen_rows(line_idx);
+        if rows.is_empty() {
+            line_idx += 1;
+            continue;
+        }
 
-        // Try to get highlighted spans
-        let highlighted_spans =
-            highlight_line_content(&content, &doc_info.filetype, &app.highlighting);
+        // Try to get highlighted spans; large files are shown plain
+        let highlighted_spans = if doc_info.large_file {
+            None
+        } else {
+            highlight_line_content(&content, &doc_info.filetype, &app.highlighting)
+        };
 
-        line_data.push(LineRenderData::Content {
-            line_idx,
-            content,
-            highlighted_spans,
-        });
+        let row_count = rows.len();
+        for (i, row) in rows.into_iter().enumerate() {
+            if line_data.len() >= visible_lines {
+                break;
+            }
+            line_data.push(LineRenderData::Content {
+                line_idx,
+                content: content.clone(),
+                highlighted_spans: highlighted_spans.clone(),
+                row,
+                continuation: i > 0,
+                last_row: i + 1 == row_count,
+                fold_marker: doc.fold_marker(line_idx),
+            });
+        }
+        line_idx += 1;
     }
 
     // Now render everything
@@ -104,6 +159,10 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
                 line_idx,
                 content,
                 highlighted_spans,
+                row,
+                continuation,
+                last_row,
+                fold_marker,
             } => {
                 let is_current_line = *line_idx == doc_info.cursor_line;
 
@@ -116,22 +175,45 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
                     Style::default().fg(theme.line_number).bg(theme.editor_bg)
                 };
 
-                let num_str = format!(
-                    "{:>width$} ",
-                    line_idx + 1,
-                    width = (doc_info.gutter_width - 1) as usize
-                );
+                // The first gutter cell marks bookmarks
+                let bookmark = if !*continuation && doc_info.bookmarks.contains(line_idx) {
+                    Span::styled("◆", Style::default().fg(theme.cursor).bg(theme.editor_bg))
+                } else {
+                    Span::styled(" ", num_style)
+                };
+
+                // Continuation rows of a wrapped line show a wrap marker instead;
+                // the last gutter cell marks fold headers
+                let num_str = if *continuation {
+                    format!(
+                        "{:>width$} ",
+                        "↪",
+                        width = (doc_info.gutter_width - 2) as usize
+                    )
+                } else {
+                    let marker = match fold_marker {
+                        Some(true) => '▸',
+                        Some(false) => '▾',
+                        None => ' ',
+                    };
+                    format!(
+                        "{:>width$}{marker}",
+                        line_idx + 1,
+                        width = (doc_info.gutter_width - 2) as usize
+                    )
+                };
 
-                let mut spans = vec![Span::styled(num_str, num_style)];
+                let mut spans = vec![bookmark, Span::styled(num_str, num_style)];
+                let cells = visible_cells(content, row, *last_row, &doc_info);
 
                 // Render with syntax highlighting if available
                 if let Some(hl_spans) = highlighted_spans {
                     render_highlighted_line(
-                        &mut spans, hl_spans, content, *line_idx, &doc_info, &theme, focused,
+                        &mut spans, hl_spans, cells, *line_idx, &doc_info, &theme, focused,
                     );
                 } else {
                     // Fallback to plain rendering
-                    render_plain_line(&mut spans, content, *line_idx, &doc_info, &theme, focused);
+                    render_plain_line(&mut spans, cells, *line_idx, &doc_info, &theme, focused);
                 }
 
                 lines.push(Line::from(spans));
@@ -142,9 +224,9 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
                 let code_style = Style::default().bg(theme.editor_bg);
 
                 let num_str = format!(
-                    "{:>width$} ",
+                    " {:>width$} ",
                     "~",
-                    width = (doc_info.gutter_width - 1) as usize
+                    width = (doc_info.gutter_width - 2) as usize
                 );
 
                 lines.push(Line::from(vec![
@@ -157,6 +239,23 @@ pub fn draw(frame: &mut Frame, app: &mut App, area: Rect, focused: bool) {
 
     let content = Paragraph::new(lines);
     frame.render_widget(content, inner);
+
+    // Shade the column past the maximum line length where nothing else is drawn
+    if let Some(col) = doc_info
+        .ruler
+        .and_then(|col| col.checked_sub(doc_info.scroll_x))
+    {
+        if col < doc_info.content_width {
+            let x = inner.x + doc_info.gutter_width + col as u16;
+            for y in inner.y..inner.y + inner.height {
+                if let Some(cell) = frame.buffer_mut().cell_mut((x, y)) {
+                    if cell.bg == theme.editor_bg {
+                        cell.set_bg(theme.line_highlight);
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Highlight a single line of content
@@ -214,7 +313,7 @@ fn highlight_line_content(
 fn render_highlighted_line(
     spans: &mut Vec<Span<'static>>,
     hl_spans: &[StyledSpan],
-    content: &str,
+    cells: Vec<(Option<usize>, String)>,
     line_idx: usize,
     info: &DocInfo,
     theme: &crate::theme::Theme,
@@ -223,39 +322,20 @@ fn render_highlighted_line(
     let is_current_line = line_idx == info.cursor_line;
 
     // Build a character-level style map from the highlighted spans
-    let chars: Vec<char> = content.chars().collect();
-    let mut char_styles: Vec<HighlightStyle> = vec![HighlightStyle::default(); chars.len()];
-
-    let mut char_idx = 0;
-    for hl_span in hl_spans {
-        for _ in hl_span.text.chars() {
-            if char_idx < char_styles.len() {
-                char_styles[char_idx] = hl_span.style;
-            }
-            char_idx += 1;
-        }
-    }
+    let char_styles: Vec<HighlightStyle> = hl_spans
+        .iter()
+        .flat_map(|hl_span| hl_span.text.chars().map(|_| hl_span.style))
+        .collect();
 
     // Render visible portion with cursor/selection overlay
-    let display_start = info.scroll_x;
-
-    for col_offset in 0..info.content_width {
-        let actual_col = display_start + col_offset;
-        let ch = if actual_col < chars.len() {
-            chars[actual_col]
-        } else {
-            ' '
-        };
-
-        let is_cursor = focused && is_current_line && actual_col == info.cursor_col;
-        let is_selected = info.selection.contains(line_idx, actual_col);
+    for (col, text) in cells {
+        let is_cursor = focused && col.is_some_and(|c| info.is_caret(line_idx, c));
+        let is_selected = col.is_some_and(|c| info.is_selected(line_idx, c));
 
         // Get base style from highlighting
-        let hl_style = if actual_col < char_styles.len() {
-            char_styles[actual_col]
-        } else {
-            HighlightStyle::default()
-        };
+        let hl_style = col
+            .and_then(|c| char_styles.get(c).copied())
+            .unwrap_or_default();
 
         // Build final style with cursor/selection/current line overlay
         let style = if is_cursor {
@@ -287,15 +367,31 @@ fn render_highlighted_line(
             }
             s
         };
+        let style = emphasize_bracket(style, col, line_idx, info, is_cursor);
+
+        spans.push(Span::styled(text, style));
+    }
+}
 
-        spans.push(Span::styled(ch.to_string(), style));
+/// Underline a matched bracket pair (not under the caret, which is drawn already)
+fn emphasize_bracket(
+    style: Style,
+    col: Option<usize>,
+    line_idx: usize,
+    info: &DocInfo,
+    is_cursor: bool,
+) -> Style {
+    if !is_cursor && col.is_some_and(|c| info.is_bracket_match(line_idx, c)) {
+        style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+    } else {
+        style
     }
 }
 
 /// Render a line without syntax highlighting (fallback)
 fn render_plain_line(
     spans: &mut Vec<Span<'static>>,
-    content: &str,
+    cells: Vec<(Option<usize>, String)>,
     line_idx: usize,
     info: &DocInfo,
     theme: &crate::theme::Theme,
@@ -303,23 +399,10 @@ fn render_plain_line(
 ) {
     let is_current_line = line_idx == info.cursor_line;
 
-    // Handle horizontal scrolling
-    let display_start = info.scroll_x;
-    let display_content: String = content
-        .chars()
-        .skip(display_start)
-        .take(info.content_width)
-        .collect();
-
-    // Pad to fill width
-    let padded_content = format!("{:<width$}", display_content, width = info.content_width);
-
-    // Determine background for each character
-    for (col_offset, ch) in padded_content.chars().enumerate() {
-        let actual_col = info.scroll_x + col_offset;
-
-        let is_cursor = focused && is_current_line && actual_col == info.cursor_col;
-        let is_selected = info.selection.contains(line_idx, actual_col);
+    // Determine background for each grapheme
+    for (col, text) in cells {
+        let is_cursor = focused && col.is_some_and(|c| info.is_caret(line_idx, c));
+        let is_selected = col.is_some_and(|c| info.is_selected(line_idx, c));
 
         let style = if is_cursor {
             Style::default().fg(theme.editor_bg).bg(theme.cursor)
@@ -330,17 +413,78 @@ fn render_plain_line(
         } else {
             Style::default().fg(theme.fg).bg(theme.editor_bg)
         };
+        let style = emphasize_bracket(style, col, line_idx, info, is_cursor);
 
-        spans.push(Span::styled(ch.to_string(), style));
+        spans.push(Span::styled(text, style));
     }
 }
 
+/// The visible part of a screen row as `(char column, text)` per drawn grapheme,
+/// each text exactly as wide as the cells it covers, padded with blanks to the
+/// content width. Blanks that stand for no position in the line have no column.
+fn visible_cells(
+    content: &str,
+    row: &VisualRow,
+    last_row: bool,
+    info: &DocInfo,
+) -> Vec<(Option<usize>, String)> {
+    let left = info.scroll_x;
+    let right = left + info.content_width;
+
+    let mut cells = Vec::with_capacity(info.content_width);
+    let mut x = left;
+    if row.indent > left {
+        // Continuation indent of a wrapped row
+        x = row.indent.min(right);
+        cells.push((None, " ".repeat(x - left)));
+    }
+
+    let mut row_end = row.indent;
+    let graphemes = layout_line(content, info.tab_width);
+    for g in graphemes
+        .iter()
+        .filter(|g| g.col >= row.start_col && g.col < row.end_col)
+    {
+        let start = row.x_of(g);
+        let end = start + g.width;
+        row_end = end;
+        if end <= left {
+            continue;
+        }
+        if start >= right {
+            break;
+        }
+        if start < left || end > right {
+            // Cut by the edge of the view: draw the visible part blank
+            cells.push((Some(g.col), " ".repeat(end.min(right) - start.max(left))));
+        } else {
+            cells.push((Some(g.col), g.display_text()));
+        }
+        x = end.min(right);
+    }
+
+    // Cells past the end of the line continue the column count; a wrapped row
+    // ends where the next one begins
+    for x in x..right {
+        let col = last_row.then(|| row.end_col + x - row_end);
+        cells.push((col, " ".to_string()));
+    }
+    cells
+}
+
 /// Line render data
 enum LineRenderData {
     Content {
         line_idx: usize,
         content: String,
         highlighted_spans: Option<Vec<StyledSpan>>,
+        /// Part of the line shown on this screen row
+        row: VisualRow,
+        /// Whether this is a wrapped continuation of the line
+        continuation: bool,
+        last_row: bool,
+        /// Fold header marker: Some(collapsed) if a fold region starts here
+        fold_marker: Option<bool>,
     },
     Empty,
 }
@@ -349,13 +493,46 @@ enum LineRenderData {
 struct DocInfo {
     line_count: usize,
     scroll_y: usize,
+    /// Horizontal scroll in display columns
     scroll_x: usize,
     cursor_line: usize,
-    cursor_col: usize,
-    selection: crate::editor::Selection,
+    selections: Vec<crate::editor::Selection>,
+    carets: Vec<(usize, usize)>,
+    /// Rows of a block selection as (line, start_col, end_col)
+    block_spans: Vec<(usize, usize, usize)>,
     gutter_width: u16,
     content_width: usize,
+    tab_width: usize,
     filetype: String,
+    /// Large-file mode: no syntax highlighting
+    large_file: bool,
+    /// Column of the long-line marker (max_line_length from .editorconfig)
+    ruler: Option<usize>,
+    /// The bracket next to the caret and its match
+    brackets: Vec<(usize, usize)>,
+    /// Bookmarked lines, sorted
+    bookmarks: Vec<usize>,
+}
+
+impl DocInfo {
+    /// Whether any caret sits at this position
+    fn is_caret(&self, line: usize, col: usize) -> bool {
+        self.carets.contains(&(line, col))
+    }
+
+    /// Whether this position holds the bracket at the caret or its match
+    fn is_bracket_match(&self, line: usize, col: usize) -> bool {
+        self.brackets.contains(&(line, col))
+    }
+
+    /// Whether any selection covers this position
+    fn is_selected(&self, line: usize, col: usize) -> bool {
+        self.selections.iter().any(|s| s.contains(line, col))
+            || self
+                .block_spans
+                .iter()
+                .any(|&(l, start, end)| l == line && col >= start && col < end)
+    }
 }
 
 /// Calculate the width needed for line numbers
@@ -365,12 +542,55 @@ fn calculate_gutter_width(line_count: usize) -> u16 {
     } else {
         (line_count as f64).log10().floor() as u16 + 1
     };
-    // At least 4 digits, plus 1 for space
-    digits.max(4) + 1
+    // At least 4 digits, plus the bookmark and fold marker cells
+    digits.max(4) + 2
 }
 
 /// Get the document position from screen coordinates
 pub fn position_from_screen(app: &App, x: u16, y: u16) -> Option<(usize, usize)> {
+    let (area, gutter_width) = text_area(app, x, y)?;
+    let doc = app.active_document()?;
+
+    let screen_row = (y - area.y) as usize;
+    let screen_col = (x - area.x - gutter_width) as usize;
+
+    Some(doc.position_at_screen(screen_row, screen_col))
+}
+
+/// Get the (line, display column) under screen coordinates, without clamping
+/// to the document
+pub fn cell_from_screen(app: &App, x: u16, y: u16) -> Option<(usize, usize)> {
+    let (area, gutter_width) = text_area(app, x, y)?;
+    let doc = app.active_document()?;
+
+    let screen_row = (y - area.y) as usize;
+    let screen_col = (x - area.x - gutter_width) as usize;
+
+    if doc.wrap_width.is_some() || doc.has_folds() {
+        // Wrapped or folded rows do not line up with lines; use the position
+        let (line, col) = doc.position_at_screen(screen_row, screen_col);
+        return Some((line, doc.buffer.visual_col(line, col, doc.tab_width)));
+    }
+    Some((doc.scroll_y + screen_row, doc.scroll_x + screen_col))
+}
+
+/// Get the line whose gutter is at screen coordinates, and whether the fold
+/// marker cell was hit (clicks elsewhere in the gutter toggle bookmarks)
+pub fn gutter_line_from_screen(app: &App, x: u16, y: u16) -> Option<(usize, bool)> {
+    let area = app.editor_area?;
+    let doc = app.active_document()?;
+    let gutter_width = calculate_gutter_width(doc.line_count());
+
+    if x < area.x || x >= area.x + gutter_width || y < area.y || y >= area.y + area.height {
+        return None;
+    }
+
+    let (line, _) = doc.position_at_screen((y - area.y) as usize, 0);
+    Some((line, x == area.x + gutter_width - 1))
+}
+
+/// The editor area and gutter width, if screen coordinates fall on the text
+fn text_area(app: &App, x: u16, y: u16) -> Option<(Rect, u16)> {
     let area = app.editor_area?;
 
     if x < area.x || x >= area.x + area.width || y < area.y || y >= area.y + area.height {
@@ -385,15 +605,5 @@ pub fn position_from_screen(app: &App, x: u16, y: u16) -> Option<(usize, usize)>
         return None;
     }
 
-    let screen_row = (y - area.y) as usize;
-    let screen_col = (x - area.x - gutter_width) as usize;
-
-    let line = doc.scroll_y + screen_row;
-    let col = doc.scroll_x + screen_col;
-
-    // Clamp to valid positions
-    let line = line.min(doc.line_count().saturating_sub(1));
-    let col = col.min(doc.line_len(line));
-
-    Some((line, col))
+    Some((area, gutter_width))
 }
@@ -0,0 +1,77 @@
+use crate::app::App;
+use crate::editor::BYTES_PER_ROW;
+use ratatui::{prelude::*, widgets::Paragraph};
+
+/// Draw a binary document as rows of offset, hex bytes and ASCII
+pub fn draw(frame: &mut Frame, app: &mut App, inner: Rect, focused: bool) {
+    let theme = app.theme.clone();
+    let Some(hex) = app
+        .documents
+        .get_mut(app.active_doc)
+        .and_then(|doc| doc.hex.as_mut())
+    else {
+        return;
+    };
+    let visible_rows = inner.height as usize;
+    hex.ensure_cursor_visible(visible_rows);
+
+    let normal = Style::default().fg(theme.fg).bg(theme.editor_bg);
+    let offset_style = Style::default().fg(theme.line_number).bg(theme.editor_bg);
+    let cursor_style = Style::default().fg(theme.editor_bg).bg(theme.cursor);
+    // The byte's cell in the column not being typed in
+    let shadow_style = Style::default().fg(theme.fg).bg(theme.selection_bg);
+
+    let mut lines = Vec::with_capacity(visible_rows);
+    for row in hex.scroll_row..hex.scroll_row + visible_rows {
+        let start = row * BYTES_PER_ROW;
+        if start >= hex.bytes.len() && row > 0 {
+            lines.push(Line::from(Span::styled("~", offset_style)));
+            continue;
+        }
+        let end = (start + BYTES_PER_ROW).min(hex.bytes.len());
+        let mut spans = vec![Span::styled(format!("{:08X}  ", start), offset_style)];
+
+        for i in 0..BYTES_PER_ROW {
+            let offset = start + i;
+            let text = match hex.bytes.get(offset) {
+                Some(byte) if offset < end => format!("{:02X}", byte),
+                _ => "  ".to_string(),
+            };
+            let style = match offset == hex.cursor && offset < end {
+                true if focused && !hex.ascii => cursor_style,
+                true => shadow_style,
+                false => normal,
+            };
+            spans.push(Span::styled(text, style));
+            spans.push(Span::styled(
+                if i == BYTES_PER_ROW / 2 - 1 {
+                    "  "
+                } else {
+                    " "
+                },
+                normal,
+            ));
+        }
+
+        spans.push(Span::styled(" |", offset_style));
+        for offset in start..end {
+            let byte = hex.bytes[offset];
+            let ch = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            let style = match offset == hex.cursor {
+                true if focused && hex.ascii => cursor_style,
+                true => shadow_style,
+                false => normal,
+            };
+            spans.push(Span::styled(ch.to_string(), style));
+        }
+        spans.push(Span::styled("|", offset_style));
+
+        lines.push(Line::from(spans));
+    }
+
+    frame.render_widget(Paragraph::new(lines).style(normal), inner);
+}
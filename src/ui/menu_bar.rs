@@ -1,4 +1,5 @@
 use crate::app::App;
+use crate::editor::TextCase;
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Clear, Paragraph},
@@ -33,6 +34,27 @@ pub enum MenuAction {
     Copy,
     Paste,
     SelectAll,
+    AddCursorAbove,
+    AddCursorBelow,
+    AddNextOccurrence,
+    ToggleLineComment,
+    ToggleBlockComment,
+    IndentLines,
+    UnindentLines,
+
+    // Edit ▸ Format submenu
+    SortLines,
+    RemoveDuplicateLines,
+    ReverseLines,
+    JoinLines,
+    ChangeCase(TextCase),
+    TransposeChars,
+
+    // Edit ▸ Macros submenu
+    ToggleMacroRecording,
+    PlayLastMacro,
+    PlayMacro,
+    SaveMacro,
 
     // Search menu
     Find,
@@ -40,15 +62,38 @@ pub enum MenuAction {
     FindPrevious,
     Replace,
     GoToLine,
+    GoToMatchingBrace,
+    SelectToMatchingBrace,
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
 
     // View menu
     ToggleSidebar,
     ToggleEditor,
     ToggleTerminal,
+    ToggleWordWrap,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
     FocusEditor,
     FocusFileTree,
     FocusTerminal,
 
+    // Document menu
+    SetEncoding,
+    ReopenWithEncoding,
+    ConvertToLf,
+    ConvertToCrLf,
+    ConvertToCr,
+    StripTrailingWhitespace,
+    EnsureFinalNewline,
+    Indentation,
+    IndentToSpaces,
+    IndentToTabs,
+    ReopenAsHex,
+
     // Terminal menu
     NewTerminal,
     CloseTerminal,
@@ -60,6 +105,9 @@ pub enum MenuAction {
 
     // Separator (not a real action)
     Separator,
+
+    /// Opens `SUBMENUS[i]` beside the item
+    Submenu(usize),
 }
 
 /// The available menus
@@ -186,6 +234,72 @@ pub const MENUS: &[(&str, &[MenuItem])] = &[
                 action: MenuAction::SelectAll,
                 enabled: true,
             },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Add Cursor Above",
+                shortcut: Some("Ctrl+Alt+Up"),
+                action: MenuAction::AddCursorAbove,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Add Cursor Below",
+                shortcut: Some("Ctrl+Alt+Down"),
+                action: MenuAction::AddCursorBelow,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Add Next Occurrence",
+                shortcut: Some("Ctrl+Alt+D"),
+                action: MenuAction::AddNextOccurrence,
+                enabled: true,
+            },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Toggle Line Comment",
+                shortcut: Some("Ctrl+E"),
+                action: MenuAction::ToggleLineComment,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Toggle Block Comment",
+                shortcut: Some("Ctrl+Shift+E"),
+                action: MenuAction::ToggleBlockComment,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Indent Lines",
+                shortcut: Some("Ctrl+I"),
+                action: MenuAction::IndentLines,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Unindent Lines",
+                shortcut: Some("Ctrl+U"),
+                action: MenuAction::UnindentLines,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Format",
+                shortcut: Some("▸"),
+                action: MenuAction::Submenu(0),
+                enabled: true,
+            },
+            MenuItem {
+                label: "Macros",
+                shortcut: Some("▸"),
+                action: MenuAction::Submenu(1),
+                enabled: true,
+            },
         ],
     ),
     (
@@ -227,6 +341,42 @@ pub const MENUS: &[(&str, &[MenuItem])] = &[
                 action: MenuAction::GoToLine,
                 enabled: true,
             },
+            MenuItem {
+                label: "Go to Matching Bracket",
+                shortcut: Some("Ctrl+B"),
+                action: MenuAction::GoToMatchingBrace,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Select to Matching Bracket",
+                shortcut: Some("Ctrl+Alt+B"),
+                action: MenuAction::SelectToMatchingBrace,
+                enabled: true,
+            },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Toggle Bookmark",
+                shortcut: Some("Ctrl+M"),
+                action: MenuAction::ToggleBookmark,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Next Bookmark",
+                shortcut: Some("Ctrl+."),
+                action: MenuAction::NextBookmark,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Previous Bookmark",
+                shortcut: Some("Ctrl+,"),
+                action: MenuAction::PrevBookmark,
+                enabled: true,
+            },
         ],
     ),
     (
@@ -234,13 +384,13 @@ pub const MENUS: &[(&str, &[MenuItem])] = &[
         &[
             MenuItem {
                 label: "Toggle Sidebar",
-                shortcut: Some("Ctrl+B"),
+                shortcut: Some("Ctrl+Shift+B"),
                 action: MenuAction::ToggleSidebar,
                 enabled: true,
             },
             MenuItem {
                 label: "Toggle Editor",
-                shortcut: Some("Ctrl+E"),
+                shortcut: None,
                 action: MenuAction::ToggleEditor,
                 enabled: true,
             },
@@ -250,6 +400,42 @@ pub const MENUS: &[(&str, &[MenuItem])] = &[
                 action: MenuAction::ToggleTerminal,
                 enabled: true,
             },
+            MenuItem {
+                label: "Word Wrap",
+                shortcut: Some("Alt+Z"),
+                action: MenuAction::ToggleWordWrap,
+                enabled: true,
+            },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Fold",
+                shortcut: Some("Alt+-"),
+                action: MenuAction::Fold,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Unfold",
+                shortcut: Some("Alt+="),
+                action: MenuAction::Unfold,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Fold All",
+                shortcut: Some("Alt+Shift+-"),
+                action: MenuAction::FoldAll,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Unfold All",
+                shortcut: Some("Alt+Shift+="),
+                action: MenuAction::UnfoldAll,
+                enabled: true,
+            },
             MenuItem {
                 label: "─────────",
                 shortcut: None,
@@ -276,6 +462,95 @@ pub const MENUS: &[(&str, &[MenuItem])] = &[
             },
         ],
     ),
+    (
+        "Document",
+        &[
+            MenuItem {
+                label: "Set Encoding...",
+                shortcut: None,
+                action: MenuAction::SetEncoding,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Reopen with Encoding...",
+                shortcut: None,
+                action: MenuAction::ReopenWithEncoding,
+                enabled: true,
+            },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Convert Line Endings to LF",
+                shortcut: None,
+                action: MenuAction::ConvertToLf,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Convert Line Endings to CRLF",
+                shortcut: None,
+                action: MenuAction::ConvertToCrLf,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Convert Line Endings to CR",
+                shortcut: None,
+                action: MenuAction::ConvertToCr,
+                enabled: true,
+            },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Strip Trailing Whitespace",
+                shortcut: None,
+                action: MenuAction::StripTrailingWhitespace,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Ensure Final Newline",
+                shortcut: None,
+                action: MenuAction::EnsureFinalNewline,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Indentation...",
+                shortcut: None,
+                action: MenuAction::Indentation,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Convert Indentation to Spaces",
+                shortcut: None,
+                action: MenuAction::IndentToSpaces,
+                enabled: true,
+            },
+            MenuItem {
+                label: "Convert Indentation to Tabs",
+                shortcut: None,
+                action: MenuAction::IndentToTabs,
+                enabled: true,
+            },
+            MenuItem {
+                label: "─────────",
+                shortcut: None,
+                action: MenuAction::Separator,
+                enabled: false,
+            },
+            MenuItem {
+                label: "Reopen in Hex View",
+                shortcut: None,
+                action: MenuAction::ReopenAsHex,
+                enabled: true,
+            },
+        ],
+    ),
     (
         "Terminal",
         &[
@@ -322,6 +597,112 @@ pub const MENUS: &[(&str, &[MenuItem])] = &[
     ),
 ];
 
+/// Submenus opened by `MenuAction::Submenu`
+pub const SUBMENUS: &[&[MenuItem]] = &[
+    // Edit ▸ Format
+    &[
+        MenuItem {
+            label: "Sort Lines",
+            shortcut: Some("F9"),
+            action: MenuAction::SortLines,
+            enabled: true,
+        },
+        MenuItem {
+            label: "Reverse Lines",
+            shortcut: Some("Shift+F9"),
+            action: MenuAction::ReverseLines,
+            enabled: true,
+        },
+        MenuItem {
+            label: "Remove Duplicate Lines",
+            shortcut: Some("Ctrl+F9"),
+            action: MenuAction::RemoveDuplicateLines,
+            enabled: true,
+        },
+        MenuItem {
+            label: "Join Lines",
+            shortcut: Some("Ctrl+J"),
+            action: MenuAction::JoinLines,
+            enabled: true,
+        },
+        MenuItem {
+            label: "─────────",
+            shortcut: None,
+            action: MenuAction::Separator,
+            enabled: false,
+        },
+        MenuItem {
+            label: "UPPER CASE",
+            shortcut: Some("Alt+U"),
+            action: MenuAction::ChangeCase(TextCase::Upper),
+            enabled: true,
+        },
+        MenuItem {
+            label: "lower case",
+            shortcut: Some("Alt+L"),
+            action: MenuAction::ChangeCase(TextCase::Lower),
+            enabled: true,
+        },
+        MenuItem {
+            label: "Title Case",
+            shortcut: Some("Alt+C"),
+            action: MenuAction::ChangeCase(TextCase::Title),
+            enabled: true,
+        },
+        MenuItem {
+            label: "snake_case",
+            shortcut: Some("Alt+Shift+S"),
+            action: MenuAction::ChangeCase(TextCase::Snake),
+            enabled: true,
+        },
+        MenuItem {
+            label: "camelCase",
+            shortcut: Some("Alt+Shift+C"),
+            action: MenuAction::ChangeCase(TextCase::Camel),
+            enabled: true,
+        },
+        MenuItem {
+            label: "─────────",
+            shortcut: None,
+            action: MenuAction::Separator,
+            enabled: false,
+        },
+        MenuItem {
+            label: "Transpose Characters",
+            shortcut: Some("Alt+T"),
+            action: MenuAction::TransposeChars,
+            enabled: true,
+        },
+    ],
+    // Edit ▸ Macros
+    &[
+        MenuItem {
+            label: "Start/Stop Recording",
+            shortcut: Some("F7"),
+            action: MenuAction::ToggleMacroRecording,
+            enabled: true,
+        },
+        MenuItem {
+            label: "Play Last Macro",
+            shortcut: Some("F8"),
+            action: MenuAction::PlayLastMacro,
+            enabled: true,
+        },
+        MenuItem {
+            label: "Play Macro...",
+            shortcut: Some("Shift+F8"),
+            action: MenuAction::PlayMacro,
+            enabled: true,
+        },
+        MenuItem {
+            label: "Save Last Macro...",
+            shortcut: Some("Ctrl+F8"),
+            action: MenuAction::SaveMacro,
+            enabled: true,
+        },
+    ],
+];
+
 /// Draw just the menu bar (not the dropdown)
 pub fn draw_bar(frame: &mut Frame, app: &mut App, area: Rect) {
     let bg_style = Style::default()
@@ -368,7 +749,8 @@ pub fn draw_bar(frame: &mut Frame, app: &mut App, area: Rect) {
 }
 
 /// Draw the dropdown menu (call this AFTER drawing all other content)
-pub fn draw_dropdown(frame: &mut Frame, app: &App, menu_bar_area: Rect) {
+pub fn draw_dropdown(frame: &mut Frame, app: &mut App, menu_bar_area: Rect) {
+    app.submenu_area = None;
     let Some(menu_idx) = app.menu_open else {
         return;
     };
@@ -384,7 +766,40 @@ pub fn draw_dropdown(frame: &mut Frame, app: &App, menu_bar_area: Rect) {
         .map(|(start, _, _)| *start)
         .unwrap_or(0);
 
-    // Calculate dropdown dimensions
+    // Get total screen size
+    let screen_width = frame.area().width;
+
+    let (width, height) = dropdown_size(items);
+    let dropdown_area = Rect {
+        x: x_pos.min(screen_width.saturating_sub(width)),
+        y: menu_bar_area.y + 1,
+        width,
+        height,
+    };
+    draw_items(frame, app, dropdown_area, items, app.menu_selected);
+
+    // An open submenu unfolds beside its item, or to the left without room
+    let Some((selected, submenu)) = app.open_submenu() else {
+        return;
+    };
+    let (width, height) = dropdown_size(submenu);
+    let right = dropdown_area.x + dropdown_area.width;
+    let submenu_area = Rect {
+        x: if right + width <= screen_width {
+            right
+        } else {
+            dropdown_area.x.saturating_sub(width)
+        },
+        y: dropdown_area.y + selected as u16,
+        width,
+        height,
+    };
+    draw_items(frame, app, submenu_area, submenu, app.submenu_selected);
+    app.submenu_area = Some(submenu_area);
+}
+
+/// Width and height of a dropdown showing `items`, borders included
+fn dropdown_size(items: &[MenuItem]) -> (u16, u16) {
     let max_label_width = items
         .iter()
         .map(|item| item.label.len())
@@ -397,21 +812,21 @@ pub fn draw_dropdown(frame: &mut Frame, app: &App, menu_bar_area: Rect) {
         .max()
         .unwrap_or(0);
 
-    let dropdown_width = (max_label_width + max_shortcut_width + 6) as u16; // padding + borders
-    let dropdown_height = items.len() as u16 + 2; // +2 for borders
-
-    // Get total screen size
-    let screen_width = frame.area().width;
-
-    let dropdown_area = Rect {
-        x: x_pos.min(screen_width.saturating_sub(dropdown_width)),
-        y: menu_bar_area.y + 1,
-        width: dropdown_width,
-        height: dropdown_height,
-    };
+    let width = (max_label_width + max_shortcut_width + 6) as u16; // padding + borders
+    let height = items.len() as u16 + 2; // +2 for borders
+    (width, height)
+}
 
+/// Draw a bordered list of menu items with `selected` highlighted
+fn draw_items(
+    frame: &mut Frame,
+    app: &App,
+    area: Rect,
+    items: &[MenuItem],
+    selected: Option<usize>,
+) {
     // Clear the area behind the dropdown
-    frame.render_widget(Clear, dropdown_area);
+    frame.render_widget(Clear, area);
 
     // Draw dropdown background/border
     let block = Block::default()
@@ -419,8 +834,8 @@ pub fn draw_dropdown(frame: &mut Frame, app: &App, menu_bar_area: Rect) {
         .border_style(Style::default().fg(app.theme.border))
         .style(Style::default().bg(app.theme.menubar_bg));
 
-    let inner = block.inner(dropdown_area);
-    frame.render_widget(block, dropdown_area);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
 
     // Draw menu items
     let normal_style = Style::default()
@@ -437,7 +852,7 @@ pub fn draw_dropdown(frame: &mut Frame, app: &App, menu_bar_area: Rect) {
     let inner_width = inner.width as usize;
 
     for (i, item) in items.iter().enumerate() {
-        let is_selected = app.menu_selected == Some(i);
+        let is_selected = selected == Some(i);
 
         let style = if item.action == MenuAction::Separator {
             disabled_style
@@ -451,7 +866,7 @@ pub fn draw_dropdown(frame: &mut Frame, app: &App, menu_bar_area: Rect) {
 
         let shortcut_str = item.shortcut.unwrap_or("");
         let label_len = item.label.len();
-        let shortcut_len = shortcut_str.len();
+        let shortcut_len = shortcut_str.chars().count();
         let padding = inner_width.saturating_sub(label_len + shortcut_len + 2);
 
         let line_text = format!(
@@ -485,12 +900,15 @@ pub fn menu_at_position(app: &App, x: u16) -> Option<usize> {
 /// Get the menu item at a given y position in the dropdown (relative to dropdown top)
 pub fn item_at_position(menu_idx: usize, y: usize) -> Option<usize> {
     if let Some((_, items)) = MENUS.get(menu_idx) {
-        if y < items.len() {
-            // Skip separators
-            if items[y].action != MenuAction::Separator && items[y].enabled {
-                return Some(y);
-            }
-        }
+        return selectable_item(items, y);
     }
     None
 }
+
+/// `items[y]` if it can be chosen (not a separator or disabled)
+pub fn selectable_item(items: &[MenuItem], y: usize) -> Option<usize> {
+    items
+        .get(y)
+        .filter(|item| item.action != MenuAction::Separator && item.enabled)
+        .map(|_| y)
+}
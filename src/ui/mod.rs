@@ -1,6 +1,7 @@
 pub mod dialog;
-mod editor;
+pub mod editor;
 mod file_tree;
+mod hex_view;
 mod layout;
 pub mod menu_bar;
 mod search_bar;
@@ -1,8 +1,9 @@
 use crate::app::App;
+use crate::editor::IndentStyle;
 use ratatui::{prelude::*, widgets::Paragraph};
 
 /// Draw the status bar at the bottom of the screen
-pub fn draw(frame: &mut Frame, app: &App, area: Rect) {
+pub fn draw(frame: &mut Frame, app: &mut App, area: Rect) {
     let style = Style::default()
         .fg(app.theme.statusbar_fg)
         .bg(app.theme.statusbar_bg);
@@ -22,31 +23,93 @@ pub fn draw(frame: &mut Frame, app: &App, area: Rect) {
                 doc.cursor.col + 1, // 1-based for display
                 sel_len,
                 if doc.insert_mode { "INS" } else { "OVR" },
-                doc.line_ending.display_name(),
-                doc.encoding.as_str(),
+                if doc.mixed_line_endings {
+                    format!("{} (mixed)", doc.line_ending.display_name())
+                } else {
+                    doc.line_ending.display_name().to_string()
+                },
+                doc.encoding.display_name(),
                 doc.filetype.as_str(),
             )
         } else {
-            (1, 1, 1, 0, "INS", "LF", "UTF-8", "Plain Text")
+            (
+                1,
+                1,
+                1,
+                0,
+                "INS",
+                "LF".to_string(),
+                "UTF-8".to_string(),
+                "Plain Text",
+            )
         };
 
-    let indent_mode = "SP"; // Spaces (we hardcoded 4 spaces for tabs)
+    // Effective indentation (clickable), then the line length limit and
+    // where the settings came from when an .editorconfig applies
+    let (indent_mode, indent_extra) = match app.active_document() {
+        Some(doc) => {
+            let mode = match doc.indent_style {
+                IndentStyle::Tabs => "TAB".to_string(),
+                IndentStyle::Spaces => format!("SP {}", doc.indent_width),
+            };
+            let mut extra = String::new();
+            if let Some(max) = doc.editorconfig.max_line_length {
+                extra.push_str(&format!(" | max {}", max));
+            }
+            if !doc.editorconfig.is_empty() {
+                extra.push_str(" (editorconfig)");
+            }
+            (mode, extra)
+        }
+        None => ("SP 4".to_string(), String::new()),
+    };
     let modified = app.active_document().map(|d| d.modified).unwrap_or(false);
     let mod_indicator = if modified { " [+]" } else { "" };
+    let mode_indicator = match app.active_document() {
+        Some(doc) if doc.read_only => " | RO",
+        Some(doc) if doc.large_file => " | LARGE",
+        _ => "",
+    };
 
-    let left_status = format!(
-        " line: {}/{} | col: {} | sel: {} | {} | {} | EOL: {} | {} | {}{}",
-        line,
-        total_lines,
-        col,
-        selection_len,
-        insert_mode,
-        indent_mode,
-        eol,
-        encoding,
-        filetype,
-        mod_indicator
-    );
+    let mut indent_indicator = None;
+    let left_status = match app.active_document().and_then(|doc| doc.hex.as_ref()) {
+        // Binary documents show the byte offset instead of line and column
+        Some(hex) => format!(
+            " offset: 0x{:X}/0x{:X} | {} | OVR | {}{}{}",
+            hex.cursor,
+            hex.bytes.len(),
+            if hex.ascii { "ASCII" } else { "HEX" },
+            filetype,
+            mode_indicator,
+            mod_indicator
+        ),
+        None => {
+            let before = format!(
+                " line: {}/{} | col: {} | sel: {} | {} | ",
+                line, total_lines, col, selection_len, insert_mode,
+            );
+            // Remember where the indentation is for clicks
+            indent_indicator = Some(Rect {
+                x: area.x + before.len() as u16,
+                y: area.y,
+                width: indent_mode.len() as u16,
+                height: 1,
+            });
+            format!(
+                "{}{}{} | EOL: {} | {} | {}{}{}",
+                before,
+                indent_mode,
+                indent_extra,
+                eol,
+                encoding,
+                filetype,
+                mode_indicator,
+                mod_indicator
+            )
+        }
+    };
+
+    app.indent_indicator = indent_indicator;
 
     // For scope, we could show function name, but that requires parsing
     // For now, just show the focused pane
@@ -55,7 +118,16 @@ pub fn draw(frame: &mut Frame, app: &App, area: Rect) {
         crate::ui::Pane::Editor => "Editor",
         crate::ui::Pane::Terminal => "Terminal",
     };
-    let right_status = format!(" {} ", pane_name);
+    // Background loads take the place of the pane name
+    let recording = if app.macros.is_recording() {
+        "REC | "
+    } else {
+        ""
+    };
+    let right_status = match app.loads.first() {
+        Some(load) => format!(" Loading {}... {}% ", load.title(), load.progress()),
+        None => format!(" {}{} ", recording, pane_name),
+    };
 
     // Calculate padding
     let total_len = left_status.len() + right_status.len();
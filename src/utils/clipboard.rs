@@ -3,12 +3,17 @@ use anyhow::Result;
 /// Clipboard operations
 pub struct Clipboard {
     clipboard: Option<arboard::Clipboard>,
+    /// Text most recently copied from a block selection
+    last_block: Option<String>,
 }
 
 impl Clipboard {
     pub fn new() -> Self {
         let clipboard = arboard::Clipboard::new().ok();
-        Self { clipboard }
+        Self {
+            clipboard,
+            last_block: None,
+        }
     }
 
     /// Get text from clipboard
@@ -21,6 +26,7 @@ impl Clipboard {
 
     /// Set text to clipboard
     pub fn set_text(&mut self, text: &str) -> Result<()> {
+        self.last_block = None;
         match &mut self.clipboard {
             Some(cb) => {
                 cb.set_text(text)?;
@@ -29,6 +35,19 @@ impl Clipboard {
             None => anyhow::bail!("Clipboard not available"),
         }
     }
+
+    /// Set text copied from a block selection, remembering it so that it can be
+    /// pasted back as a block
+    pub fn set_block_text(&mut self, text: &str) -> Result<()> {
+        self.set_text(text)?;
+        self.last_block = Some(text.to_string());
+        Ok(())
+    }
+
+    /// Whether `text` (as read back from the clipboard) was copied from a block selection
+    pub fn is_block_text(&self, text: &str) -> bool {
+        self.last_block.as_deref() == Some(text)
+    }
 }
 
 impl Default for Clipboard {
@@ -0,0 +1,138 @@
+//! Line-based diff for showing how two versions of a text differ
+
+/// One line of a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// Present in both versions
+    Same(String),
+    /// Only in the old version
+    Removed(String),
+    /// Only in the new version
+    Added(String),
+}
+
+/// Above this many line pairs the changed middle is shown as a whole
+/// replacement instead of being matched line by line
+const MAX_TABLE: usize = 4_000_000;
+
+/// Diff two texts line by line (longest common subsequence after trimming the
+/// common start and end)
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut diff: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|l| DiffLine::Same(l.to_string()))
+        .collect();
+
+    if a.len() * b.len() > MAX_TABLE {
+        diff.extend(a.iter().map(|l| DiffLine::Removed(l.to_string())));
+        diff.extend(b.iter().map(|l| DiffLine::Added(l.to_string())));
+    } else {
+        // lcs[i][j] = common subsequence length of a[i..] and b[j..]
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                diff.push(DiffLine::Same(a[i].to_string()));
+                i += 1;
+                j += 1;
+            } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                diff.push(DiffLine::Removed(a[i].to_string()));
+                i += 1;
+            } else {
+                diff.push(DiffLine::Added(b[j].to_string()));
+                j += 1;
+            }
+        }
+    }
+
+    diff.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|l| DiffLine::Same(l.to_string())),
+    );
+    diff
+}
+
+/// Diff as display lines prefixed with ` `, `-` or `+`, keeping `context`
+/// unchanged lines around each change and `...` where lines were skipped
+pub fn unified(old: &str, new: &str, context: usize) -> Vec<String> {
+    let diff = diff_lines(old, new);
+
+    // Lines within `context` of a change, each marked once
+    let mut near_change = vec![false; diff.len()];
+    let mut marked_to = 0;
+    for (c, _) in diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+    {
+        let end = (c + context + 1).min(diff.len());
+        near_change[c.saturating_sub(context).max(marked_to)..end].fill(true);
+        marked_to = end;
+    }
+
+    let mut out = Vec::new();
+    let mut last_shown = None;
+    for (i, line) in diff.iter().enumerate() {
+        if !near_change[i] {
+            continue;
+        }
+        if last_shown.is_some_and(|last| last + 1 < i) || (last_shown.is_none() && i > 0) {
+            out.push("...".to_string());
+        }
+        out.push(match line {
+            DiffLine::Same(text) => format!(" {}", text),
+            DiffLine::Removed(text) => format!("-{}", text),
+            DiffLine::Added(text) => format!("+{}", text),
+        });
+        last_shown = Some(i);
+    }
+    if last_shown.is_some_and(|last| last + 1 < diff.len()) {
+        out.push("...".to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\n";
+        let new = "a\nb\nc\nD\ne\nf\n";
+        assert_eq!(
+            unified(old, new, 1),
+            vec!["...", " c", "-d", "+D", " e", "..."]
+        );
+        assert!(unified(old, old, 3).is_empty());
+
+        let new = "A\nb\nc\nd\ne\nF\n";
+        assert_eq!(
+            unified(old, new, 1),
+            vec!["-a", "+A", " b", "...", " e", "-f", "+F"]
+        );
+    }
+}
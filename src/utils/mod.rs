@@ -1 +1,3 @@
 pub mod clipboard;
+pub mod diff;
+pub mod fs;